@@ -0,0 +1,54 @@
+use std::fmt::Display;
+
+use crate::{style, Status};
+
+/// A task running in the background, created by [`background`], which is
+/// cancelled if it is dropped before it is ended.
+#[derive(Debug)]
+pub struct Background {
+	id: u64,
+	message: String,
+	ended: bool,
+}
+
+/// Starts a task that runs in the background, such as uploading artifacts
+/// while the rest of a build goes on. It is nested under no task, nothing
+/// is nested under it, and it is never ended by the ending macros, so it
+/// can outlive the tree of the thread that started it.
+///
+/// In the interactive format, each background task is shown on a row of
+/// its own at the bottom of the screen, as in `● uploading artifacts`,
+/// which stays in place while the tree scrolls above it. How it ended is
+/// printed beneath the tree once it has. Other formats print its start and
+/// end as they would any other task's.
+pub fn background(message: impl Display) -> Background {
+	let message = message.to_string();
+
+	Background {
+		id: crate::begin_background(message.clone()),
+		message,
+		ended: false,
+	}
+}
+
+impl Background {
+	/// Shows `note` after the task's message, such as how far along it is.
+	pub fn note(&self, note: impl Display) {
+		crate::note_of(self.id, style::clean(&note.to_string()));
+	}
+
+	/// Ends the task with `status` and `message`, returning the status.
+	pub fn end(mut self, status: Status, message: impl Display) -> Status {
+		self.ended = true;
+		crate::end_task_by_id(self.id, status, style::clean(&message.to_string()));
+		status
+	}
+}
+
+impl Drop for Background {
+	fn drop(&mut self) {
+		if !self.ended {
+			crate::end_task_by_id(self.id, Status::Cancel, style::clean(&self.message));
+		}
+	}
+}
@@ -0,0 +1,326 @@
+use std::{
+	collections::HashSet,
+	io::{self, IsTerminal, Stdout, Write},
+};
+
+use crossterm::{
+	cursor,
+	event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+	execute, queue,
+	style::{Attribute, Print, SetAttribute},
+	terminal::{self, ClearType},
+};
+
+use crate::{format, locale, output, style, time, Record, Session, Status};
+
+/// One row of the browser, which is either a task or a line shown
+/// beneath an expanded one.
+enum Row<'a> {
+	// the task's index among its siblings, and theirs, outermost first
+	Task { path: Vec<usize>, record: &'a Record },
+	Line { path: Vec<usize>, text: &'a str, hint: bool },
+}
+
+impl Row<'_> {
+	fn path(&self) -> &[usize] {
+		match self {
+			Self::Task { path, .. } | Self::Line { path, .. } => path,
+		}
+	}
+}
+
+/// The state of the browser between keys.
+struct Browser<'a> {
+	session: &'a Session,
+	// the paths of the tasks whose subtasks and lines are shown
+	expanded: HashSet<Vec<usize>>,
+	failures: bool,
+	selected: usize,
+	// the first row in view
+	top: usize,
+}
+
+/// Puts the terminal back as it was when the browser closes, however
+/// it closes.
+struct Restore;
+
+impl Drop for Restore {
+	fn drop(&mut self) {
+		// output is best effort, just as it is while drawing the tree
+		_ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+		_ = terminal::disable_raw_mode();
+		output::unmute();
+	}
+}
+
+/// Returns whether a task failed, or any task nested under it did.
+fn failed(record: &Record) -> bool {
+	record.status == Status::Fail || record.children.iter().any(failed)
+}
+
+impl<'a> Browser<'a> {
+	fn rows(&self) -> Vec<Row<'a>> {
+		let mut rows = Vec::new();
+		self.walk(&self.session.tasks, &mut Vec::new(), &mut rows);
+		rows
+	}
+
+	/// Adds the rows of `records` and, for those that are expanded,
+	/// their hints, their output, and the rows of their subtasks.
+	fn walk(&self, records: &'a [Record], path: &mut Vec<usize>, rows: &mut Vec<Row<'a>>) {
+		for (index, record) in records.iter().enumerate() {
+			if self.failures && !failed(record) {
+				continue;
+			}
+
+			path.push(index);
+			rows.push(Row::Task { path: path.clone(), record });
+
+			if self.expanded.contains(path) {
+				if let Some(hint) = &record.hint {
+					rows.push(Row::Line { path: path.clone(), text: hint, hint: true });
+				}
+
+				for text in &record.output {
+					rows.push(Row::Line { path: path.clone(), text, hint: false });
+				}
+
+				self.walk(&record.children, path, rows);
+			}
+
+			path.pop();
+		}
+	}
+
+	/// Expands or collapses every task, or every failing one while only
+	/// failures are shown.
+	fn expand_all(&mut self, expand: bool) {
+		fn paths(records: &[Record], path: &mut Vec<usize>, failures: bool, all: &mut Vec<Vec<usize>>) {
+			for (index, record) in records.iter().enumerate() {
+				if failures && !failed(record) {
+					continue;
+				}
+
+				path.push(index);
+				all.push(path.clone());
+				paths(&record.children, path, failures, all);
+				path.pop();
+			}
+		}
+
+		if !expand {
+			self.expanded.clear();
+			return;
+		}
+
+		let mut all = Vec::new();
+		paths(&self.session.tasks, &mut Vec::new(), self.failures, &mut all);
+		self.expanded.extend(all);
+	}
+
+	/// Selects the row of the task at `path`, or of the closest task
+	/// it is nested under that has a row.
+	fn select(&mut self, mut path: &[usize]) {
+		let rows = self.rows();
+
+		while !path.is_empty() {
+			let found = rows.iter().position(|row| matches!(row, Row::Task { path: other, .. } if other == path));
+
+			if let Some(index) = found {
+				self.selected = index;
+				return;
+			}
+
+			path = &path[..path.len() - 1];
+		}
+
+		self.selected = self.selected.min(rows.len().saturating_sub(1));
+	}
+
+	/// Handles a key, returning whether the browser should close.
+	fn key(&mut self, code: KeyCode, modifiers: KeyModifiers, page: usize) -> bool {
+		let rows = self.rows();
+		let last = rows.len().saturating_sub(1);
+		let Some(row) = rows.get(self.selected) else {
+			return matches!(code, KeyCode::Char('q') | KeyCode::Esc);
+		};
+
+		let path = row.path().to_vec();
+		let task = matches!(row, Row::Task { .. });
+		let expanded = task && self.expanded.contains(&path);
+
+		match code {
+			KeyCode::Char('q') | KeyCode::Esc => return true,
+			KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => return true,
+			KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+			KeyCode::Down | KeyCode::Char('j') => self.selected = (self.selected + 1).min(last),
+			KeyCode::PageUp => self.selected = self.selected.saturating_sub(page),
+			KeyCode::PageDown => self.selected = (self.selected + page).min(last),
+			KeyCode::Home | KeyCode::Char('g') => self.selected = 0,
+			KeyCode::End | KeyCode::Char('G') => self.selected = last,
+			// an expanded task moves into the rows beneath it
+			KeyCode::Right | KeyCode::Char('l') if expanded => self.selected = (self.selected + 1).min(last),
+			KeyCode::Right | KeyCode::Char('l') if task => _ = self.expanded.insert(path),
+			// a collapsed task, or a line, moves out to the task it is in
+			KeyCode::Left | KeyCode::Char('h') if expanded => _ = self.expanded.remove(&path),
+			KeyCode::Left | KeyCode::Char('h') => {
+				let outer = if task { &path[..path.len() - 1] } else { &path[..] };
+				self.select(outer);
+			},
+			KeyCode::Enter | KeyCode::Char(' ') if expanded => _ = self.expanded.remove(&path),
+			KeyCode::Enter | KeyCode::Char(' ') if task => _ = self.expanded.insert(path),
+			KeyCode::Char('f') => {
+				self.failures = !self.failures;
+				self.select(&path);
+			},
+			KeyCode::Char('e') => self.expand_all(true),
+			KeyCode::Char('c') => {
+				self.expand_all(false);
+				self.select(&path[..1]);
+			},
+			_ => {},
+		}
+
+		false
+	}
+
+	/// Writes the row at `index`, fitted within `width` cells.
+	fn line(&self, row: &Row, index: usize, width: usize) -> String {
+		let selected = index == self.selected;
+
+		let (head, symbol, text, tail, color) = match row {
+			Row::Task { path, record } => {
+				let foldable = record.hint.is_some() || !record.output.is_empty() || !record.children.is_empty();
+				let marker = match (foldable, self.expanded.contains(path)) {
+					(false, _) => "  ",
+					(true, false) => "▸ ",
+					(true, true) => "▾ ",
+				};
+
+				(
+					format!("{}{marker}", "  ".repeat(path.len() - 1)),
+					Some(record.status.symbol()),
+					format::plain(&record.end_message),
+					format!("  {}", time::format(record.duration)),
+					"",
+				)
+			},
+			Row::Line { path, text, hint } => {
+				let text = if *hint {
+					locale::text("hint: {hint}", &[("hint", text)])
+				} else {
+					format::plain(text)
+				};
+
+				let color = if *hint { "\x1b[36m" } else { "\x1b[90m" };
+				(format!("{}    ", "  ".repeat(path.len() - 1)), None, text, String::new(), color)
+			},
+		};
+
+		let mut room = width.saturating_sub(style::width(&head) + if symbol.is_some() { 2 } else { 0 });
+		let text = style::truncate(&text, room);
+		room -= style::width(&text);
+
+		// the duration is left out rather than cutting the message shorter
+		let tail = if style::width(&tail) <= room { tail } else { String::new() };
+		let symbol = symbol.map_or_else(String::new, |symbol| format!("{symbol} "));
+
+		if selected {
+			return format::plain(&format!("{head}{symbol}{text}{tail}"));
+		}
+
+		format::uncolored(&format!("{head}{symbol}{color}{text}\x1b[0m\x1b[90m{tail}\x1b[0m")).into_owned()
+	}
+
+	fn draw(&mut self, out: &mut Stdout) -> io::Result<()> {
+		let (width, height) = terminal::size()?;
+		let (width, height) = (usize::from(width), usize::from(height).saturating_sub(1).max(1));
+		let rows = self.rows();
+
+		self.selected = self.selected.min(rows.len().saturating_sub(1));
+		self.top = self.top.min(self.selected).max((self.selected + 1).saturating_sub(height));
+
+		for y in 0..height {
+			queue!(out, cursor::MoveTo(0, y as u16))?;
+
+			if let Some(row) = rows.get(self.top + y) {
+				let selected = self.top + y == self.selected;
+
+				if selected {
+					queue!(out, SetAttribute(Attribute::Reverse))?;
+				}
+
+				queue!(out, Print(self.line(row, self.top + y, width)))?;
+
+				if selected {
+					queue!(out, SetAttribute(Attribute::Reset))?;
+				}
+			}
+
+			queue!(out, terminal::Clear(ClearType::UntilNewLine))?;
+		}
+
+		let tasks = rows.iter().filter(|row| matches!(row, Row::Task { .. })).count();
+		let help = locale::text(
+			"{shown} tasks shown · ↑↓ move · ←→ fold · e/c expand/collapse all · f failures only · q quit",
+			&[("shown", &tasks)],
+		);
+
+		queue!(
+			out,
+			cursor::MoveTo(0, height as u16),
+			Print(format::uncolored(&format!("\x1b[90m{}\x1b[0m", style::truncate(&help, width)))),
+			terminal::Clear(ClearType::UntilNewLine),
+		)?;
+
+		out.flush()
+	}
+}
+
+/// Opens a browser of a finished run in the terminal, in which its task
+/// tree can be expanded and collapsed, each task's hint and output read,
+/// and every task hidden but the failures and the tasks they are nested
+/// in, for runs too large to read back through the scrollback. The call
+/// returns once the browser is closed with `q`.
+///
+/// Tasks are moved between with the arrow keys (or `j` and `k`), and are
+/// expanded and collapsed with the left and right arrows, or enter. `e`
+/// and `c` expand and collapse every task, and `f` shows only failures.
+///
+/// The browser takes over the whole terminal while it is open, and jeflog
+/// draws nothing else meanwhile, so it is meant to be opened after the
+/// tasks of a run have ended, as in `jeflog::browse(&jeflog::session())`.
+/// It returns at once if the standard input or output isn't a terminal.
+pub fn browse(session: &Session) -> io::Result<()> {
+	if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+		return Ok(());
+	}
+
+	let mut browser = Browser {
+		session,
+		expanded: HashSet::new(),
+		failures: false,
+		selected: 0,
+		top: 0,
+	};
+
+	let mut out = io::stdout();
+	output::mute();
+	let _restore = Restore;
+
+	terminal::enable_raw_mode()?;
+	execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+
+	loop {
+		browser.draw(&mut out)?;
+
+		if let Event::Key(key) = event::read()? {
+			let page = usize::from(terminal::size()?.1.saturating_sub(1)).max(1);
+
+			// some platforms report releasing a key as well as pressing it
+			if key.kind != KeyEventKind::Release && browser.key(key.code, key.modifiers, page) {
+				return Ok(());
+			}
+		}
+	}
+}
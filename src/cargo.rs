@@ -0,0 +1,192 @@
+use std::{
+	io,
+	process::{Command, ExitStatus},
+};
+
+use crate::{json::{self, Value}, locale, redact, style, Process, Status};
+
+/// A crate, or one target of it, that the compiler has reported on.
+struct Unit {
+	id: String,
+	name: String,
+	lines: Vec<String>,
+	warnings: usize,
+	errors: usize,
+}
+
+/// Runs a cargo command, such as `cargo build`, showing each crate it
+/// compiles as a subtask of the running task, along with any warnings
+/// and errors the compiler reported for it.
+///
+/// The output is read in cargo's JSON format, which is requested with
+/// `--message-format` unless the command already gives one. Crates that
+/// were already up to date are left out, and cargo's own progress lines
+/// are only shown if the running task fails.
+pub fn cargo(command: &mut Command) -> io::Result<ExitStatus> {
+	let configured = command.get_args().any(|arg| {
+		arg == "--" || arg.to_str().is_some_and(|arg| arg.starts_with("--message-format"))
+	});
+
+	if !configured {
+		command.arg("--message-format=json-diagnostic-rendered-ansi");
+	}
+
+	let mut units = Vec::<Unit>::new();
+
+	let status = Process::new(command)
+		.output(crate::stdout().on_failure())
+		.on_stdout_line(|line| handle(&mut units, line))
+		.run();
+
+	// crates that failed to compile never produce an artifact
+	for unit in units.drain(..) {
+		show(unit, false);
+	}
+
+	status
+}
+
+/// Handles one line of cargo's output.
+fn handle(units: &mut Vec<Unit>, line: &str) {
+	let Some(message) = json::parse(line) else {
+		// anything else, like the output of `cargo run`, is shown as it is
+		crate::print_line(&style::sanitize(&redact::apply(line)));
+		return;
+	};
+
+	let reason = message.get("reason").and_then(Value::as_str);
+	let id = unit_id(&message);
+
+	match (reason, id) {
+		(Some("compiler-message"), Some(id)) => {
+			let index = match units.iter().position(|unit| unit.id == id) {
+				Some(index) => index,
+				None => {
+					units.push(Unit {
+						name: unit_name(&message),
+						id,
+						lines: Vec::new(),
+						warnings: 0,
+						errors: 0,
+					});
+
+					units.len() - 1
+				},
+			};
+
+			let unit = &mut units[index];
+			let diagnostic = message.get("message");
+
+			match diagnostic.and_then(|diagnostic| diagnostic.get("level")).and_then(Value::as_str) {
+				Some("warning") => unit.warnings += 1,
+				Some("error" | "error: internal compiler error") => unit.errors += 1,
+				_ => {},
+			}
+
+			let rendered = diagnostic
+				.and_then(|diagnostic| diagnostic.get("rendered"))
+				.and_then(Value::as_str)
+				.unwrap_or_default();
+
+			unit.lines.extend(rendered.trim_end().lines().map(|line| style::sanitize(&redact::apply(line))));
+		},
+		(Some("compiler-artifact"), Some(id)) => {
+			let fresh = message.get("fresh").and_then(Value::as_bool).unwrap_or(false);
+
+			let unit = match units.iter().position(|unit| unit.id == id) {
+				Some(index) => units.remove(index),
+				None => Unit {
+					name: unit_name(&message),
+					id,
+					lines: Vec::new(),
+					warnings: 0,
+					errors: 0,
+				},
+			};
+
+			// up to date crates are only worth showing for what they report
+			if !fresh || !unit.lines.is_empty() {
+				show(unit, true);
+			}
+		},
+		_ => {},
+	}
+}
+
+/// Shows a crate as a subtask that ends right away, with the messages
+/// the compiler reported for it beneath it.
+fn show(unit: Unit, compiled: bool) {
+	crate::__start_task__(None, format_args!("{}", unit.name));
+	crate::print_block(&unit.lines);
+
+	let (status, message) = if unit.errors > 0 || !compiled {
+		let errors = locale::count(unit.errors as u64, "{count} error", "{count} errors");
+		(Status::Fail, format!("{}: {errors}", unit.name))
+	} else if unit.warnings > 0 {
+		let warnings = locale::count(unit.warnings as u64, "{count} warning", "{count} warnings");
+		(Status::Warn, format!("{}: {warnings}", unit.name))
+	} else {
+		(Status::Pass, unit.name)
+	};
+
+	crate::__end_task__(status, format_args!("{message}"));
+}
+
+/// Identifies the target of a package that a message is about.
+fn unit_id(message: &Value) -> Option<String> {
+	let package = message.get("package_id")?.as_str()?;
+	let target = message.get("target")?.get("name")?.as_str()?;
+
+	Some(format!("{package} {target}"))
+}
+
+/// Names the target of a package that a message is about, by its package
+/// along with the kind of target if it isn't the package's library.
+fn unit_name(message: &Value) -> String {
+	let package = message.get("package_id")
+		.and_then(Value::as_str)
+		.map(package_name)
+		.unwrap_or_default();
+
+	let target = message.get("target");
+	let name = target.and_then(|target| target.get("name")).and_then(Value::as_str).unwrap_or_default();
+
+	let kinds = target
+		.and_then(|target| target.get("kind"))
+		.map(Value::as_array)
+		.unwrap_or_default();
+
+	if kinds.iter().any(|kind| kind.as_str() == Some("custom-build")) {
+		format!("{package} ({})", locale::text("build script", &[]))
+	} else if name.is_empty() || name == package.replace('-', "_") || name == package {
+		package.to_owned()
+	} else {
+		format!("{package} ({name})")
+	}
+}
+
+/// Extracts the name of a package from its id, which looks like
+/// `registry+https://...#name@1.0.0`, or `path+file:///.../name#1.0.0`,
+/// or `name 1.0.0 (registry+https://...)` in older versions of cargo.
+fn package_name(id: &str) -> &str {
+	match id.split_once('#') {
+		Some((source, fragment)) => match fragment.split_once('@') {
+			Some((name, _)) => name,
+			None => source.rsplit('/').next().unwrap_or(source),
+		},
+		None => id.split(' ').next().unwrap_or(id),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn package_names_are_read_from_every_kind_of_id() {
+		assert_eq!(package_name("registry+https://github.com/rust-lang/crates.io-index#serde@1.0.200"), "serde");
+		assert_eq!(package_name("path+file:///home/me/jeflog#0.3.0"), "jeflog");
+		assert_eq!(package_name("path+file:///home/me/app#my-app@0.1.0"), "my-app");
+		assert_eq!(package_name("serde 1.0.200 (registry+https://github.com/rust-lang/crates.io-index)"), "serde");
+	}
+}
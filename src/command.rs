@@ -0,0 +1,130 @@
+use std::{
+	io::{self, BufRead, BufReader, Read, Write},
+	process::{Command, ExitStatus, Stdio},
+	sync::mpsc,
+	thread,
+};
+
+use crate::Stdout;
+
+type Callback<'a> = Box<dyn FnMut(&str) + 'a>;
+
+/// Which stream of a child process a line was written to.
+#[derive(Clone, Copy)]
+enum Stream {
+	Stdout,
+	Stderr,
+}
+
+/// Runs a command with its output shown as part of the task tree, and
+/// optionally handed line by line to callbacks that turn it into tasks.
+///
+/// Both standard output and standard error are read as the command runs.
+/// Each line is passed to the callback for its stream if there is one,
+/// and printed beneath the running tasks otherwise. Callbacks are called
+/// on the thread that runs the command, so tasks they start or end are
+/// nested just as they would be anywhere else on that thread.
+pub struct Process<'a> {
+	command: &'a mut Command,
+	output: Stdout,
+	on_stdout_line: Option<Callback<'a>>,
+	on_stderr_line: Option<Callback<'a>>,
+}
+
+impl<'a> Process<'a> {
+	/// Prepares to run `command`, whose standard output and standard
+	/// error are replaced with pipes when it is run.
+	pub fn new(command: &'a mut Command) -> Self {
+		Self {
+			command,
+			output: crate::stdout(),
+			on_stdout_line: None,
+			on_stderr_line: None,
+		}
+	}
+
+	/// Sets the writer that lines without a callback are printed through,
+	/// such as `jeflog::stdout().tail(5)`. By default, every line is
+	/// printed beneath the running tasks.
+	pub fn output(mut self, output: Stdout) -> Self {
+		self.output = output;
+		self
+	}
+
+	/// Passes each line the command writes to standard output to `callback`
+	/// instead of printing it, as in parsing `Compiling x` into a subtask.
+	pub fn on_stdout_line(mut self, callback: impl FnMut(&str) + 'a) -> Self {
+		self.on_stdout_line = Some(Box::new(callback));
+		self
+	}
+
+	/// Passes each line the command writes to standard error to `callback`
+	/// instead of printing it.
+	pub fn on_stderr_line(mut self, callback: impl FnMut(&str) + 'a) -> Self {
+		self.on_stderr_line = Some(Box::new(callback));
+		self
+	}
+
+	/// Runs the command to completion, returning how it exited.
+	pub fn run(mut self) -> io::Result<ExitStatus> {
+		let mut child = self.command
+			.stdout(Stdio::piped())
+			.stderr(Stdio::piped())
+			.spawn()?;
+
+		let stdout = child.stdout.take();
+		let stderr = child.stderr.take();
+		let (sender, receiver) = mpsc::channel();
+
+		thread::scope(|scope| {
+			if let Some(stdout) = stdout {
+				let sender = sender.clone();
+				scope.spawn(move || read_lines(stdout, Stream::Stdout, sender));
+			}
+
+			if let Some(stderr) = stderr {
+				let sender = sender.clone();
+				scope.spawn(move || read_lines(stderr, Stream::Stderr, sender));
+			}
+
+			// the receiver ends once both pipes have been closed
+			drop(sender);
+
+			for (stream, line) in receiver {
+				let callback = match stream {
+					Stream::Stdout => &mut self.on_stdout_line,
+					Stream::Stderr => &mut self.on_stderr_line,
+				};
+
+				match callback {
+					Some(callback) => callback(&line),
+					None => {
+						// the writer only prints whole lines, which can't fail
+						_ = self.output.write_all(line.as_bytes());
+						_ = self.output.write_all(b"\n");
+					},
+				}
+			}
+		});
+
+		child.wait()
+	}
+}
+
+/// Sends each line read from `pipe` until it is closed, without the
+/// line ending, and with any invalid UTF-8 replaced.
+fn read_lines(pipe: impl Read, stream: Stream, sender: mpsc::Sender<(Stream, String)>) {
+	let mut reader = BufReader::new(pipe);
+	let mut line = Vec::new();
+
+	while reader.read_until(b'\n', &mut line).is_ok_and(|read| read > 0) {
+		let text = String::from_utf8_lossy(&line);
+		let text = text.trim_end_matches(['\n', '\r']).to_owned();
+
+		if sender.send((stream, text)).is_err() {
+			break;
+		}
+
+		line.clear();
+	}
+}
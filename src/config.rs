@@ -0,0 +1,782 @@
+use std::{borrow::Cow, env, sync::RwLock, time::Duration};
+
+#[cfg(feature = "config-file")]
+use std::{
+	fs,
+	io,
+	path::{Path, PathBuf},
+};
+
+use crate::{sync::Recover, Color, Connectors, DurationStyle, Format, Level, Propagation, Sanitize, Stream, Theme, TimestampFormat};
+
+/// Settings that change how tasks are reported, installed all at once
+/// with [`Config::builder`].
+///
+/// Each setting can also be changed on its own with the matching setter
+/// function, such as [`set_level`](crate::set_level).
+#[derive(Clone, Debug)]
+pub struct Config {
+	/// The format chosen by the user, or `None` to detect one.
+	pub(crate) format: Option<Format>,
+
+	/// Whether output is colored, or `None` to detect it.
+	pub(crate) color: Option<bool>,
+
+	/// The most detailed level of log line shown by default.
+	pub(crate) level: Level,
+
+	/// How control characters in messages are handled.
+	pub(crate) sanitize: Sanitize,
+
+	/// How tasks end once a task beneath them has failed.
+	pub(crate) propagation: Propagation,
+
+	/// Ring the terminal bell when a task fails.
+	pub(crate) bell_on_fail: bool,
+
+	/// Ring the terminal bell when a task that ran at least this long ends.
+	pub(crate) bell_threshold: Option<Duration>,
+
+	/// Send a desktop notification (OSC 777) alongside every bell.
+	pub(crate) notify: bool,
+
+	/// Append how long each task took to its ending message.
+	pub(crate) durations: bool,
+
+	/// Append the time since the previous task event to each task's line.
+	pub(crate) deltas: bool,
+
+	/// List every warning given when finishing.
+	pub(crate) digest: bool,
+
+	/// The exit code reported when tasks warned but none failed.
+	pub(crate) warning_exit_code: i32,
+
+	/// Exit the process as soon as a task fails.
+	pub(crate) exit_on_fail: bool,
+
+	/// Show the current task and its progress in the terminal's title.
+	pub(crate) title: bool,
+
+	/// Report overall progress to the terminal's taskbar icon (OSC 9;4).
+	pub(crate) taskbar: bool,
+
+	/// Show the local time of each task event in place of its delta.
+	#[cfg(feature = "chrono")]
+	pub(crate) local_times: bool,
+
+	/// Query the cursor's position to notice lines written by others.
+	#[cfg(feature = "crossterm")]
+	pub(crate) reanchor: bool,
+
+	/// The most running subtasks of one task shown at once, if limited.
+	pub(crate) max_subtasks: Option<usize>,
+
+	/// The most lines printed inside one task that are kept for its record, if limited.
+	pub(crate) max_output: Option<usize>,
+
+	/// Collapse repeated lines of text into one with a counter.
+	pub(crate) collapse_repeats: bool,
+
+	/// How durations are written.
+	pub(crate) duration_style: DurationStyle,
+
+	/// Durations at least this long are colored yellow.
+	pub(crate) yellow_after: Duration,
+
+	/// Durations at least this long are colored red.
+	pub(crate) red_after: Duration,
+
+	/// How long the spinner waits between frames, never less than [`MIN_TICK`].
+	pub(crate) tick: Duration,
+
+	/// Show how long each running task has been running.
+	pub(crate) live_elapsed: bool,
+
+	/// Show a line with the elapsed time and counts above the tree.
+	pub(crate) header: bool,
+
+	/// How long a task can go without any activity before it's stalled.
+	pub(crate) stall_after: Option<Duration>,
+
+	/// Print a warning when a task stalls.
+	pub(crate) stall_warnings: bool,
+
+	/// The connectors, and the colors by depth, of the tree.
+	pub(crate) theme: Theme,
+
+	/// The widest a path in a message can be before it's shortened.
+	pub(crate) max_path_width: Option<usize>,
+
+	/// Start lines printed as a log with the host and process id.
+	pub(crate) stamp: bool,
+
+	/// Draw on standard error, leaving standard output to data.
+	pub(crate) stderr: bool,
+
+	/// The deepest tasks are drawn, with deeper ones flattened to it.
+	pub(crate) max_depth: Option<usize>,
+
+	/// How points in time are written in exports.
+	pub(crate) timestamps: TimestampFormat,
+}
+
+impl Config {
+	/// The settings in effect before any are changed.
+	const DEFAULT: Self = Config {
+		format: None,
+		color: None,
+		level: Level::Info,
+		sanitize: Sanitize::Styles,
+		propagation: Propagation::Off,
+		bell_on_fail: false,
+		bell_threshold: None,
+		notify: false,
+		durations: false,
+		deltas: false,
+		digest: true,
+		warning_exit_code: 0,
+		exit_on_fail: false,
+		collapse_repeats: true,
+		max_subtasks: None,
+		max_output: Some(1000),
+		title: false,
+		taskbar: false,
+		#[cfg(feature = "chrono")]
+		local_times: false,
+		#[cfg(feature = "crossterm")]
+		reanchor: false,
+		duration_style: DurationStyle::Auto,
+		yellow_after: Duration::from_secs(1),
+		red_after: Duration::from_secs(10),
+		tick: Duration::from_millis(100),
+		live_elapsed: false,
+		header: false,
+		stall_after: None,
+		stall_warnings: false,
+		theme: Theme::DEFAULT,
+		max_path_width: None,
+		stamp: false,
+		stderr: false,
+		max_depth: None,
+		timestamps: TimestampFormat::EpochMillis,
+	};
+
+	/// Starts building a configuration from the default settings, which
+	/// replaces every setting at once when it is applied.
+	pub fn builder() -> ConfigBuilder {
+		ConfigBuilder { config: Self::DEFAULT }
+	}
+
+	/// Starts building a configuration from the default settings along
+	/// with any given by environment variables, as [`ConfigBuilder::env`]
+	/// reads them.
+	pub fn from_env() -> ConfigBuilder {
+		Self::builder().env()
+	}
+
+	/// Replaces every setting with these at once, so no task can be
+	/// reported with only some of them in effect.
+	pub fn apply(self) {
+		*CONFIG.write().recover() = self;
+	}
+}
+
+/// The settings that can be given by name, in environment variables
+/// (prefixed with `JEFLOG_` and in uppercase) and configuration files.
+const KEYS: [&str; 24] = [
+	"format",
+	"color",
+	"level",
+	"tick_ms",
+	"durations",
+	"deltas",
+	"duration_style",
+	"sanitize",
+	"max_subtasks",
+	"max_depth",
+	"max_output",
+	"collapse_repeats",
+	"title",
+	"taskbar",
+	"bell",
+	"live_elapsed",
+	"header",
+	// the theme comes before the parts of it, which change the theme given
+	"theme",
+	"connectors",
+	"depth_colors",
+	"depth_palette",
+	"stamp",
+	"stderr",
+	"timestamps",
+];
+
+impl Config {
+	/// Changes the setting named `key` to the value written as `value`,
+	/// leaving it alone if the value can't be parsed.
+	fn set(&mut self, key: &str, value: &str) {
+		// patterns are case sensitive, unlike every other value
+		#[cfg(feature = "chrono")]
+		let pattern = value.trim().to_owned();
+
+		let value = value.trim().to_ascii_lowercase();
+
+		let switch = match value.as_str() {
+			"1" | "true" | "yes" | "on" => Some(true),
+			"0" | "false" | "no" | "off" => Some(false),
+			_ => None,
+		};
+
+		match key {
+			"format" => self.format = Format::from_name(&value).or(self.format),
+			"color" => self.color = match value.as_str() {
+				"auto" => None,
+				_ => switch.or(self.color),
+			},
+			"level" => self.level = value.parse().unwrap_or(self.level),
+			"tick_ms" => self.tick = value.parse().map(Duration::from_millis).map_or(self.tick, |tick| tick.max(MIN_TICK)),
+			"durations" => self.durations = switch.unwrap_or(self.durations),
+			"deltas" => self.deltas = switch.unwrap_or(self.deltas),
+			"duration_style" => self.duration_style = match value.as_str() {
+				"auto" => DurationStyle::Auto,
+				"milliseconds" | "ms" => DurationStyle::Milliseconds,
+				"seconds" | "s" => DurationStyle::Seconds,
+				"components" => DurationStyle::Components,
+				"words" => DurationStyle::Words,
+				_ => self.duration_style,
+			},
+			"sanitize" => self.sanitize = match value.as_str() {
+				"off" => Sanitize::Off,
+				"styles" => Sanitize::Styles,
+				"all" => Sanitize::All,
+				_ => self.sanitize,
+			},
+			"max_subtasks" => self.max_subtasks = match value.as_str() {
+				"none" => None,
+				limit => limit.parse().ok().or(self.max_subtasks),
+			},
+			"max_output" => self.max_output = match value.as_str() {
+				"none" => None,
+				limit => limit.parse().ok().or(self.max_output),
+			},
+			"max_depth" => self.max_depth = match value.as_str() {
+				"none" => None,
+				depth => depth.parse().ok().or(self.max_depth),
+			},
+			"collapse_repeats" => self.collapse_repeats = switch.unwrap_or(self.collapse_repeats),
+			"title" => self.title = switch.unwrap_or(self.title),
+			"taskbar" => self.taskbar = switch.unwrap_or(self.taskbar),
+			"bell" => self.bell_on_fail = switch.unwrap_or(self.bell_on_fail),
+			"live_elapsed" => self.live_elapsed = switch.unwrap_or(self.live_elapsed),
+			"header" => self.header = switch.unwrap_or(self.header),
+			"stamp" => self.stamp = switch.unwrap_or(self.stamp),
+			"stderr" => self.stderr = switch.unwrap_or(self.stderr),
+			"theme" => self.theme = Theme::from_name(&value).unwrap_or_else(|| self.theme.clone()),
+			"connectors" => self.theme.connectors = match value.as_str() {
+				"heavy" => Connectors::Heavy,
+				"light" => Connectors::Light,
+				"rounded" => Connectors::Rounded,
+				"double" => Connectors::Double,
+				"dotted" => Connectors::Dotted,
+				"ascii" => Connectors::Ascii,
+				_ => self.theme.connectors,
+			},
+			"depth_colors" => self.theme.depth_colors = switch.unwrap_or(self.theme.depth_colors),
+			"depth_palette" => {
+				// a palette with any color that isn't known is left out whole
+				let palette = value.split(',')
+					.map(|color| Color::from_name(color.trim()))
+					.collect::<Option<Vec<_>>>();
+
+				if let Some(palette) = palette {
+					self.theme.depth_palette = Cow::Owned(palette);
+				}
+			},
+			"timestamps" => self.timestamps = match value.as_str() {
+				"millis" | "epoch" => TimestampFormat::EpochMillis,
+				"rfc3339" => TimestampFormat::Rfc3339,
+				#[cfg(feature = "chrono")]
+				_ if pattern.contains('%') => TimestampFormat::Strftime(pattern),
+				_ => self.timestamps.clone(),
+			},
+			_ => {},
+		}
+	}
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Self::DEFAULT
+	}
+}
+
+/// Builds a [`Config`] one setting at a time, created by [`Config::builder`].
+///
+/// Settings that aren't given keep their defaults, and nothing changes
+/// until [`apply`](Self::apply) is called, so this is best done before any
+/// task starts, as in `Config::builder().level(Level::Debug).apply()`. A
+/// configuration can also be built and kept, then applied later with
+/// [`Config::apply`], as in `let config = Config::builder().build();`.
+#[derive(Clone, Debug)]
+#[must_use]
+pub struct ConfigBuilder {
+	config: Config,
+}
+
+impl ConfigBuilder {
+	/// Chooses the format tasks are written in, like [`set_format`](crate::set_format).
+	pub fn format(mut self, format: Format) -> Self {
+		self.config.format = Some(format);
+		self
+	}
+
+	/// Chooses whether output is colored, like [`set_color`](crate::set_color).
+	pub fn color(mut self, enabled: bool) -> Self {
+		self.config.color = Some(enabled);
+		self
+	}
+
+	/// Sets the most detailed level of log line shown, like [`set_level`](crate::set_level).
+	pub fn level(mut self, level: Level) -> Self {
+		self.config.level = level;
+		self
+	}
+
+	/// Sets how control characters in messages are handled, like
+	/// [`set_sanitize`](crate::set_sanitize).
+	pub fn sanitize(mut self, sanitize: Sanitize) -> Self {
+		self.config.sanitize = sanitize;
+		self
+	}
+
+	/// Sets the glyphs that connect tasks to their subtasks, like
+	/// [`set_connectors`](crate::set_connectors).
+	pub fn connectors(mut self, connectors: Connectors) -> Self {
+		self.config.theme.connectors = connectors;
+		self
+	}
+
+	/// Sets how the task tree looks, replacing its connectors, colors by
+	/// depth, and palette at once, like [`set_theme`](crate::set_theme).
+	/// Any of them given after this change the theme that was given.
+	pub fn theme(mut self, theme: Theme) -> Self {
+		self.config.theme = theme;
+		self
+	}
+
+	/// Shortens paths in messages that are wider than `width` cells, like
+	/// [`set_max_path_width`](crate::set_max_path_width).
+	pub fn max_path_width(mut self, width: Option<usize>) -> Self {
+		self.config.max_path_width = width;
+		self
+	}
+
+	/// Starts lines printed as a log with the host and process id, like
+	/// [`set_stamp`](crate::set_stamp).
+	pub fn stamp(mut self, enabled: bool) -> Self {
+		self.config.stamp = enabled;
+		self
+	}
+
+	/// Draws tasks on standard error, leaving standard output to the
+	/// lines printed with [`data!`](crate::data), like [`set_stderr`](crate::set_stderr).
+	pub fn stderr(mut self, enabled: bool) -> Self {
+		self.config.stderr = enabled;
+		self
+	}
+
+	/// Chooses the stream tasks are drawn on, which is the same as calling
+	/// [`stderr`](Self::stderr) with whether it is [`Stream::Stderr`].
+	pub fn output(mut self, stream: Stream) -> Self {
+		self.config.stderr = stream == Stream::Stderr;
+		self
+	}
+
+	/// Sets how points in time are written in exports, like
+	/// [`set_timestamp_format`](crate::set_timestamp_format).
+	pub fn timestamp_format(mut self, format: TimestampFormat) -> Self {
+		self.config.timestamps = format;
+		self
+	}
+
+	/// Sets how tasks end once a task beneath them has failed, like
+	/// [`set_propagation`](crate::set_propagation).
+	pub fn propagation(mut self, propagation: Propagation) -> Self {
+		self.config.propagation = propagation;
+		self
+	}
+
+	/// Rings the bell when a task fails, like [`set_bell_on_fail`](crate::set_bell_on_fail).
+	pub fn bell_on_fail(mut self, enabled: bool) -> Self {
+		self.config.bell_on_fail = enabled;
+		self
+	}
+
+	/// Rings the bell when a long task ends, like
+	/// [`set_bell_threshold`](crate::set_bell_threshold).
+	pub fn bell_threshold(mut self, threshold: Option<Duration>) -> Self {
+		self.config.bell_threshold = threshold;
+		self
+	}
+
+	/// Sends a desktop notification along with every bell, like
+	/// [`set_notifications`](crate::set_notifications).
+	pub fn notifications(mut self, enabled: bool) -> Self {
+		self.config.notify = enabled;
+		self
+	}
+
+	/// Shows how long each task took, like [`set_durations`](crate::set_durations).
+	pub fn durations(mut self, enabled: bool) -> Self {
+		self.config.durations = enabled;
+		self
+	}
+
+	/// Sets how durations are written, like [`set_duration_style`](crate::set_duration_style).
+	pub fn duration_style(mut self, style: DurationStyle) -> Self {
+		self.config.duration_style = style;
+		self
+	}
+
+	/// Sets when durations are colored yellow and red, like
+	/// [`set_duration_thresholds`](crate::set_duration_thresholds).
+	pub fn duration_thresholds(mut self, yellow: Duration, red: Duration) -> Self {
+		self.config.yellow_after = yellow;
+		self.config.red_after = red;
+		self
+	}
+
+	/// Shows the time since the previous task event, like [`set_deltas`](crate::set_deltas).
+	pub fn deltas(mut self, enabled: bool) -> Self {
+		self.config.deltas = enabled;
+		self
+	}
+
+	/// Lists every warning when finishing, like
+	/// [`set_warning_digest`](crate::set_warning_digest).
+	pub fn warning_digest(mut self, enabled: bool) -> Self {
+		self.config.digest = enabled;
+		self
+	}
+
+	/// Sets the exit code for runs that only warned, like
+	/// [`set_warning_exit_code`](crate::set_warning_exit_code).
+	pub fn warning_exit_code(mut self, code: i32) -> Self {
+		self.config.warning_exit_code = code;
+		self
+	}
+
+	/// Exits as soon as a task fails, like [`set_exit_on_fail`](crate::set_exit_on_fail).
+	pub fn exit_on_fail(mut self, enabled: bool) -> Self {
+		self.config.exit_on_fail = enabled;
+		self
+	}
+
+	/// Shows the current task in the terminal's title, like [`set_title`](crate::set_title).
+	pub fn title(mut self, enabled: bool) -> Self {
+		self.config.title = enabled;
+		self
+	}
+
+	/// Reports progress on the taskbar icon, like
+	/// [`set_taskbar_progress`](crate::set_taskbar_progress).
+	pub fn taskbar_progress(mut self, enabled: bool) -> Self {
+		self.config.taskbar = enabled;
+		self
+	}
+
+	/// Shows the local time of each task event, like
+	/// [`set_local_times`](crate::set_local_times).
+	#[cfg(feature = "chrono")]
+	pub fn local_times(mut self, enabled: bool) -> Self {
+		self.config.local_times = enabled;
+		self
+	}
+
+	/// Notices lines written by others before redrawing, like
+	/// [`set_reanchor`](crate::set_reanchor).
+	#[cfg(feature = "crossterm")]
+	pub fn reanchor(mut self, enabled: bool) -> Self {
+		self.config.reanchor = enabled;
+		self
+	}
+
+	/// Limits how many running subtasks are shown at once, like
+	/// [`set_max_subtasks`](crate::set_max_subtasks).
+	pub fn max_subtasks(mut self, limit: Option<usize>) -> Self {
+		self.config.max_subtasks = limit;
+		self
+	}
+
+	/// Limits how many lines printed inside each task are kept for its
+	/// record, like [`set_max_output`](crate::set_max_output).
+	pub fn max_output(mut self, limit: Option<usize>) -> Self {
+		self.config.max_output = limit;
+		self
+	}
+
+	/// Limits how deeply tasks are drawn, like
+	/// [`set_max_depth`](crate::set_max_depth).
+	pub fn max_depth(mut self, depth: Option<usize>) -> Self {
+		self.config.max_depth = depth;
+		self
+	}
+
+	/// Collapses repeated lines of text, like
+	/// [`set_collapse_repeats`](crate::set_collapse_repeats).
+	pub fn collapse_repeats(mut self, enabled: bool) -> Self {
+		self.config.collapse_repeats = enabled;
+		self
+	}
+
+	/// Sets how long the spinner waits between frames, which is 100ms by
+	/// default and at least 10ms, like [`set_tick`](crate::set_tick).
+	pub fn tick(mut self, tick: Duration) -> Self {
+		self.config.tick = tick.max(MIN_TICK);
+		self
+	}
+
+	/// Overrides settings with those given by environment variables, so
+	/// that end users can tune how any tool built on jeflog is rendered
+	/// without it exposing flags of its own. Settings given before this are
+	/// defaults that users may override, and settings given after it can't
+	/// be overridden.
+	///
+	/// These are read, and any that are missing or can't be parsed are
+	/// left alone:
+	///
+	/// - `JEFLOG_FORMAT`: a format by name, such as `sequential`
+	/// - `JEFLOG_COLOR`: whether output is colored, or `auto` to detect it
+	/// - `JEFLOG_LEVEL`: `error`, `warn`, `info`, `debug`, or `trace`, which
+	///   applies wherever `JEFLOG_LOG` doesn't give a level
+	/// - `JEFLOG_TICK_MS`: the milliseconds between frames of the spinner, at
+	///   least 10
+	/// - `JEFLOG_DURATIONS` and `JEFLOG_DELTAS`: whether durations and the
+	///   time between events are shown
+	/// - `JEFLOG_DURATION_STYLE`: `auto`, `milliseconds`, `seconds`,
+	///   `components`, or `words`
+	/// - `JEFLOG_SANITIZE`: `off`, `styles`, or `all`
+	/// - `JEFLOG_THEME`: a [`Theme`] by name, which is `default`, `light`,
+	///   `rounded`, `ascii`, or `colorful`
+	/// - `JEFLOG_CONNECTORS`: `heavy`, `light`, `rounded`, `double`,
+	///   `dotted`, or `ascii`, which replaces the connectors of the theme
+	/// - `JEFLOG_DEPTH_COLORS`: whether spinners and connectors are colored
+	///   by depth, and `JEFLOG_DEPTH_PALETTE`: the colors they cycle through,
+	///   separated by commas, as in `cyan,magenta,blue`, which both replace
+	///   those of the theme
+	/// - `JEFLOG_MAX_SUBTASKS`, `JEFLOG_MAX_DEPTH`, and `JEFLOG_MAX_OUTPUT`:
+	///   a number, or `none` for no limit
+	/// - `JEFLOG_TIMESTAMPS`: `millis`, `rfc3339`, or, with the `chrono`
+	///   feature, a strftime pattern
+	/// - `JEFLOG_COLLAPSE_REPEATS`, `JEFLOG_TITLE`, `JEFLOG_TASKBAR`,
+	///   `JEFLOG_LIVE_ELAPSED`, `JEFLOG_HEADER`, `JEFLOG_STAMP`,
+	///   `JEFLOG_STDERR`, and `JEFLOG_BELL` (which rings the bell on
+	///   failure): whether each is on
+	///
+	/// Switches are on for `1`, `true`, `yes`, or `on`, and off for `0`,
+	/// `false`, `no`, or `off`.
+	pub fn env(mut self) -> Self {
+		for key in KEYS {
+			if let Ok(value) = env::var(format!("JEFLOG_{}", key.to_ascii_uppercase())) {
+				self.config.set(key, &value);
+			}
+		}
+
+		self
+	}
+
+	/// Overrides settings with those in the user's configuration file,
+	/// `jeflog.toml` in `$XDG_CONFIG_HOME` (or `~/.config`), or in `%APPDATA%`
+	/// on Windows, so that users can set a personal style once for every
+	/// tool built on jeflog. A file that is missing or can't be read is
+	/// ignored, just as environment variables that aren't set are.
+	///
+	/// The file gives settings by the same names as [`env`](Self::env) reads,
+	/// in lowercase and without the prefix, as in `durations = true` or
+	/// `duration_style = "words"`, and lists as arrays, so that a personal
+	/// style is written as `theme = "rounded"` followed by `depth_colors = true`
+	/// and `depth_palette = ["cyan", "blue"]`. Calling this before `env` lets
+	/// environment variables override the file.
+	#[cfg(feature = "config-file")]
+	pub fn file(self) -> Self {
+		let directory = env::var_os("XDG_CONFIG_HOME")
+			.map(PathBuf::from)
+			.or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+			.or_else(|| env::var_os("APPDATA").map(PathBuf::from));
+
+		let Some(path) = directory.map(|directory| directory.join("jeflog.toml")) else {
+			return self;
+		};
+
+		match self.clone().file_at(path) {
+			Ok(builder) => builder,
+			Err(_) => self,
+		}
+	}
+
+	/// Overrides settings with those in the configuration file at `path`,
+	/// written just as [`file`](Self::file) reads them, returning an error
+	/// if it can't be read or isn't valid TOML.
+	#[cfg(feature = "config-file")]
+	pub fn file_at(mut self, path: impl AsRef<Path>) -> io::Result<Self> {
+		let text = fs::read_to_string(path)?;
+
+		let table = text.parse::<toml::Table>()
+			.map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+		for key in KEYS {
+			let value = match table.get(key) {
+				Some(toml::Value::String(value)) => value.clone(),
+				Some(toml::Value::Integer(value)) => value.to_string(),
+				Some(toml::Value::Boolean(value)) => value.to_string(),
+				// lists, such as of colors, are read as they are written in variables
+				Some(toml::Value::Array(values)) => values.iter()
+					.map(|value| value.as_str().map_or_else(|| value.to_string(), str::to_owned))
+					.collect::<Vec<_>>()
+					.join(","),
+				_ => continue,
+			};
+
+			self.config.set(key, &value);
+		}
+
+		Ok(self)
+	}
+
+	/// Shows how long each running task has been running, like
+	/// [`set_live_elapsed`](crate::set_live_elapsed).
+	pub fn live_elapsed(mut self, enabled: bool) -> Self {
+		self.config.live_elapsed = enabled;
+		self
+	}
+
+	/// Shows a line above the tree with the elapsed time and how many tasks
+	/// have ended each way, like [`set_header`](crate::set_header).
+	pub fn header(mut self, enabled: bool) -> Self {
+		self.config.header = enabled;
+		self
+	}
+
+	/// Sets how long a task can go without activity before it is marked as
+	/// stalled, like [`set_stall_after`](crate::set_stall_after).
+	pub fn stall_after(mut self, after: Option<Duration>) -> Self {
+		self.config.stall_after = after;
+		self
+	}
+
+	/// Prints a warning when a task stalls, like
+	/// [`set_stall_warnings`](crate::set_stall_warnings).
+	pub fn stall_warnings(mut self, enabled: bool) -> Self {
+		self.config.stall_warnings = enabled;
+		self
+	}
+
+	/// Colors spinners and connectors by depth, like
+	/// [`set_depth_colors`](crate::set_depth_colors).
+	pub fn depth_colors(mut self, enabled: bool) -> Self {
+		self.config.theme.depth_colors = enabled;
+		self
+	}
+
+	/// Sets the colors cycled through by depth, like
+	/// [`set_depth_palette`](crate::set_depth_palette).
+	pub fn depth_palette(mut self, palette: &[Color]) -> Self {
+		self.config.theme.depth_palette = Cow::Owned(palette.to_vec());
+		self
+	}
+
+	/// Returns the configuration that has been built, without applying it,
+	/// so that it can be kept and applied later with [`Config::apply`].
+	pub fn build(self) -> Config {
+		self.config
+	}
+
+	/// Replaces every setting with those that have been built at once, just
+	/// as `build().apply()` does.
+	pub fn apply(self) {
+		self.build().apply();
+	}
+}
+
+/// The shortest tick the spinner is drawn with.
+pub(crate) const MIN_TICK: Duration = Duration::from_millis(10);
+
+// a poisoned lock is recovered like any other, since every setting
+// is replaced whole and can't be left half written
+pub(crate) static CONFIG: RwLock<Config> = RwLock::new(Config::DEFAULT);
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn themes_are_read_by_name() {
+		let mut config = Config::DEFAULT;
+		config.set("theme", " Colorful ");
+		assert_eq!(config.theme, Theme::COLORFUL);
+
+		config.set("theme", "unknown");
+		assert_eq!(config.theme, Theme::COLORFUL);
+	}
+
+	#[test]
+	fn parts_of_a_theme_change_the_theme_given() {
+		// keys are read in the order they're listed
+		let position = |name| KEYS.iter().position(|&key| key == name);
+		assert!(position("theme") < position("connectors"));
+		assert!(position("theme") < position("depth_palette"));
+
+		let mut config = Config::DEFAULT;
+		config.set("theme", "colorful");
+		config.set("connectors", "ascii");
+		assert_eq!(config.theme, Theme::COLORFUL.with_connectors(Connectors::Ascii));
+	}
+
+	#[test]
+	fn palettes_are_read_as_lists_of_colors() {
+		let mut config = Config::DEFAULT;
+		config.set("depth_palette", "Cyan, grey,blue");
+		assert_eq!(config.theme.depth_palette(), [Color::Cyan, Color::Gray, Color::Blue]);
+
+		config.set("depth_palette", "cyan,mauve");
+		assert_eq!(config.theme.depth_palette(), [Color::Cyan, Color::Gray, Color::Blue]);
+
+		config.set("depth_colors", "on");
+		assert!(config.theme.depth_colors());
+	}
+
+	#[test]
+	fn ticks_are_never_too_short() {
+		let mut config = Config::DEFAULT;
+		config.set("tick_ms", "0");
+		assert_eq!(config.tick, MIN_TICK);
+
+		config.set("tick_ms", "250");
+		assert_eq!(config.tick, Duration::from_millis(250));
+
+		let config = Config::builder().tick(Duration::ZERO).config;
+		assert_eq!(config.tick, MIN_TICK);
+	}
+
+	#[test]
+	fn output_chooses_the_stream_drawn_on() {
+		assert!(Config::builder().output(Stream::Stderr).build().stderr);
+		assert!(!Config::builder().stderr(true).output(Stream::Stdout).build().stderr);
+	}
+
+	#[cfg(feature = "config-file")]
+	#[test]
+	fn themes_are_read_from_files() {
+		let path = std::env::temp_dir().join(format!("jeflog-theme-{}.toml", std::process::id()));
+		fs::write(&path, "theme = \"rounded\"\ndepth_colors = true\ndepth_palette = [\"cyan\", \"blue\"]\n").unwrap();
+
+		let builder = Config::builder().file_at(&path);
+		_ = fs::remove_file(&path);
+		let theme = builder.unwrap().config.theme;
+
+		assert_eq!(theme.connectors(), Connectors::Rounded);
+		assert!(theme.depth_colors());
+		assert_eq!(theme.depth_palette(), [Color::Cyan, Color::Blue]);
+	}
+}
@@ -0,0 +1,70 @@
+use std::{thread, time::Duration};
+
+use crate::{cancel, fail, pass, progress, task, warn};
+
+fn pause(millis: u64) {
+	thread::sleep(Duration::from_millis(millis));
+}
+
+/// Runs a short, scripted sequence of nested tasks that pass, warn,
+/// fail, get cancelled, and report progress.
+///
+/// This is useful for checking how a terminal renders jeflog, and
+/// gives a canonical scenario to refer to when reporting an issue.
+/// It is always shown, even if jeflog hasn't been initialized.
+pub fn demo() {
+	crate::enable();
+
+	task!("Preparing workspace");
+	pause(600);
+	pass!("Prepared workspace");
+
+	task!("Downloading toolchain");
+
+	for chunk in 0..=40 {
+		progress(chunk, 40);
+		pause(40);
+	}
+
+	pass!("Downloaded toolchain");
+
+	task!("Building project");
+
+	task!("Resolving dependencies");
+	pause(800);
+	pass!("Resolved 12 dependencies");
+
+	task!("Compiling crates");
+
+	task!("Compiling core");
+	pause(700);
+	pass!("Compiled core");
+
+	task!("Compiling cli");
+	pause(900);
+	warn!("Compiled cli with 2 warnings");
+
+	pass!("Compiled 2 crates");
+
+	task!("Linking binary");
+	pause(500);
+	pass!("Linked binary");
+
+	warn!("Built project with warnings");
+
+	task!("Running tests");
+
+	task!("Running unit tests");
+	pause(700);
+	pass!("42 unit tests passed");
+
+	task!("Running integration tests");
+	pause(1000);
+	fail!("3 of 17 integration tests failed");
+
+	task!("Running doc tests");
+	pause(300);
+	cancel!("Skipped doc tests after failure");
+
+	fail!("Tests failed");
+}
@@ -0,0 +1,199 @@
+/// How many unchanged lines are shown around each change.
+const CONTEXT: usize = 3;
+
+/// The most cells the table for finding common lines may have, beyond
+/// which the changed lines are shown as removed and re-added wholesale.
+const MAX_CELLS: usize = 1 << 22;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Edit<'a> {
+	Keep(&'a str),
+	Remove(&'a str),
+	Add(&'a str),
+}
+
+/// Finds the edits that turn the lines of `old` into those of `new`,
+/// keeping as many lines as possible.
+fn edits<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Edit<'a>> {
+	// lines shared at the start and end are kept without searching
+	let prefix = old.iter().zip(new).take_while(|(old, new)| old == new).count();
+
+	let suffix = old[prefix..].iter().rev()
+		.zip(new[prefix..].iter().rev())
+		.take_while(|(old, new)| old == new)
+		.count();
+
+	let middle_old = &old[prefix..old.len() - suffix];
+	let middle_new = &new[prefix..new.len() - suffix];
+
+	let mut edits = old[..prefix].iter().map(|&line| Edit::Keep(line)).collect::<Vec<_>>();
+	let (rows, columns) = (middle_old.len() + 1, middle_new.len() + 1);
+
+	if rows.saturating_mul(columns) > MAX_CELLS {
+		edits.extend(middle_old.iter().map(|&line| Edit::Remove(line)));
+		edits.extend(middle_new.iter().map(|&line| Edit::Add(line)));
+	} else {
+		// the length of the longest common subsequence of each pair of suffixes
+		let mut common = vec![0u32; rows * columns];
+
+		for i in (0..middle_old.len()).rev() {
+			for j in (0..middle_new.len()).rev() {
+				common[i * columns + j] = if middle_old[i] == middle_new[j] {
+					common[(i + 1) * columns + j + 1] + 1
+				} else {
+					common[(i + 1) * columns + j].max(common[i * columns + j + 1])
+				};
+			}
+		}
+
+		let (mut i, mut j) = (0, 0);
+
+		while i < middle_old.len() || j < middle_new.len() {
+			if i < middle_old.len() && j < middle_new.len() && middle_old[i] == middle_new[j] {
+				edits.push(Edit::Keep(middle_old[i]));
+				i += 1;
+				j += 1;
+			} else if j < middle_new.len() && (i == middle_old.len() || common[i * columns + j + 1] >= common[(i + 1) * columns + j]) {
+				edits.push(Edit::Add(middle_new[j]));
+				j += 1;
+			} else {
+				edits.push(Edit::Remove(middle_old[i]));
+				i += 1;
+			}
+		}
+	}
+
+	edits.extend(old[old.len() - suffix..].iter().map(|&line| Edit::Keep(line)));
+	edits
+}
+
+/// Renders a colored unified diff from `expected` to `actual`, one line
+/// per element, which is empty if they are the same.
+///
+/// Removals come before additions wherever lines were replaced, as in
+/// the output of `diff -u`.
+pub(crate) fn unified(expected: &str, actual: &str) -> Vec<String> {
+	let old = expected.lines().collect::<Vec<_>>();
+	let new = actual.lines().collect::<Vec<_>>();
+	let mut edits = edits(&old, &new);
+
+	// the search above prefers additions, so each run of changes
+	// is sorted to put its removals first
+	for run in edits.chunk_by_mut(|a, b| !matches!(a, Edit::Keep(_)) && !matches!(b, Edit::Keep(_))) {
+		run.sort_by_key(|edit| matches!(edit, Edit::Add(_)));
+	}
+
+	let changes = edits.iter()
+		.enumerate()
+		.filter(|(_, edit)| !matches!(edit, Edit::Keep(_)))
+		.map(|(index, _)| index)
+		.collect::<Vec<_>>();
+
+	if changes.is_empty() {
+		return Vec::new();
+	}
+
+	// group changes close enough that their context would overlap
+	let mut hunks = Vec::<(usize, usize)>::new();
+
+	for &change in &changes {
+		let start = change.saturating_sub(CONTEXT);
+		let end = (change + CONTEXT + 1).min(edits.len());
+
+		match hunks.last_mut() {
+			Some((_, last)) if start <= *last => *last = end,
+			_ => hunks.push((start, end)),
+		}
+	}
+
+	let mut lines = vec![
+		String::from("\x1b[1m--- expected\x1b[0m"),
+		String::from("\x1b[1m+++ actual\x1b[0m"),
+	];
+
+	// how many lines of each side come before each edit
+	let mut before = Vec::with_capacity(edits.len());
+	let (mut old_line, mut new_line) = (0, 0);
+
+	for edit in &edits {
+		before.push((old_line, new_line));
+
+		match edit {
+			Edit::Keep(_) => {
+				old_line += 1;
+				new_line += 1;
+			},
+			Edit::Remove(_) => old_line += 1,
+			Edit::Add(_) => new_line += 1,
+		}
+	}
+
+	for (start, end) in hunks {
+		let hunk = &edits[start..end];
+		let old_count = hunk.iter().filter(|edit| !matches!(edit, Edit::Add(_))).count();
+		let new_count = hunk.iter().filter(|edit| !matches!(edit, Edit::Remove(_))).count();
+		let (old_start, new_start) = before[start];
+
+		// an empty side is numbered by the line it would follow
+		let old_start = old_start + usize::from(old_count > 0);
+		let new_start = new_start + usize::from(new_count > 0);
+
+		lines.push(format!("\x1b[36m@@ -{old_start},{old_count} +{new_start},{new_count} @@\x1b[0m"));
+
+		for edit in hunk {
+			lines.push(match edit {
+				Edit::Keep(line) => format!(" {line}"),
+				Edit::Remove(line) => format!("\x1b[31m-{line}\x1b[0m"),
+				Edit::Add(line) => format!("\x1b[32m+{line}\x1b[0m"),
+			});
+		}
+	}
+
+	lines
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::format::plain;
+
+	#[test]
+	fn equal_texts_have_no_diff() {
+		assert!(unified("a\nb", "a\nb").is_empty());
+	}
+
+	#[test]
+	fn replaced_lines_are_removed_then_added() {
+		let diff = unified("a\nb\nc", "a\nB\nc\nd");
+
+		assert_eq!(diff.iter().map(|line| plain(line)).collect::<Vec<_>>(), [
+			"--- expected",
+			"+++ actual",
+			"@@ -1,3 +1,4 @@",
+			" a",
+			"-b",
+			"+B",
+			" c",
+			"+d",
+		]);
+	}
+
+	#[test]
+	fn distant_changes_are_in_separate_hunks() {
+		let expected = (0..20).map(|line| line.to_string()).collect::<Vec<_>>();
+		let mut actual = expected.clone();
+		actual[1] = String::from("one");
+		actual[18] = String::from("eighteen");
+
+		let diff = unified(&expected.join("\n"), &actual.join("\n"));
+		let hunks = diff.iter().map(|line| plain(line)).filter(|line| line.starts_with("@@")).collect::<Vec<_>>();
+
+		assert_eq!(hunks, ["@@ -1,5 +1,5 @@", "@@ -16,5 +16,5 @@"]);
+	}
+
+	#[test]
+	fn lines_added_to_nothing_are_numbered_from_zero() {
+		let diff = unified("", "new");
+		assert_eq!(plain(&diff[2]), "@@ -0,0 +1,1 @@");
+	}
+}
@@ -0,0 +1,208 @@
+use alloc::string::{String, ToString};
+use core::{fmt::Write, time::Duration};
+#[cfg(feature = "std")]
+use std::sync::{mpsc::{self, Receiver, Sender}, Mutex, RwLock};
+
+use crate::{json, Status};
+#[cfg(feature = "std")]
+use crate::{format::{self, Format}, sync::Recover, time::Instant, TASKS};
+
+/// Something that happened to the task tree, as passed to every [`Sink`].
+///
+/// Ids are unique for the life of the process, and messages are plain
+/// text, without styles. Each event about a task gives its `parent` and
+/// `depth` as well, so the tree can be rebuilt from any of them, and every
+/// event has the `time` it happened, measured from a fixed point on a clock
+/// that never goes backward: the first time jeflog read the time, or the
+/// point the clock set with [`set_clock`](crate::set_clock) counts from.
+/// For a [`Tracker`](crate::Tracker), the time is read from its clock.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Event {
+	/// A task started, nested under the task with id `parent` if it has one.
+	/// A task that is reopened starts again with the id it ended with.
+	Start {
+		id: u64,
+		parent: Option<u64>,
+		depth: usize,
+		time: Duration,
+		message: String,
+	},
+	/// A task ended with `status` after running for `duration`.
+	End {
+		id: u64,
+		parent: Option<u64>,
+		depth: usize,
+		time: Duration,
+		status: Status,
+		duration: Duration,
+		message: String,
+	},
+	/// A line of text was printed beneath the task with id `task`, or
+	/// outside of any task, at the depth a subtask of it would have.
+	Text {
+		task: Option<u64>,
+		depth: usize,
+		time: Duration,
+		text: String,
+	},
+	/// A task reported that it is `current` out of `total` units along.
+	Progress {
+		id: u64,
+		parent: Option<u64>,
+		depth: usize,
+		time: Duration,
+		current: u64,
+		total: u64,
+	},
+	/// A task went `idle` without any activity, longer than
+	/// [`set_stall_after`](crate::set_stall_after) allows.
+	Stall {
+		id: u64,
+		parent: Option<u64>,
+		depth: usize,
+		time: Duration,
+		idle: Duration,
+	},
+}
+
+impl Event {
+	/// Writes the event as a single line of JSON, without a newline, such as
+	/// `{"event":"start","id":3,"parent":1,"depth":1,"time_ms":420,"message":"linking"}`.
+	/// Fields that are absent are written as `null`, and durations and
+	/// times are in milliseconds.
+	pub fn to_json(&self) -> String {
+		let optional = |id: Option<u64>| id.map_or_else(|| String::from("null"), |id| id.to_string());
+		let mut out = String::new();
+
+		match self {
+			Self::Start { id, parent, depth, time, message } => _ = write!(
+				out,
+				r#"{{"event":"start","id":{id},"parent":{},"depth":{depth},"time_ms":{},"message":{}}}"#,
+				optional(*parent),
+				time.as_millis(),
+				json::string(message),
+			),
+			Self::End { id, parent, depth, time, status, duration, message } => _ = write!(
+				out,
+				r#"{{"event":"end","id":{id},"parent":{},"depth":{depth},"time_ms":{},"status":"{}","duration_ms":{},"message":{}}}"#,
+				optional(*parent),
+				time.as_millis(),
+				status.name(),
+				duration.as_millis(),
+				json::string(message),
+			),
+			Self::Text { task, depth, time, text } => _ = write!(
+				out,
+				r#"{{"event":"text","task":{},"depth":{depth},"time_ms":{},"text":{}}}"#,
+				optional(*task),
+				time.as_millis(),
+				json::string(text),
+			),
+			Self::Progress { id, parent, depth, time, current, total } => _ = write!(
+				out,
+				r#"{{"event":"progress","id":{id},"parent":{},"depth":{depth},"time_ms":{},"current":{current},"total":{total}}}"#,
+				optional(*parent),
+				time.as_millis(),
+			),
+			Self::Stall { id, parent, depth, time, idle } => _ = write!(
+				out,
+				r#"{{"event":"stall","id":{id},"parent":{},"depth":{depth},"time_ms":{},"idle_ms":{}}}"#,
+				optional(*parent),
+				time.as_millis(),
+				idle.as_millis(),
+			),
+		}
+
+		out
+	}
+}
+
+/// Receives every [`Event`] of the task tree as it happens, once added
+/// with [`add_sink`], such as to forward it to a monitoring process.
+///
+/// Events are delivered in order while the task tree is locked, so a sink
+/// should hand them off quickly, and must not start tasks or log itself.
+#[cfg(feature = "std")]
+pub trait Sink: Send + Sync + 'static {
+	/// Handles one event.
+	fn event(&self, event: &Event);
+}
+
+#[cfg(feature = "std")]
+impl<F> Sink for F
+where
+	F: Fn(&Event) + Send + Sync + 'static,
+{
+	fn event(&self, event: &Event) {
+		self(event)
+	}
+}
+
+#[cfg(feature = "std")]
+static SINKS: RwLock<Vec<Box<dyn Sink>>> = RwLock::new(Vec::new());
+
+// a channel to every subscriber, such as a client of the socket
+#[cfg(feature = "std")]
+static SUBSCRIBERS: Mutex<Vec<Sender<Event>>> = Mutex::new(Vec::new());
+
+/// Adds a sink that receives every event of the task tree from now
+/// on, alongside the terminal. Tasks that are hidden produce no events.
+#[cfg(feature = "std")]
+pub fn add_sink(sink: impl Sink) {
+	SINKS.write().recover().push(Box::new(sink));
+}
+
+/// Passes an event to every sink and subscriber, and prints it in the JSON
+/// format, building it only if it goes anywhere. This must be called while
+/// the task tree is locked.
+#[cfg(feature = "std")]
+pub(crate) fn publish(event: impl FnOnce() -> Event) {
+	let sinks = SINKS.read().recover();
+	let mut subscribers = SUBSCRIBERS.lock().recover();
+	let printed = format::current() == Format::Json;
+
+	if sinks.is_empty() && subscribers.is_empty() && !printed {
+		return;
+	}
+
+	let event = event();
+
+	if printed {
+		format::json::event(&event);
+	}
+
+	for sink in sinks.iter() {
+		sink.event(&event);
+	}
+
+	// subscribers that have gone away are dropped along with their channel
+	subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+}
+
+/// Returns a channel that receives every event from now on, starting
+/// with a `Start` event for every task that is already running, followed
+/// by its progress if it has reported any, so that a subscriber joining
+/// partway through sees the whole tree.
+#[cfg(feature = "std")]
+#[cfg_attr(not(any(unix, feature = "web", feature = "ratatui")), allow(dead_code))]
+pub(crate) fn subscribe() -> Receiver<Event> {
+	let (sender, receiver) = mpsc::channel();
+
+	// the tree is locked until the subscriber is added, so that
+	// it misses no event and gets none twice
+	let tasks = TASKS.lock().recover();
+
+	for task in tasks.iter().filter(|task| !task.hidden) {
+		_ = sender.send(task.node().start(task.started.since_origin(), format::plain(&task.message)));
+
+		if let Some((current, total)) = task.progress {
+			_ = sender.send(task.node().progress(Instant::now().since_origin(), current, total));
+		}
+	}
+
+	SUBSCRIBERS.lock().recover().push(sender);
+	drop(tasks);
+
+	receiver
+}
@@ -0,0 +1,45 @@
+use std::time::Duration;
+
+use crate::{locale, output, time, Status};
+
+use super::{plain, stamp};
+
+/// Prints a line announcing a new task nested `depth` levels deep.
+pub(crate) fn start(depth: usize, label: &str) {
+	line(depth, &locale::text("Task '{task}' started", &[("task", &plain(label))]));
+}
+
+/// Prints a line describing how a task nested `depth` levels deep ended,
+/// and how long it took.
+pub(crate) fn end(depth: usize, label: &str, status: Status, message: &str, duration: Duration) {
+	// each outcome is a whole sentence, so it can be translated as one
+	let template = match status {
+		Status::Pass => "Task '{task}' succeeded after {duration}: {message}",
+		Status::Warn => "Task '{task}' succeeded with a warning after {duration}: {message}",
+		Status::Fail => "Task '{task}' failed after {duration}: {message}",
+		Status::Cancel => "Task '{task}' was cancelled after {duration}: {message}",
+	};
+
+	line(depth, &locale::text(template, &[
+		("task", &plain(label)),
+		("duration", &time::format(duration)),
+		("message", &plain(message)),
+	]));
+}
+
+/// Prints a line describing a status reported while no task was running.
+pub(crate) fn report(status: Status, message: &str) {
+	let outcome = match status {
+		Status::Pass => "Success",
+		Status::Warn => "Warning",
+		Status::Fail => "Failure",
+		Status::Cancel => "Cancelled",
+	};
+
+	line(0, &format!("{}: {}", locale::text(outcome, &[]), plain(message)));
+}
+
+fn line(depth: usize, text: &str) {
+	// nesting is kept as indentation, which screen readers pass over
+	output::emit(&format!("{}{}{text}\n", stamp(), " ".repeat(depth * 5)));
+}
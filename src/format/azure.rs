@@ -0,0 +1,42 @@
+use crate::{output, Status};
+
+use super::{plain, sequential};
+
+/// Opens a collapsible group titled `title` for each top-level task, and
+/// prints the `line` of each subtask inside it just as the sequential
+/// format does.
+pub(crate) fn start(depth: usize, title: &str, line: &str) {
+	if depth == 0 {
+		output::emit(&format!("##[group]{}\n", plain(title)));
+	} else {
+		sequential::start(depth, line);
+	}
+}
+
+/// Closes the group of a top-level task so that its ending line stays
+/// visible, and logs warnings and failures as issues of the pipeline.
+pub(crate) fn end(depth: usize, status: Status, message: &str) {
+	if depth == 0 {
+		output::emit("##[endgroup]\n");
+	}
+
+	let kind = match status {
+		Status::Warn => Some("warning"),
+		Status::Fail => Some("error"),
+		_ => None,
+	};
+
+	if let Some(kind) = kind {
+		output::emit(&format!("##vso[task.logissue type={kind}]{}\n", escape(&plain(message))));
+	}
+
+	sequential::end(depth, status, message);
+}
+
+/// Escapes the characters that logging commands treat specially.
+fn escape(message: &str) -> String {
+	message
+		.replace('%', "%AZP25")
+		.replace('\r', "%0D")
+		.replace('\n', "%0A")
+}
@@ -0,0 +1,69 @@
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
+use web_sys::console;
+
+use crate::Status;
+
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
+use super::plain;
+#[cfg(not(all(target_family = "wasm", target_os = "unknown")))]
+use super::sequential;
+
+/// Opens a group in the browser's console for a task starting `depth`
+/// levels deep, which its subtasks and lines are logged inside of.
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
+pub(crate) fn start(_depth: usize, message: &str) {
+	console::group_1(&plain(message).into());
+}
+
+/// Closes the group of a task `depth` levels deep and logs how it ended,
+/// as a warning or an error if it warned or failed, so that the console
+/// can filter on them.
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
+pub(crate) fn end(_depth: usize, status: Status, message: &str) {
+	console::group_end();
+
+	let (symbol, log): (_, fn(&_)) = match status {
+		Status::Pass => ("✔", console::log_1),
+		Status::Warn => ("▲", console::warn_1),
+		Status::Fail => ("✘", console::error_1),
+		Status::Cancel => ("⦸", console::log_1),
+	};
+
+	log(&format!("{symbol} {}", plain(message)).into());
+}
+
+/// Logs a line of text inside the group of the task it was printed in.
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
+pub(crate) fn text(_depth: usize, text: &str) {
+	log(text);
+}
+
+/// Logs text to the browser's console without styles, as everything
+/// written to the terminal is in the browser, which has none.
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
+pub(crate) fn log(text: &str) {
+	let text = plain(text);
+	let text = text.trim_end_matches('\n');
+
+	if !text.is_empty() {
+		console::log_1(&text.into());
+	}
+}
+
+// outside the browser, where there is no console to group tasks in,
+// this format is written just as the sequential format is
+
+#[cfg(not(all(target_family = "wasm", target_os = "unknown")))]
+pub(crate) fn start(depth: usize, message: &str) {
+	sequential::start(depth, message);
+}
+
+#[cfg(not(all(target_family = "wasm", target_os = "unknown")))]
+pub(crate) fn end(depth: usize, status: Status, message: &str) {
+	sequential::end(depth, status, message);
+}
+
+#[cfg(not(all(target_family = "wasm", target_os = "unknown")))]
+pub(crate) fn text(depth: usize, text: &str) {
+	sequential::text(depth, text);
+}
@@ -0,0 +1,43 @@
+use crate::{output, Status};
+
+use super::{plain, sequential};
+
+/// Opens a collapsible group titled `title` for each top-level task, and
+/// prints the `line` of each subtask inside it just as the sequential
+/// format does.
+pub(crate) fn start(depth: usize, title: &str, line: &str) {
+	if depth == 0 {
+		output::emit(&format!("::group::{}\n", plain(title)));
+	} else {
+		sequential::start(depth, line);
+	}
+}
+
+/// Closes the group of a top-level task so that its ending line stays
+/// visible, and annotates warnings and failures so that the Actions UI
+/// surfaces them on the run's summary page.
+pub(crate) fn end(depth: usize, status: Status, message: &str) {
+	if depth == 0 {
+		output::emit("::endgroup::\n");
+	}
+
+	let command = match status {
+		Status::Warn => Some("warning"),
+		Status::Fail => Some("error"),
+		_ => None,
+	};
+
+	if let Some(command) = command {
+		output::emit(&format!("::{command}::{}\n", escape(&plain(message))));
+	}
+
+	sequential::end(depth, status, message);
+}
+
+/// Escapes the characters that workflow commands treat specially.
+fn escape(message: &str) -> String {
+	message
+		.replace('%', "%25")
+		.replace('\r', "%0D")
+		.replace('\n', "%0A")
+}
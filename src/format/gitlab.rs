@@ -0,0 +1,54 @@
+use std::{
+	sync::atomic::{AtomicUsize, Ordering},
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{output, Status};
+
+use super::{plain, sequential};
+
+// section names must match between the start and end markers,
+// so each top-level task is numbered as it starts, and ended
+// with the number it was given
+static SECTION: AtomicUsize = AtomicUsize::new(0);
+
+fn timestamp() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map_or(0, |time| time.as_secs())
+}
+
+/// Opens a collapsible section titled `title` for each top-level task, and
+/// prints the `line` of each subtask inside it just as the sequential
+/// format does. Returns the number of the section opened, if any, which
+/// the task is ended with.
+pub(crate) fn start(depth: usize, title: &str, line: &str) -> Option<usize> {
+	if depth > 0 {
+		sequential::start(depth, line);
+		return None;
+	}
+
+	let section = SECTION.fetch_add(1, Ordering::Relaxed) + 1;
+
+	// the header is what GitLab shows for the section when collapsed
+	output::emit(&format!(
+		"\x1b[0Ksection_start:{}:jeflog_task_{section}\r\x1b[0K{}\n",
+		timestamp(),
+		plain(title),
+	));
+
+	Some(section)
+}
+
+/// Closes `section`, the section of a top-level task, so that its ending
+/// line stays visible after the section is collapsed.
+pub(crate) fn end(depth: usize, section: Option<usize>, status: Status, message: &str) {
+	if let Some(section) = section {
+		output::emit(&format!(
+			"\x1b[0Ksection_end:{}:jeflog_task_{section}\r\x1b[0K",
+			timestamp(),
+		));
+	}
+
+	sequential::end(depth, status, message);
+}
@@ -0,0 +1,1292 @@
+use std::{
+	collections::VecDeque,
+	sync::{atomic::{AtomicBool, AtomicUsize, Ordering}, Condvar, Mutex},
+	thread::{self, JoinHandle},
+	time::Duration,
+};
+
+use crate::{
+	config::CONFIG,
+	format::plain,
+	locale,
+	output,
+	style,
+	sync::Recover,
+	term::{self, Frame},
+	time::{self, Instant},
+	Status,
+	Task,
+	TASKS,
+};
+
+// how long subtasks are shown before collapsed ones take their place
+const ROTATE_AFTER: Duration = Duration::from_secs(3);
+
+// whether the spinner thread is running, which is only changed while
+// TASKS is locked so that a task can't start just as the spinner stops
+static SPINNING: AtomicBool = AtomicBool::new(false);
+
+// the spinner thread, kept so that it can be waited on by `stop`
+static SPINNER: Mutex<Option<JoinHandle<()>>> = Mutex::new(None);
+
+// wakes the spinner thread between ticks, paired with TASKS
+static TICK: Condvar = Condvar::new();
+
+/// A line of text that has been printed some number of times in a row.
+struct Repeat {
+	text: String,
+	count: usize,
+}
+
+// whether the tree is laid out for a narrow terminal, which is decided
+// as each tree starts so that all of its rows line up with one another
+static NARROW: AtomicBool = AtomicBool::new(false);
+
+/// The width of the terminal below which trees are laid out narrowly.
+const NARROW_WIDTH: usize = 60;
+
+// how many rows at the bottom of the screen are kept for background
+// tasks, beneath the scrolling region that the tree is drawn in
+static STATUS_ROWS: AtomicUsize = AtomicUsize::new(0);
+
+// the last line of text printed, if it is still the bottom line
+static LAST_TEXT: Mutex<Option<Repeat>> = Mutex::new(None);
+
+/// The most recent lines written to a tailing writer, shown in a window
+/// of rows at the bottom of the tree that is rewritten as lines arrive.
+struct Tail {
+	owner: u64,
+	lines: VecDeque<String>,
+	rows: usize,
+}
+
+// the window of tailed lines, if it is still the bottom of the tree
+static TAIL: Mutex<Option<Tail>> = Mutex::new(None);
+
+/// Erases the window of tailed lines, if there is one, moving the tree
+/// back down over its rows so that something else can be printed beneath
+/// it. Returns the number of rows that were erased.
+fn clear_tail(tasks: &mut [Task]) -> usize {
+	let Some(tail) = TAIL.lock().recover().take() else {
+		return 0;
+	};
+
+	let mut frame = Frame::new();
+
+	for _ in 0..tail.rows {
+		frame.column(1);
+		frame.clear_line();
+		frame.up(1);
+	}
+
+	frame.emit();
+
+	shift(tasks, -(tail.rows as i32));
+
+	tail.rows
+}
+
+/// The line above the tree that shows the elapsed time and how many
+/// tasks have ended each way.
+struct Header {
+	started: Option<Instant>,
+	row_offset: Option<i32>,
+	passed: usize,
+	warned: usize,
+	failed: usize,
+}
+
+// the header, whose row is only known while a tree is being drawn
+static HEADER: Mutex<Header> = Mutex::new(Header {
+	started: None,
+	row_offset: None,
+	passed: 0,
+	warned: 0,
+	failed: 0,
+});
+
+/// Moves every task in `tasks`, along with the header, up by `rows` rows
+/// to account for lines printed beneath them.
+fn shift(tasks: &mut [Task], rows: i32) {
+	for task in tasks.iter_mut() {
+		task.shift(rows);
+	}
+
+	if let Some(offset) = &mut HEADER.lock().recover().row_offset {
+		*offset += rows;
+	}
+}
+
+/// Rewrites the header with the current elapsed time and counts,
+/// if it is being shown and is still on the screen.
+fn draw_header(frame: &mut Frame) {
+	let header = HEADER.lock().recover();
+
+	let (Some(started), Some(row)) = (header.started, header.row_offset) else {
+		return;
+	};
+
+	if row <= 0 || row as usize >= height() {
+		return;
+	}
+
+	let seconds = started.elapsed().as_secs();
+
+	let clock = match seconds / 3600 {
+		0 => format!("{:02}:{:02}", seconds / 60, seconds % 60),
+		hours => format!("{hours}:{:02}:{:02}", seconds / 60 % 60, seconds % 60),
+	};
+
+	frame.save();
+	frame.up(row as usize);
+	frame.column(1);
+	frame.clear_line();
+	frame.print(format!(
+		"\x1b[2m⏱ {clock} •\x1b[0m {} {} {} {} {} {}",
+		header.passed,
+		Status::Pass.symbol(),
+		header.warned,
+		Status::Warn.symbol(),
+		header.failed,
+		Status::Fail.symbol(),
+	));
+	frame.restore();
+}
+
+/// Draws the header one last time once its tree has ended, leaving it
+/// in place with the final counts.
+fn finish_header(frame: &mut Frame) {
+	draw_header(frame);
+	HEADER.lock().recover().row_offset = None;
+}
+
+/// Stops collapsing repeats into the last line of text, once
+/// something else has been printed beneath it.
+pub(crate) fn forget_text() {
+	*LAST_TEXT.lock().recover() = None;
+}
+
+// where the cursor was left after jeflog last drew, which is
+// compared against its position before the next draw to notice
+// lines that were written by something else in between
+#[cfg(feature = "crossterm")]
+static ANCHOR: Mutex<Option<(u16, u16)>> = Mutex::new(None);
+
+/// Records where the cursor was left after drawing.
+#[cfg(feature = "crossterm")]
+fn anchor() {
+	if CONFIG.read().recover().reanchor {
+		*ANCHOR.lock().recover() = term::position();
+	}
+}
+
+/// Checks whether anything else has written to the terminal since
+/// jeflog last drew, and if it has, shifts the row offset of every
+/// task by the number of lines that were written. Returns that shift.
+#[cfg(feature = "crossterm")]
+fn resync(tasks: &mut [Task]) -> i32 {
+	if !CONFIG.read().recover().reanchor {
+		return 0;
+	}
+
+	let anchor = ANCHOR.lock().recover().take();
+
+	let (Some((last_column, last_row)), Some((column, row))) = (anchor, term::position()) else {
+		return 0;
+	};
+
+	let rows = if row > last_row {
+		i32::from(row - last_row)
+	} else if row == last_row && column != last_column && row as usize + 1 >= height() {
+		// the terminal scrolled at its bottom row, which keeps the
+		// cursor on the same row; this can't tell how many lines were
+		// written, so it assumes the most likely case of a single line
+		1
+	} else {
+		0
+	};
+
+	shift(tasks, rows);
+	rows
+}
+
+#[cfg(not(feature = "crossterm"))]
+fn anchor() {}
+
+#[cfg(not(feature = "crossterm"))]
+fn resync(_tasks: &mut [Task]) -> i32 {
+	0
+}
+
+/// Notes that a line is about to be printed beneath every running task
+/// other than the task at index `enclosing` and the tasks it is nested
+/// under, since a connector to a later subtask of any of those tasks
+/// would be drawn across it.
+fn interrupt(tasks: &mut [Task], enclosing: Option<usize>) {
+	let mut lineage = Vec::new();
+	let mut next = enclosing.map(|index| tasks[index].id);
+
+	while let Some(id) = next {
+		lineage.push(id);
+		next = tasks.iter().find(|task| task.id == id).and_then(|task| task.parent);
+	}
+
+	for task in tasks.iter_mut() {
+		if !lineage.contains(&task.id) {
+			task.interrupted = true;
+		}
+	}
+}
+
+/// Prints the line for `task`, which isn't in `tasks` yet, beneath every
+/// running task, nested under the task at index `parent` if there is one,
+/// and records how many rows the line spans.
+pub(crate) fn start(tasks: &mut [Task], parent: Option<usize>, task: &mut Task, message: &str) {
+	resync(tasks);
+	clear_tail(tasks);
+	forget_text();
+	let mut frame = Frame::new();
+
+	if crate::tree_empty(tasks) {
+		NARROW.store(too_narrow(), Ordering::Relaxed);
+	}
+
+	if !crate::tree_empty(tasks) {
+		// adjust the offset (from bottom row) of each task
+		shift(tasks, 1);
+
+		frame.newline();
+	} else if CONFIG.read().recover().header {
+		// each tree gets a header of its own, on the row above it
+		let mut header = HEADER.lock().recover();
+		header.started.get_or_insert_with(Instant::now);
+		header.row_offset = Some(1);
+		frame.newline();
+	}
+
+	// the parent's connector is put back as it was if the line is removed
+	let sibling = parent.map_or((None, false), |parent| (tasks[parent].last_child_offset, tasks[parent].interrupted));
+
+	branch(tasks, parent, &mut frame);
+	let depth = parent.map_or(0, |parent| tasks[parent].depth + 1);
+	frame.print(format!("{} ", spinner(depth, '-')));
+	let rows = print_wrapped(&mut frame, depth * step() + 2, &fit(depth * step() + 2, message, &[]));
+
+	// every row above the task's last moves up with it
+	shift(tasks, rows as i32 - 1);
+
+	task.rows = rows;
+	task.sibling_offset = sibling.0.map(|offset| offset + rows as i32 - 1);
+	task.parent_interrupted = sibling.1;
+
+	draw_header(&mut frame);
+	frame.emit();
+	anchor();
+	spin_up();
+}
+
+/// Draws the task at index `index` in `tasks`, which has just been
+/// reopened, as running again on the line it ended on.
+pub(crate) fn reopen(tasks: &mut [Task], index: usize) {
+	resync(tasks);
+	forget_text();
+	let mut frame = Frame::new();
+
+	// a tree that had ended left the cursor beneath its last line,
+	// which is where the cursor is kept while the tree is running
+	if tasks.iter().filter(|task| !task.background).count() == 1 {
+		frame.up(1);
+	}
+
+	let task = &tasks[index];
+	let row = top(task, task.row_offset);
+
+	// a task that has scrolled off the screen can't be reached
+	if row < height() {
+		frame.save();
+		rewrite(&mut frame, task, row, Some(&spinner(task.depth, '-')), &(task.label() + &task.delta), &[]);
+
+		if row != 0 {
+			frame.restore();
+		}
+	}
+
+	draw_header(&mut frame);
+	frame.emit();
+	anchor();
+	spin_up();
+}
+
+/// Erases the `rows` lines above the cursor, or as many of them as are
+/// still on the screen, leaving the cursor at the start of the highest,
+/// where whatever is printed next replaces them.
+pub(crate) fn erase(rows: usize) {
+	forget_text();
+	let mut frame = Frame::new();
+
+	for _ in 0..rows.min(height().saturating_sub(1)) {
+		frame.up(1);
+		frame.column(1);
+		frame.clear_line();
+	}
+
+	frame.emit();
+	anchor();
+}
+
+/// Starts the spinner if it isn't running.
+fn spin_up() {
+	// wait for the previous spinner thread to exit first, which it has
+	// already begun to do, except in the browser, where threads can't
+	// be spawned
+	if cfg!(not(all(target_family = "wasm", target_os = "unknown"))) && !SPINNING.swap(true, Ordering::Relaxed) {
+		let mut spinner = SPINNER.lock().recover();
+
+		if let Some(previous) = spinner.take() {
+			_ = previous.join();
+		}
+
+		*spinner = Some(thread::spawn(spin));
+	}
+}
+
+/// Draws the connector and corner leading to a subtask's line of the task
+/// at index `parent`, which has just been started as the bottom row.
+fn branch(tasks: &mut [Task], parent: Option<usize>, frame: &mut Frame) {
+	let depth = parent.map_or(0, |parent| tasks[parent].depth + 1);
+	let connectors = CONFIG.read().recover().theme.connectors;
+	interrupt(tasks, parent);
+
+	if let Some(parent) = parent.map(|parent| &mut tasks[parent]) {
+		// the connector runs down from the previous subtask, whose corner
+		// becomes a junction, or from the parent itself if this is its first
+		// subtask; lines printed beneath the parent only get a vertical line
+		let (top, junction) = match parent.last_child_offset {
+			Some(offset) => (offset as usize, true),
+			None => (parent.row_offset as usize - 1, false),
+		};
+
+		parent.last_child_offset = Some(0);
+
+		// lines of other tasks running at the same time may have been
+		// printed beneath the parent, and the connector would cross them
+		let interrupted = std::mem::take(&mut parent.interrupted);
+
+		if top > 0 && !interrupted {
+			// the cursor can't move above the top of the screen, so
+			// connectors are only drawn as far up as the screen reaches
+			let reach = top.min(height() - 1);
+
+			frame.save();
+			frame.up(reach);
+			frame.column(corner_column(parent.depth + 1) + 1);
+			let glyph = if junction && reach == top { connectors.junction() } else { connectors.vertical() };
+			frame.print(paint(parent.depth, glyph));
+
+			for _ in 0..reach {
+				frame.left(1);
+				frame.down(1);
+				frame.print(paint(parent.depth, connectors.vertical()));
+			}
+
+			frame.restore();
+		}
+	}
+
+	if depth > 0 {
+		frame.print(format!("{}{} ", " ".repeat(corner_column(depth)), paint(depth - 1, connectors.corner())));
+	}
+}
+
+/// Returns how many rows of the screen the tree is drawn in, which is all
+/// of them but those kept for background tasks.
+fn height() -> usize {
+	term::height().saturating_sub(STATUS_ROWS.load(Ordering::Relaxed))
+}
+
+/// Returns whether the tree is laid out for a terminal narrower than
+/// [`NARROW_WIDTH`], with less indentation, no durations, and rows cut
+/// short to fit rather than wrapped, which would throw off every row
+/// above them.
+pub(crate) fn narrow() -> bool {
+	NARROW.load(Ordering::Relaxed)
+}
+
+/// Returns whether the terminal is narrow enough now that a tree started
+/// on it would be laid out narrowly.
+pub(crate) fn too_narrow() -> bool {
+	term::width() < NARROW_WIDTH
+}
+
+/// Returns how many columns each level of depth is indented by.
+fn step() -> usize {
+	if narrow() { 3 } else { 5 }
+}
+
+/// Returns the column, counting from zero, at which the corner leading
+/// to a subtask `depth` levels deep starts, beneath the connector of its
+/// parent.
+fn corner_column(depth: usize) -> usize {
+	(depth - 1) * step() + step() - 3
+}
+
+/// Returns how many columns are left for text that starts `indent` columns
+/// in, leaving the last column empty, since some terminals wrap as soon as
+/// it is written.
+fn room(indent: usize) -> usize {
+	term::width().saturating_sub(indent + 1)
+}
+
+/// Prints `text`, which starts `indent` columns in, wrapping it onto as
+/// many rows as it needs rather than letting the terminal wrap it, so that
+/// every row after the first starts `indent` columns in as well and stays
+/// clear of the connectors to its left. Returns how many rows it spans.
+fn print_wrapped(frame: &mut Frame, indent: usize, text: &str) -> usize {
+	let pieces = style::wrap(text, room(indent));
+
+	for (index, piece) in pieces.iter().enumerate() {
+		if index > 0 {
+			frame.newline();
+			frame.print(" ".repeat(indent));
+		}
+
+		frame.print(piece);
+	}
+
+	pieces.len()
+}
+
+/// Returns how many rows above the cursor the top row of `task` is, whose
+/// last row is `row` rows above it.
+fn top(task: &Task, row: i32) -> usize {
+	row as usize + task.rows - 1
+}
+
+/// Rewrites the line of `task`, whose top row is `row` rows above the
+/// cursor, with `symbol` in place of its spinner if one is given and then
+/// `message` and its `extras`, as [`fit`] would. Every row the line spans
+/// is cleared, so nothing is left of it where the new line is shorter,
+/// and the new line is cut short rather than wrapped past them, which
+/// would write over the row beneath.
+fn rewrite(frame: &mut Frame, task: &Task, row: usize, symbol: Option<&str>, message: &str, extras: &[String]) {
+	let indent = task.depth * step() + 2;
+	let room = room(indent);
+	let pieces = style::wrap(&shorten(room * task.rows, message, extras), room);
+
+	frame.up(row);
+
+	match symbol {
+		Some(symbol) => {
+			frame.column(indent - 1);
+			frame.print(format!("{symbol} "));
+		},
+		None => frame.column(indent + 1),
+	}
+
+	for index in 0..task.rows {
+		// the connectors to the left of each row are left alone
+		if index > 0 {
+			frame.down(1);
+			frame.column(indent + 1);
+		}
+
+		frame.clear_line();
+
+		if let Some(piece) = pieces.get(index) {
+			frame.print(piece);
+		}
+	}
+}
+
+/// Fits a row whose message starts `indent` columns in within the terminal,
+/// if the tree is laid out narrowly, by leaving out the `extras` shown after
+/// the message from the last, which matter least, and then cutting the
+/// message itself short. Rows of other trees are left as they are.
+fn fit(indent: usize, message: &str, extras: &[String]) -> String {
+	if !narrow() {
+		return message.to_owned() + &extras.concat();
+	}
+
+	shorten(room(indent), message, extras)
+}
+
+/// Fits `message` and its `extras` within `room` columns, leaving out the
+/// extras from the last and then cutting the message itself short.
+fn shorten(room: usize, message: &str, extras: &[String]) -> String {
+	let width = |text: &str| style::width(&plain(text));
+	let mut kept = extras.len();
+
+	while kept > 0 && width(message) + extras[..kept].iter().map(|extra| width(extra)).sum::<usize>() > room {
+		kept -= 1;
+	}
+
+	if width(message) > room {
+		return format!("{}…", style::truncate(&plain(message), room.saturating_sub(1)));
+	}
+
+	message.to_owned() + &extras[..kept].concat()
+}
+
+/// Wraps `text` in the color for tasks `depth` levels deep, if
+/// spinners and connectors are colored by depth.
+fn paint(depth: usize, text: &str) -> String {
+	match depth_color(depth) {
+		Some(code) => format!("\x1b[{code}m{text}\x1b[0m"),
+		None => text.to_owned(),
+	}
+}
+
+/// Draws the spinner of a task `depth` levels deep, in bold yellow
+/// unless spinners are colored by depth.
+fn spinner(depth: usize, spinner: char) -> String {
+	format!("\x1b[{};1m{spinner}\x1b[0m", depth_color(depth).unwrap_or(33))
+}
+
+/// The color of the palette for tasks `depth` levels deep, if spinners
+/// and connectors are colored by depth.
+fn depth_color(depth: usize) -> Option<u8> {
+	let theme = &CONFIG.read().recover().theme;
+
+	if !theme.depth_colors || theme.depth_palette.is_empty() {
+		return None;
+	}
+
+	Some(theme.depth_palette[depth % theme.depth_palette.len()].code())
+}
+
+/// Wakes the spinner thread so that it stops if there are no more tasks,
+/// then waits for it to exit.
+pub(crate) fn stop() {
+	// notifying while TASKS is locked means the spinner is either waiting
+	// for the next tick or yet to see whether any tasks are left
+	let tasks = TASKS.lock().recover();
+	TICK.notify_all();
+	drop(tasks);
+
+	let spinner = SPINNER.lock().recover().take();
+
+	if let Some(spinner) = spinner {
+		// the spinner never panics, so there is nothing to report
+		_ = spinner.join();
+	}
+}
+
+/// Redraws the message of the task at `index` in `tasks`, followed
+/// by its progress bar if it has reported any progress, and how long
+/// it has been running if that is shown.
+pub(crate) fn redraw(tasks: &mut [Task], index: usize) {
+	if tasks[index].background {
+		status(tasks);
+		return;
+	}
+
+	resync(tasks);
+
+	let task = &mut tasks[index];
+	let extras = extras(task);
+	let row = top(task, task.row_offset);
+	let mut frame = Frame::new();
+
+	// a task that has scrolled off the screen can't be reached
+	if row >= height() {
+		return;
+	}
+
+	frame.save();
+	rewrite(&mut frame, task, row, None, &(task.label() + &task.delta), &extras);
+
+	// the last task's line ends where the cursor should be
+	if row != 0 {
+		frame.restore();
+	}
+
+	frame.emit();
+	anchor();
+}
+
+/// Returns the parts of the row of `task` after its message, from most to
+/// least important: its progress bar, its note, and how long it has been
+/// running if that is shown, which is noted as shown.
+fn extras(task: &mut Task) -> Vec<String> {
+	let elapsed = task.started.elapsed().as_secs();
+	let live = CONFIG.read().recover().live_elapsed && elapsed > 0;
+	task.shown_elapsed = elapsed;
+
+	let mut extras = Vec::new();
+
+	if let Some(progress) = task.progress {
+		extras.push(format!(" {}", bar(progress)));
+	}
+
+	if !task.note.is_empty() {
+		extras.push(format!(" \x1b[2m{}\x1b[0m", task.note));
+	}
+
+	if live && !narrow() {
+		let elapsed = time::format(Duration::from_secs(elapsed));
+		extras.push(format!(" \x1b[2m({elapsed})\x1b[0m"));
+	}
+
+	extras
+}
+
+/// Draws every running task in `tasks` again from the start of the
+/// cursor's row, as a tree of its own, since the rows it was drawn on can
+/// no longer be trusted. Lines printed between tasks and the rows of tasks
+/// that have ended aren't kept, so they aren't drawn again.
+pub(crate) fn repaint(tasks: &mut [Task]) {
+	*TAIL.lock().recover() = None;
+	forget_text();
+	NARROW.store(too_narrow(), Ordering::Relaxed);
+
+	let mut frame = Frame::new();
+	let mut first = true;
+
+	frame.column(1);
+	frame.clear_line();
+
+	// the header keeps its row above the tree, if the tree has one
+	if CONFIG.read().recover().header && HEADER.lock().recover().started.is_some() {
+		HEADER.lock().recover().row_offset = Some(1);
+		frame.newline();
+	}
+
+	for task in tasks.iter_mut() {
+		task.row_offset = 0;
+		task.last_child_offset = None;
+		task.summary_offset = None;
+		task.interrupted = false;
+	}
+
+	for index in 0..tasks.len() {
+		if tasks[index].hidden || tasks[index].collapsed || tasks[index].background {
+			continue;
+		}
+
+		let (earlier, rest) = tasks.split_at_mut(index);
+		let task = &mut rest[0];
+
+		if !first {
+			shift(earlier, 1);
+			frame.newline();
+		}
+
+		first = false;
+
+		let parent = task.parent.and_then(|id| earlier.iter().position(|parent| parent.id == id));
+		branch(earlier, crate::shown_parent(earlier, parent), &mut frame);
+
+		let indent = task.depth * step() + 2;
+		let extras = extras(task);
+
+		if task.stalled {
+			frame.print("\x1b[31;1m!\x1b[0m ");
+		} else {
+			frame.print(format!("{} ", spinner(task.depth, '-')));
+		}
+
+		task.rows = print_wrapped(&mut frame, indent, &fit(indent, &(task.label() + &task.delta), &extras));
+		shift(earlier, task.rows as i32 - 1);
+	}
+
+	draw_header(&mut frame);
+	frame.emit();
+	anchor();
+	status(tasks);
+}
+
+/// Draws every background task in `tasks` on a row of its own at the
+/// bottom of the screen, which stays where it is as the tree scrolls above
+/// it. Rows are taken for background tasks that have started since this
+/// was last called, and given back for those that have ended.
+pub(crate) fn status(tasks: &mut [Task]) {
+	let height = term::height();
+	let mut background = tasks.iter_mut().filter(|task| task.background && !task.hidden).collect::<Vec<_>>();
+
+	// at most a third of the screen is given to them
+	let rows = background.len().min(height / 3);
+	let kept = STATUS_ROWS.swap(rows, Ordering::Relaxed);
+	let mut frame = Frame::new();
+
+	// rows taken from the bottom of the screen may have been beneath the
+	// cursor, which is moved clear of them, scrolling the tree up if it
+	// must, and back to the same row of the tree
+	if rows > kept {
+		for _ in kept..rows {
+			frame.newline();
+		}
+
+		frame.up(rows - kept);
+	}
+
+	frame.save();
+
+	if rows != kept {
+		frame.region(height - rows);
+	}
+
+	// rows that were given back are cleared, which are now beneath the tree
+	for row in height - rows.max(kept)..height {
+		frame.row(row + 1);
+		frame.clear_line();
+
+		if let Some(task) = (row + rows).checked_sub(height).and_then(|index| background.get_mut(index)) {
+			let extras = extras(task);
+			frame.print(format!("\x1b[33;1m●\x1b[0m {}", shorten(room(2), &task.label(), &extras)));
+		}
+	}
+
+	frame.restore();
+	frame.emit();
+
+	// the lines that were moved back over didn't move the tree
+	output::unwritten(rows.saturating_sub(kept) as u64);
+}
+
+/// Prints how a background task ended beneath the tree, since the row
+/// it was shown on is given back, just as a line of text would be.
+pub(crate) fn end_background(tasks: &mut [Task], status: Status, message: &str) {
+	self::status(tasks);
+	text(tasks, None, 0, &format!("{} {message}", status.symbol()));
+}
+
+/// Prints a line of text beneath every running task in `tasks`, shifting
+/// them up to make room for it. The text belongs to the task at index
+/// `enclosing`, if there is one, and is indented to line up with subtasks
+/// `depth` levels deep.
+pub(crate) fn text(tasks: &mut [Task], enclosing: Option<usize>, depth: usize, text: &str) {
+	clear_tail(tasks);
+	let mut last = LAST_TEXT.lock().recover();
+	let mut frame = Frame::new();
+
+	if resync(tasks) != 0 {
+		*last = None;
+	}
+
+	let indent = depth * step();
+
+	// a line identical to the one just printed is collapsed into it,
+	// as long as nothing else has been printed beneath it since
+	if let Some(repeat) = last.as_mut().filter(|repeat| repeat.text == text) {
+		repeat.count += 1;
+
+		// without any tasks, the cursor is at the start of the next line
+		if crate::tree_empty(tasks) {
+			frame.up(1);
+		}
+
+		frame.column(indent + 1);
+		frame.clear_line();
+		frame.print(shorten(room(indent), text, &[format!(" \x1b[2m(×{})\x1b[0m", repeat.count)]));
+
+		if crate::tree_empty(tasks) {
+			frame.newline();
+		}
+
+		frame.emit();
+		anchor();
+		return;
+	}
+
+	let line = fit(indent, text, &[]);
+
+	// only a line on a single row can be rewritten with its count, which
+	// is left out if it wouldn't fit in that row either
+	if CONFIG.read().recover().collapse_repeats && style::width(&plain(&line)) <= room(indent) {
+		*last = Some(Repeat { text: text.to_owned(), count: 1 });
+	} else {
+		*last = None;
+	}
+
+	if crate::tree_empty(tasks) {
+		// without any tasks, the cursor is already at the start of a line
+		frame.print(text);
+		frame.newline();
+	} else {
+		interrupt(tasks, enclosing);
+
+		// the text lines up with the spinners of subtasks, which keeps it
+		// clear of the connectors drawn when the next subtask starts
+		frame.newline();
+		frame.print(" ".repeat(indent));
+
+		// a line too wide for the terminal moves the tree up by every
+		// row it's wrapped onto
+		let rows = print_wrapped(&mut frame, indent, &line);
+		shift(tasks, rows as i32);
+	}
+
+	frame.emit();
+	anchor();
+}
+
+/// Prints a question beneath every running task in `tasks`, just as
+/// [`text`] prints a line, leaving the cursor after it for the answer.
+pub(crate) fn ask(tasks: &mut [Task], enclosing: Option<usize>, question: &str) {
+	resync(tasks);
+	clear_tail(tasks);
+	forget_text();
+	let indent = enclosing.map_or(0, |enclosing| (tasks[enclosing].depth + 1) * step());
+	let mut frame = Frame::new();
+
+	if !crate::tree_empty(tasks) {
+		interrupt(tasks, enclosing);
+
+		shift(tasks, 1);
+
+		frame.newline();
+	}
+
+	frame.print(format!("{}{question} ", " ".repeat(indent)));
+	frame.emit();
+}
+
+/// Puts the cursor back where the task tree expects it once a question
+/// has been answered, depending on whether the answer's newline was
+/// echoed by the terminal.
+pub(crate) fn answered(tasks: &[Task], echoed: bool) {
+	let mut frame = Frame::new();
+
+	// the line of the question is now the bottom line of the tree,
+	// while without any tasks, the cursor belongs on the next line
+	match (crate::tree_empty(tasks), echoed) {
+		(false, true) => frame.up(1),
+		(true, false) => frame.newline(),
+		_ => {},
+	}
+
+	frame.emit();
+	anchor();
+}
+
+/// Shows how many subtasks of the task at index `parent` are collapsed
+/// on a summary line beneath it, which is printed beneath the tree when
+/// it's first needed and rewritten in place from then on.
+pub(crate) fn summarize(tasks: &mut [Task], parent: usize) {
+	let id = tasks[parent].id;
+	let indent = (tasks[parent].depth + 1) * step();
+
+	let collapsed = tasks.iter()
+		.filter(|task| task.parent == Some(id) && task.collapsed)
+		.count();
+
+	let summary = match (collapsed, tasks[parent].unshown) {
+		(0, 0) => String::new(),
+		(0, unshown) => format!("\x1b[2m… {}\x1b[0m", locale::text("{count} more done", &[("count", &unshown)])),
+		(collapsed, _) => format!("\x1b[2m… {}\x1b[0m", locale::text("+{count} more running", &[("count", &collapsed)])),
+	};
+
+	let mut frame = Frame::new();
+
+	match tasks[parent].summary_offset {
+		None if collapsed == 0 => return,
+		None => {
+			resync(tasks);
+			clear_tail(tasks);
+			forget_text();
+			interrupt(tasks, Some(parent));
+
+			shift(tasks, 1);
+
+			tasks[parent].summary_offset = Some(0);
+			frame.newline();
+			frame.print(format!("{}{summary}", " ".repeat(indent)));
+		},
+		Some(offset) => {
+			let row = (offset + resync(tasks)) as usize;
+
+			if row >= height() {
+				return;
+			}
+
+			// the connectors to the left of the summary are left alone
+			frame.save();
+			frame.up(row);
+			frame.column(indent + 1);
+			frame.clear_line();
+			frame.print(summary);
+
+			if row != 0 {
+				frame.restore();
+			}
+		},
+	}
+
+	frame.emit();
+	anchor();
+}
+
+/// Shows a line of text in the window of recent lines beneath the task
+/// at index `enclosing`, keeping at most `capacity` of them. The window
+/// is erased once anything else is printed beneath the tree.
+pub(crate) fn tail(tasks: &mut [Task], enclosing: usize, capacity: usize, text: &str) {
+	resync(tasks);
+
+	let owner = tasks[enclosing].id;
+
+	if TAIL.lock().recover().as_ref().is_some_and(|tail| tail.owner != owner) {
+		clear_tail(tasks);
+	}
+
+	forget_text();
+
+	let indent = (tasks[enclosing].depth + 1) * step();
+	let mut tail = TAIL.lock().recover();
+	let tail = tail.get_or_insert_with(|| Tail { owner, lines: VecDeque::new(), rows: 0 });
+
+	// lines are cut short rather than wrapped, since every line must
+	// take up exactly one row, and the window must fit on the screen
+	let room = term::width().saturating_sub(indent + 1);
+	let capacity = capacity.clamp(1, height().saturating_sub(1).max(1));
+
+	tail.lines.push_back(style::truncate(&plain(text), room));
+
+	while tail.lines.len() > capacity {
+		tail.lines.pop_front();
+	}
+
+	let mut frame = Frame::new();
+
+	if tail.rows < tail.lines.len() {
+		interrupt(tasks, Some(enclosing));
+
+		shift(tasks, 1);
+
+		frame.newline();
+		tail.rows += 1;
+	}
+
+	if tail.rows > 1 {
+		frame.up(tail.rows - 1);
+	}
+
+	for (index, line) in tail.lines.iter().enumerate() {
+		if index > 0 {
+			frame.down(1);
+		}
+
+		frame.column(1);
+		frame.clear_line();
+		frame.print(format!("{}\x1b[2m{line}\x1b[0m", " ".repeat(indent)));
+	}
+
+	frame.emit();
+	anchor();
+}
+
+/// Draws `current` out of `total` as a bar, followed by a percentage.
+fn bar((current, total): (u64, u64)) -> String {
+	const WIDTH: u64 = 20;
+
+	let filled = crate::share(current, total, WIDTH);
+	let percent = crate::share(current, total, 100);
+
+	format!(
+		"\x1b[32m{}\x1b[90m{}\x1b[0m {percent:>3}%",
+		"━".repeat(filled as usize),
+		"━".repeat((WIDTH - filled) as usize),
+	)
+}
+
+/// Replaces the spinner of `task`, which has just been removed from
+/// `tasks`, with the symbol for `status` and rewrites its message.
+pub(crate) fn end(tasks: &mut [Task], task: &Task, status: Status, message: &str) {
+	{
+		let mut header = HEADER.lock().recover();
+
+		match status {
+			Status::Pass => header.passed += 1,
+			Status::Warn => header.warned += 1,
+			Status::Fail => header.failed += 1,
+			Status::Cancel => {},
+		}
+	}
+
+	// a collapsed task never had a row, and passing isn't worth one
+	if task.collapsed && matches!(status, Status::Pass | Status::Cancel) {
+		if crate::tree_empty(tasks) {
+			let mut frame = Frame::new();
+			finish_header(&mut frame);
+			frame.newline();
+			frame.emit();
+		}
+
+		return;
+	}
+
+	// the task may own the window of tailed lines, which goes with it,
+	// and its ending may need to be printed beneath the tree
+	let erased = clear_tail(tasks) as i32;
+	let row = top(task, task.row_offset - erased + resync(tasks));
+	forget_text();
+	let mut frame = Frame::new();
+
+	if task.collapsed || row >= height() {
+		// the task's line has scrolled off the screen, and moving the
+		// cursor up to it would overwrite whichever line is at the top,
+		// so its ending is printed as a new line instead, as it is for
+		// a collapsed task without a line of its own
+		shift(tasks, 1);
+
+		frame.newline();
+
+		// a parent that has already ended gets no connector
+		let parent = tasks.iter().position(|parent| Some(parent.id) == task.parent);
+
+		match crate::shown_parent(tasks, parent) {
+			Some(parent) => branch(tasks, Some(parent), &mut frame),
+			None if task.depth > 0 => {
+				let corner = CONFIG.read().recover().theme.connectors.corner();
+				frame.print(format!("{}{} ", " ".repeat(corner_column(task.depth)), paint(task.depth - 1, corner)));
+			},
+			None => {},
+		}
+
+		frame.print(format!("{} ", status.symbol()));
+		let rows = print_wrapped(&mut frame, task.depth * step() + 2, &fit(task.depth * step() + 2, message, &[]));
+		shift(tasks, rows as i32 - 1);
+	} else {
+		// replace spinner with symbol:
+		//   save cursor's current position
+		//   move the cursor up to the task's top row
+		//   print the symbol replacing the spinner
+		//   clear every row the old line spans
+		//   print the ending message in place of the old message
+		frame.save();
+		rewrite(&mut frame, task, row, Some(status.symbol()), message, &[]);
+
+		// restore the cursor's position if not the last task
+		if row != 0 {
+			frame.restore();
+		}
+	}
+
+	if crate::tree_empty(tasks) {
+		finish_header(&mut frame);
+		frame.newline();
+	} else {
+		draw_header(&mut frame);
+	}
+
+	frame.emit();
+	anchor();
+}
+
+/// Removes the line of `task`, which has just passed and been taken out of
+/// `tasks`, as though it had never started, moving the rows beneath it up
+/// in its place. Returns whether it was removed, which it can't be once it
+/// has scrolled off the screen, in which case it should end as usual.
+pub(crate) fn remove(tasks: &mut [Task], task: &mut Task) -> bool {
+	let removed = take_out(tasks, task);
+
+	if removed {
+		HEADER.lock().recover().passed += 1;
+	}
+
+	removed
+}
+
+/// Removes the rows of `task`, which has been taken out of `tasks`, just
+/// as [`remove`] does, whether or not it has ended.
+pub(crate) fn take_out(tasks: &mut [Task], task: &mut Task) -> bool {
+	if task.collapsed {
+		return false;
+	}
+
+	let erased = clear_tail(tasks) as i32;
+	task.row_offset += resync(tasks) - erased;
+
+	let row = top(task, task.row_offset);
+
+	if row >= height() {
+		return false;
+	}
+
+	forget_text();
+
+	let row = row as i32;
+	let connectors = CONFIG.read().recover().theme.connectors;
+	let mut frame = Frame::new();
+
+	// whatever was branched from the line is branched from whatever the
+	// line was branched from instead, and if it was its parent's last
+	// subtask, the connector down to it is taken back up
+	for other in tasks.iter_mut() {
+		if other.sibling_offset == Some(row) {
+			other.sibling_offset = task.sibling_offset;
+			other.parent_interrupted |= task.parent_interrupted;
+		}
+
+		if other.last_child_offset != Some(row) {
+			continue;
+		}
+
+		let parent = other;
+		parent.last_child_offset = task.sibling_offset;
+		parent.interrupted |= task.parent_interrupted;
+
+		if task.parent_interrupted {
+			continue;
+		}
+
+		let from = task.sibling_offset.unwrap_or(parent.row_offset - 1);
+		let reach = from.min(height() as i32 - 1);
+
+		if reach > row {
+			// the previous subtask's junction becomes a corner again
+			let glyph = if task.sibling_offset == Some(reach) {
+				paint(parent.depth, connectors.corner())
+			} else {
+				" ".to_owned()
+			};
+
+			let column = corner_column(parent.depth + 1) + 1;
+			frame.save();
+			frame.up(reach as usize);
+			frame.column(column);
+			frame.print(glyph);
+
+			for _ in row + 1..reach {
+				frame.down(1);
+				frame.column(column);
+				frame.print(" ");
+			}
+
+			frame.restore();
+		}
+	}
+
+	frame.up(row as usize);
+	frame.column(1);
+	frame.delete(task.rows);
+
+	// rows above the line are that much closer to the bottom row, except
+	// when there isn't one left, and the cursor stays on the emptied row
+	let empty = crate::tree_empty(tasks);
+	let moved = (task.rows - usize::from(empty)) as i32;
+
+	let lower = |offset: &mut i32| {
+		if *offset > row {
+			*offset -= moved;
+		}
+	};
+
+	for other in tasks.iter_mut() {
+		let offsets = [
+			Some(&mut other.row_offset),
+			other.last_child_offset.as_mut(),
+			other.summary_offset.as_mut(),
+			other.sibling_offset.as_mut(),
+		];
+
+		offsets.into_iter().flatten().for_each(lower);
+	}
+
+	if let Some(offset) = &mut HEADER.lock().recover().row_offset {
+		lower(offset);
+	}
+
+	// the cursor goes back to the bottom row, or stays on the emptied row
+	// if there are no more, just as it would be after the tree's last line
+	if task.row_offset > 0 {
+		frame.down(task.row_offset as usize - 1);
+	} else if !empty {
+		frame.up(1);
+	}
+
+	if empty {
+		finish_header(&mut frame);
+	} else {
+		draw_header(&mut frame);
+	}
+
+	frame.emit();
+	anchor();
+
+	output::unwritten(moved as u64);
+	true
+}
+
+fn spin() {
+	let mut spinner = '-';
+	let mut rotated = Instant::now();
+	let mut tasks = TASKS.lock().recover();
+
+	loop {
+		// stop spinning if there are no more tasks, while TASKS is still
+		// locked, so that a task started after this starts a new spinner
+		if tasks.is_empty() {
+			SPINNING.store(false, Ordering::Relaxed);
+			break;
+		}
+
+		resync(&mut tasks);
+
+		// subtasks collapsed past the limit take turns being shown
+		if rotated.elapsed() >= ROTATE_AFTER {
+			crate::rotate(&mut tasks);
+			rotated = Instant::now();
+		}
+
+		let height = height();
+		let mut frame = Frame::new();
+
+		for task in tasks.iter().filter(|task| !task.hidden && !task.collapsed && !task.background) {
+			let row = top(task, task.row_offset);
+
+			// tasks that have scrolled off the screen can't be reached
+			if row >= height {
+				continue;
+			}
+
+			// replace spinner with new spinner:
+			//   save the cursor's current position
+			//   move the cursor up to correct row
+			//   move the cursor to correct column
+			//   print the updated spinner character in bold yellow
+			//   restore saved cursor position
+			frame.save();
+			frame.up(row);
+			frame.column(task.depth * step() + 1);
+
+			if task.stalled {
+				frame.print("\x1b[31;1m!\x1b[0m");
+			} else {
+				frame.print(self::spinner(task.depth, spinner));
+			}
+
+			frame.restore();
+		}
+
+		draw_header(&mut frame);
+
+		// most systems flush stdout by newlines, and since
+		// no newlines were printed, emit flushes explicitly
+		frame.emit();
+		anchor();
+
+		// elapsed times are redrawn whenever another second has passed
+		if CONFIG.read().recover().live_elapsed {
+			for index in 0..tasks.len() {
+				let task = &tasks[index];
+
+				if !task.hidden && !task.collapsed && task.started.elapsed().as_secs() != task.shown_elapsed {
+					redraw(&mut tasks, index);
+				}
+			}
+		}
+
+		// update spinner to next spinner character (clockwise)
+		spinner = match spinner {
+			'-' => '\\',
+			'\\' => '|',
+			'|' => '/',
+			'/' => '-',
+			_ => '-', // this is not possible, but Rust demands it
+		};
+
+		// wait for the next tick, unlocking tasks so other threads may use it
+		let tick = CONFIG.read().recover().tick;
+		tasks = TICK.wait_timeout(tasks, tick).recover().0;
+	}
+}
@@ -0,0 +1,68 @@
+use std::fmt::Write;
+
+use crate::{json, output, time::Instant, Event, Session, Status};
+
+use super::{plain, SLOWEST};
+
+/// Prints an event of the task tree as a line of JSON.
+pub(crate) fn event(event: &Event) {
+	output::emit(&(event.to_json() + "\n"));
+}
+
+/// Prints a question asked `depth` levels deep, beneath the task with id
+/// `task` if there is one, as a line of JSON, which is answered on a line
+/// of standard input.
+pub(crate) fn ask(task: Option<u64>, depth: usize, question: &str) {
+	output::emit(&format!(
+		"{{\"event\":\"ask\",\"task\":{},\"depth\":{depth},\"time_ms\":{},\"question\":{}}}\n",
+		task.map_or_else(|| String::from("null"), |id| id.to_string()),
+		now(),
+		json::string(&plain(question)),
+	));
+}
+
+/// Prints a status given while no task is running, as a task without
+/// an id ending after no time at all.
+pub(crate) fn report(status: Status, message: &str) {
+	output::emit(&format!(
+		"{{\"event\":\"end\",\"id\":null,\"parent\":null,\"depth\":0,\"time_ms\":{},\"status\":\"{}\",\"duration_ms\":0,\"message\":{}}}\n",
+		now(),
+		status.name(),
+		json::string(&plain(message)),
+	));
+}
+
+/// Prints a summary of the whole run as the last line of JSON, so that
+/// whatever reads the output doesn't need to add up the events itself.
+pub(crate) fn summary(session: &Session) {
+	let [pass, warn, fail, cancel] = session.counts();
+
+	let mut out = format!(
+		"{{\"event\":\"summary\",\"time_ms\":{},\"counts\":{{\"pass\":{pass},\"warn\":{warn},\"fail\":{fail},\"cancel\":{cancel}}},\"duration_ms\":{},\"slowest\":[",
+		now(),
+		session.elapsed().as_millis(),
+	);
+
+	for (index, found) in session.slowest(SLOWEST).iter().enumerate() {
+		if index > 0 {
+			out.push(',');
+		}
+
+		let path = found.path.iter().map(|message| json::string(&plain(message))).collect::<Vec<_>>();
+
+		_ = write!(
+			out,
+			"{{\"path\":[{}],\"status\":\"{}\",\"duration_ms\":{}}}",
+			path.join(","),
+			found.record.status.name(),
+			found.record.duration.as_millis(),
+		);
+	}
+
+	output::emit(&(out + "]}\n"));
+}
+
+/// The time of an event printed now, in milliseconds, as events give it.
+fn now() -> u128 {
+	Instant::now().since_origin().as_millis()
+}
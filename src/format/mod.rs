@@ -0,0 +1,373 @@
+use std::{
+	borrow::Cow,
+	env,
+	fs,
+	process,
+	sync::OnceLock,
+};
+
+use crate::{config::CONFIG, output, style::{self, Piece}, sync::Recover};
+
+pub(crate) mod accessible;
+pub(crate) mod azure;
+pub(crate) mod console;
+pub(crate) mod github;
+pub(crate) mod gitlab;
+pub(crate) mod interactive;
+pub(crate) mod json;
+pub(crate) mod porcelain;
+pub(crate) mod sequential;
+pub(crate) mod teamcity;
+
+/// How tasks are written to the terminal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+	/// Tasks are drawn as a tree with animated spinners,
+	/// and each task's line is rewritten in place when it ends.
+	Interactive,
+	/// Every start and end of a task is printed as a new line,
+	/// without animation or cursor movement. This is suited to
+	/// dumb terminals, CI logs, and output redirected to a file.
+	Sequential,
+	/// Like the sequential format, but each top-level task is wrapped
+	/// in a collapsible group, and warnings and failures are reported
+	/// as GitHub Actions annotations.
+	GitHub,
+	/// Like the sequential format, but each top-level task is wrapped
+	/// in a collapsible section of the GitLab CI job log.
+	GitLab,
+	/// Reports tasks to TeamCity as nested blocks of the build log,
+	/// with failures reported as build problems.
+	TeamCity,
+	/// Like the sequential format, but each top-level task is wrapped
+	/// in a collapsible group, and warnings and failures are logged as
+	/// Azure Pipelines issues.
+	Azure,
+	/// Every start and end of a task is printed as a new line describing
+	/// it in words, as in `Task 'building' succeeded after 3s: built`,
+	/// without symbols, colors, or cursor movement, so that the output
+	/// can be followed with a screen reader.
+	Accessible,
+	/// Every event is printed as a line of tab-separated fields meant to be
+	/// read by scripts, whose syntax is kept stable between versions:
+	///
+	/// - `start <depth> <message>` when a task starts
+	/// - `end <depth> <status> <milliseconds> <message>` when a task ends,
+	///   where the status is one of `pass`, `warn`, `fail`, or `cancel`
+	/// - `text <depth> <text>` for a line of text, at the depth a subtask
+	///   of the task it was printed in would have
+	/// - `ask <depth> <question>` for a question, answered on standard input
+	/// - `summary <passed> <warned> <failed> <cancelled> <milliseconds>`
+	///   once the run [finishes](crate::finish), counting every task and
+	///   subtask, followed by `slowest <status> <milliseconds> <message>`
+	///   for each of the five tasks that took longest
+	///
+	/// Depths start at 0 for top-level tasks. Messages are the last field,
+	/// without styles, and with any tabs and line breaks replaced by spaces,
+	/// so that each event is always one line. A status given while no task
+	/// is running is printed as a task ending at depth 0 after 0
+	/// milliseconds. Lines may gain fields before the message in future
+	/// versions, so scripts should read it as whatever follows the fields
+	/// they know of.
+	Porcelain,
+	/// Every task is logged to the browser's console as a collapsible
+	/// group holding its subtasks and lines, with warnings and failures
+	/// logged as warnings and errors. This is the format in the browser,
+	/// on `wasm32-unknown-unknown`, and is written as the sequential
+	/// format is anywhere else.
+	Console,
+	/// Every event is printed as a line of JSON, as written by
+	/// [`Event::to_json`](crate::Event::to_json), for programs that read
+	/// the output rather than people. A question is printed as
+	/// `{"event":"ask","task":null,"depth":0,"time_ms":0,"question":"..."}`
+	/// and answered on standard input, and a status given while no task is
+	/// running as an `end` event whose `id` is `null`. Every line has the
+	/// `time_ms` it was printed, just as events do.
+	///
+	/// Once the run [finishes](crate::finish), a last line summarizes it as
+	/// `{"event":"summary","time_ms":0,"counts":{"pass":0,"warn":0,"fail":0,
+	/// "cancel":0},"duration_ms":0,"slowest":[...]}`, counting every task and subtask,
+	/// where `slowest` lists the five tasks that took longest, each with the
+	/// `path` of messages leading to it, its `status`, and its `duration_ms`.
+	Json,
+}
+
+/// How many of the slowest tasks are listed at the end of a run in the
+/// JSON and porcelain formats.
+pub(crate) const SLOWEST: usize = 5;
+
+/// Environment variables set by common CI providers.
+const CI_VARIABLES: &[&str] = &[
+	"CI",
+	"GITHUB_ACTIONS",
+	"GITLAB_CI",
+	"TF_BUILD",
+	"TEAMCITY_VERSION",
+	"BUILDKITE",
+	"CIRCLECI",
+	"TRAVIS",
+	"JENKINS_URL",
+];
+
+impl Format {
+	/// The name of the format in `JEFLOG_FORMAT`.
+	fn name(self) -> &'static str {
+		match self {
+			Self::Interactive => "interactive",
+			Self::Sequential => "sequential",
+			Self::GitHub => "github",
+			Self::GitLab => "gitlab",
+			Self::TeamCity => "teamcity",
+			Self::Azure => "azure",
+			Self::Accessible => "accessible",
+			Self::Porcelain => "porcelain",
+			Self::Console => "console",
+			Self::Json => "json",
+		}
+	}
+
+	/// Finds the format with a name, ignoring case.
+	pub(crate) fn from_name(name: &str) -> Option<Self> {
+		[
+			Self::Interactive,
+			Self::Sequential,
+			Self::GitHub,
+			Self::GitLab,
+			Self::TeamCity,
+			Self::Azure,
+			Self::Accessible,
+			Self::Porcelain,
+			Self::Console,
+			Self::Json,
+		]
+			.into_iter()
+			.find(|format| name.eq_ignore_ascii_case(format.name()))
+	}
+}
+
+/// Returns whether a CI provider's environment variables are set.
+fn ci() -> bool {
+	// CI=false is sometimes set explicitly to mean "not CI"
+	CI_VARIABLES.iter()
+		.filter_map(env::var_os)
+		.any(|value| !value.is_empty() && value != "false" && value != "0")
+}
+
+/// Picks the format best suited to where output is going.
+///
+/// This is the top of a ladder that colors are the next step down: the
+/// interactive format on a terminal that can move its cursor, then lines
+/// colored as they are there in CI logs, which show colors but not cursor
+/// movement, then lines without colors where nothing interprets them, as
+/// in files, pipes, dumb terminals, or wherever `NO_COLOR` is set. The
+/// JSON format, at the bottom, is only ever chosen by the user.
+fn detect() -> Format {
+	// the browser has no terminal, nor any environment to read
+	if cfg!(all(target_family = "wasm", target_os = "unknown")) {
+		return Format::Console;
+	}
+
+	// a parent process using jeflog chooses the format for its children
+	if let Some(format) = env::var("JEFLOG_FORMAT").ok().and_then(|name| Format::from_name(&name)) {
+		return format;
+	}
+
+	// this is set by users, so it takes precedence over the CI checks
+	if env::var_os("JEFLOG_ACCESSIBLE").is_some_and(|value| !value.is_empty() && value != "0" && value != "false") {
+		return Format::Accessible;
+	}
+
+	let dumb = env::var("TERM").is_ok_and(|term| term == "dumb");
+	let ci = ci();
+
+	if env::var("GITHUB_ACTIONS").is_ok_and(|value| value == "true") {
+		return Format::GitHub;
+	}
+
+	if env::var("GITLAB_CI").is_ok_and(|value| value == "true") {
+		return Format::GitLab;
+	}
+
+	if env::var_os("TEAMCITY_VERSION").is_some() {
+		return Format::TeamCity;
+	}
+
+	if env::var("TF_BUILD").is_ok_and(|value| value.eq_ignore_ascii_case("true")) {
+		return Format::Azure;
+	}
+
+	if dumb || ci || !output::is_terminal() {
+		Format::Sequential
+	} else {
+		Format::Interactive
+	}
+}
+
+/// Decides whether output is colored from the environment, following the
+/// `NO_COLOR` and `FORCE_COLOR` (or `CLICOLOR_FORCE`) conventions before
+/// looking at where output is going.
+fn detect_color() -> bool {
+	let set = |name| env::var_os(name).is_some_and(|value| !value.is_empty() && value != "0");
+
+	if set("NO_COLOR") {
+		return false;
+	}
+
+	if set("FORCE_COLOR") || set("CLICOLOR_FORCE") {
+		return true;
+	}
+
+	// CI logs show colors even though they aren't terminals
+	ci() || (output::is_terminal() && env::var("TERM").is_ok_and(|term| term != "dumb"))
+}
+
+/// Returns whether output is currently colored.
+pub(crate) fn colored() -> bool {
+	static DETECTED: OnceLock<bool> = OnceLock::new();
+
+	CONFIG.read().recover().color
+		.unwrap_or_else(|| *DETECTED.get_or_init(detect_color))
+}
+
+/// Returns the format that output is currently written in.
+pub(crate) fn current() -> Format {
+	static DETECTED: OnceLock<Format> = OnceLock::new();
+
+	CONFIG.read().recover().format
+		.unwrap_or_else(|| *DETECTED.get_or_init(detect))
+}
+
+/// Sets the format that tasks are written in, overriding the
+/// format that would otherwise be detected from the environment.
+///
+/// This should be called before any task is started, since tasks that
+/// are already running were drawn in the format active at the time.
+pub fn set_format(format: Format) {
+	CONFIG.write().recover().format = Some(format);
+}
+
+/// Sets whether output is colored, overriding what would otherwise be
+/// detected from the environment, where colors are left out if `NO_COLOR`
+/// is set or output isn't going to a terminal or a CI log. Without colors,
+/// every format but the JSON format keeps its symbols and layout.
+pub fn set_color(enabled: bool) {
+	CONFIG.write().recover().color = Some(enabled);
+}
+
+/// Forces the interactive format, even if the environment suggests it
+/// is unsupported, such as when `TERM=dumb` or `CI` is set or when
+/// standard output is not a terminal.
+pub fn force_interactive() {
+	set_format(Format::Interactive);
+}
+
+/// How many levels deep the tasks of a parent process using jeflog were
+/// nested when it started this one, so that tasks printed as lines can be
+/// indented to line up beneath them.
+pub(crate) fn base_depth() -> usize {
+	static BASE: OnceLock<usize> = OnceLock::new();
+
+	*BASE.get_or_init(|| {
+		env::var("JEFLOG_DEPTH")
+			.ok()
+			.and_then(|depth| depth.parse().ok())
+			.unwrap_or(0)
+	})
+}
+
+/// Returns what to put at the start of each line printed by the
+/// line-oriented formats to tell which process printed it, such as
+/// `[build-3:4121] `, if [`set_stamp`](crate::set_stamp) is enabled.
+pub(crate) fn stamp() -> String {
+	static STAMP: OnceLock<String> = OnceLock::new();
+
+	if !CONFIG.read().recover().stamp {
+		return String::new();
+	}
+
+	STAMP.get_or_init(|| format!("[{}:{}] ", hostname(), process::id())).clone()
+}
+
+/// Finds the name of the machine this process is running on, without
+/// running another program to ask for it.
+fn hostname() -> String {
+	let variables = ["HOSTNAME", "COMPUTERNAME"].into_iter().filter_map(|name| env::var(name).ok());
+	let files = ["/proc/sys/kernel/hostname", "/etc/hostname"].into_iter().filter_map(|path| fs::read_to_string(path).ok());
+
+	variables
+		.chain(files)
+		.map(|name| name.trim().to_owned())
+		.find(|name| !name.is_empty())
+		.unwrap_or_else(|| String::from("localhost"))
+}
+
+/// Returns environment variables to set on a child process that uses
+/// jeflog itself, as in `Command::new("tool").envs(jeflog::child_env())`,
+/// so that its tasks line up beneath the task running on this thread.
+///
+/// The child prints its tasks as lines (`JEFLOG_FORMAT`), since it has no
+/// way to draw into this process's interactive tree, indented as deeply as
+/// a subtask of the running task would be (`JEFLOG_DEPTH`). This is meant
+/// for children writing straight to the terminal; output passed through
+/// [`stdout`](crate::stdout) is indented by this process already.
+pub fn child_env() -> [(&'static str, String); 2] {
+	let format = match current() {
+		Format::Interactive => Format::Sequential,
+		format => format,
+	};
+
+	let depth = crate::enclosing_depth().map_or(0, |depth| depth + 1) + base_depth();
+
+	[
+		("JEFLOG_FORMAT", format.name().to_owned()),
+		("JEFLOG_DEPTH", depth.to_string()),
+	]
+}
+
+/// Removes the SGR sequences that color and style `text` where output
+/// isn't [`colored`], leaving every other escape sequence in place.
+pub(crate) fn uncolored(text: &str) -> Cow<'_, str> {
+	if colored() || !text.contains('\x1b') {
+		return Cow::Borrowed(text);
+	}
+
+	let uncolored = style::pieces(text)
+		.filter(|piece| piece.sgr().is_none())
+		.map(Piece::as_str)
+		.collect();
+
+	Cow::Owned(uncolored)
+}
+
+/// Removes ANSI escape sequences from `text`, for output that is
+/// not interpreted by a terminal.
+pub(crate) fn plain(text: &str) -> String {
+	style::pieces(text)
+		.filter_map(|piece| match piece {
+			Piece::Text(text) => Some(text),
+			Piece::Escape(_) => None,
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn plain_text_has_no_escapes() {
+		assert_eq!(plain("\x1b[1;31merror\x1b[0m"), "error");
+		assert_eq!(plain(&style::link("https://example.com", "docs")), "docs");
+		assert_eq!(plain("cut \x1b[3"), "cut ");
+		assert_eq!(plain("no escapes"), "no escapes");
+	}
+
+	#[test]
+	fn uncolored_text_keeps_other_escapes() {
+		let text = "\x1b[1mbold\x1b[0m \x1b]8;;u\x1b\\link\x1b]8;;\x1b\\\x1b[2K";
+
+		// colors are only removed where output isn't colored
+		let expected = if colored() { text } else { "bold \x1b]8;;u\x1b\\link\x1b]8;;\x1b\\\x1b[2K" };
+		assert_eq!(uncolored(text), expected);
+	}
+}
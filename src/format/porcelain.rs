@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use crate::{output, Session, Status};
+
+use super::{plain, SLOWEST};
+
+/// Prints the line for a task starting `depth` levels deep.
+pub(crate) fn start(depth: usize, label: &str) {
+	emit(&["start", &depth.to_string(), &field(label)]);
+}
+
+/// Prints the line for a task `depth` levels deep ending.
+pub(crate) fn end(depth: usize, status: Status, duration: Duration, message: &str) {
+	emit(&["end", &depth.to_string(), status.name(), &duration.as_millis().to_string(), &field(message)]);
+}
+
+/// Prints the line for text printed beneath a task `depth - 1` levels deep.
+pub(crate) fn text(depth: usize, text: &str) {
+	emit(&["text", &depth.to_string(), &field(text)]);
+}
+
+/// Prints the line for a question asked beneath a task `depth - 1` levels
+/// deep, which is answered on the line that follows it in the input.
+pub(crate) fn ask(depth: usize, question: &str) {
+	emit(&["ask", &depth.to_string(), &field(question)]);
+}
+
+/// Prints the lines summarizing the whole run: how many tasks ended with
+/// each status and how long the run took, then each of the slowest tasks.
+pub(crate) fn summary(session: &Session) {
+	let counts = session.counts().map(|count| count.to_string());
+	let duration = session.elapsed().as_millis().to_string();
+
+	emit(&["summary", &counts[0], &counts[1], &counts[2], &counts[3], &duration]);
+
+	for found in session.slowest(SLOWEST) {
+		let record = found.record;
+		emit(&["slowest", record.status.name(), &record.duration.as_millis().to_string(), &field(&record.message)]);
+	}
+}
+
+/// Strips a field of styles, of the tabs that separate fields, and of the
+/// line breaks that separate lines, so that every field takes one line.
+fn field(text: &str) -> String {
+	plain(text).replace(['\t', '\n', '\r'], " ")
+}
+
+fn emit(fields: &[&str]) {
+	output::emit(&(fields.join("\t") + "\n"));
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn fields_take_one_line() {
+		assert_eq!(field("a\tb\r\nc\nd"), "a b  c d");
+		assert_eq!(field("\x1b[1mbold\x1b[0m"), "bold");
+	}
+}
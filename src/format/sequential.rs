@@ -0,0 +1,46 @@
+use crate::{format, term::Frame, Status};
+
+/// Prints a line announcing a new task nested `depth` levels deep.
+pub(crate) fn start(depth: usize, message: &str) {
+	line(depth, "\x1b[33;1m-\x1b[0m", message);
+}
+
+/// Prints a line announcing how a task nested `depth` levels deep ended.
+pub(crate) fn end(depth: usize, status: Status, message: &str) {
+	line(depth, status.symbol(), message);
+}
+
+/// Prints a line of text beneath a task nested `depth - 1` levels deep.
+pub(crate) fn text(depth: usize, text: &str) {
+	let mut frame = Frame::new();
+	frame.print(format!("{}{}{text}", format::stamp(), " ".repeat(depth * 5)));
+	frame.newline();
+	frame.emit();
+}
+
+/// Prints a question beneath a task nested `depth - 1` levels deep,
+/// leaving the cursor after it for the answer.
+pub(crate) fn ask(depth: usize, question: &str) {
+	let mut frame = Frame::new();
+	frame.print(format!("{}{}{question} ", format::stamp(), " ".repeat(depth * 5)));
+	frame.emit();
+}
+
+/// Finishes the line of a question once it has been answered.
+pub(crate) fn answered(echoed: bool) {
+	if !echoed {
+		let mut frame = Frame::new();
+		frame.newline();
+		frame.emit();
+	}
+}
+
+fn line(depth: usize, symbol: &str, message: &str) {
+	let mut frame = Frame::new();
+
+	// subtasks line up with the message of their parent,
+	// just as they do in the interactive format
+	frame.print(format!("{}{}{symbol} {message}", format::stamp(), " ".repeat(depth * 5)));
+	frame.newline();
+	frame.emit();
+}
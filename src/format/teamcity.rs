@@ -0,0 +1,44 @@
+use crate::{output, Status};
+
+use super::{plain, sequential};
+
+/// Opens a block in the build log for every task, which TeamCity
+/// nests and collapses according to how the tasks themselves nest.
+pub(crate) fn start(message: &str) {
+	output::emit(&format!("##teamcity[blockOpened name='{}']\n", escape(&plain(message))));
+}
+
+/// Closes the block of the task started with `name`, reporting failures
+/// as build problems and warnings as warning messages.
+pub(crate) fn end(depth: usize, name: &str, status: Status, message: &str) {
+	let mut out = format!("##teamcity[blockClosed name='{}']\n", escape(&plain(name)));
+	let text = escape(&plain(message));
+
+	match status {
+		Status::Fail => out += &format!("##teamcity[buildProblem description='{text}']\n"),
+		Status::Warn => out += &format!("##teamcity[message text='{text}' status='WARNING']\n"),
+		_ => {},
+	}
+
+	output::emit(&out);
+	sequential::end(depth, status, message);
+}
+
+/// Escapes the characters that service messages treat specially.
+fn escape(text: &str) -> String {
+	let mut escaped = String::with_capacity(text.len());
+
+	for c in text.chars() {
+		match c {
+			'|' => escaped.push_str("||"),
+			'\'' => escaped.push_str("|'"),
+			'\n' => escaped.push_str("|n"),
+			'\r' => escaped.push_str("|r"),
+			'[' => escaped.push_str("|["),
+			']' => escaped.push_str("|]"),
+			c => escaped.push(c),
+		}
+	}
+
+	escaped
+}
@@ -0,0 +1,134 @@
+use std::sync::{
+	atomic::{AtomicBool, Ordering},
+	Arc,
+};
+
+use crate::{sync::Recover, Propagation, Status, Task, TASKS};
+
+/// A flag that is raised once a task is cancelled through its
+/// [`TaskHandle`], which work done for the task can poll to stop early.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken {
+	cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+	/// Returns whether the task has been cancelled.
+	pub fn is_cancelled(&self) -> bool {
+		self.cancelled.load(Ordering::Relaxed)
+	}
+
+	/// Lowers the token again, for a task that is being reopened.
+	pub(crate) fn reset(&self) {
+		self.cancelled.store(false, Ordering::Relaxed);
+	}
+
+	/// Returns whether anything besides the task itself holds the token,
+	/// such as a handle that may yet reopen the task.
+	pub(crate) fn is_shared(&self) -> bool {
+		Arc::strong_count(&self.cancelled) > 1
+	}
+}
+
+/// A handle to a running task, created by [`handle`], that can cancel it
+/// from any thread, such as one handling user input.
+#[derive(Clone, Debug)]
+pub struct TaskHandle {
+	id: u64,
+	token: CancelToken,
+}
+
+impl TaskHandle {
+	/// Returns the token that is raised when the task is cancelled, which
+	/// is shared by every handle to the same task.
+	pub fn cancel_token(&self) -> CancelToken {
+		self.token.clone()
+	}
+
+	/// Raises the task's cancellation token, then ends the task as
+	/// cancelled with the message it was started with, along with any
+	/// of its subtasks that are still running, innermost first.
+	///
+	/// The work being done for the task is not stopped, and should stop
+	/// by itself once it sees the token without ending the task, which
+	/// would otherwise end the task it is nested under instead. This
+	/// does nothing to the task if it has already ended.
+	pub fn cancel(&self) {
+		self.token.cancelled.store(true, Ordering::Relaxed);
+
+		// the lock must be released before each task is ended
+		while let Some((id, message)) = innermost(self.id) {
+			crate::end_task_by_id(id, Status::Cancel, message);
+		}
+	}
+
+	/// Puts the task back to running after it has ended, restarting its
+	/// spinner and its timers, for work that is done again as one task,
+	/// such as a rebuild in watch mode. Returns whether it was reopened,
+	/// which it isn't if it is still running, or if it is a subtask whose
+	/// parent has since ended.
+	///
+	/// If nothing has been printed since the task ended, it is reopened on
+	/// the line it ended on, beneath which the lines of its new subtasks
+	/// follow those of its last run. Otherwise it starts again on a new
+	/// line. Each run is recorded in the session separately, and its
+	/// cancellation token is lowered again.
+	pub fn reopen(&self) -> bool {
+		crate::reopen_task(self.id)
+	}
+
+	/// Changes how the task ends once a task beneath it has failed, in place
+	/// of what [`set_propagation`](crate::set_propagation) chose for every
+	/// task, such as for cleanup that should pass however its steps went,
+	/// as in `jeflog::handle().unwrap().set_propagation(Propagation::Off)`.
+	/// This does nothing if the task has already ended.
+	pub fn set_propagation(&self, propagation: Propagation) {
+		let mut tasks = TASKS.lock().recover();
+
+		if let Some(task) = tasks.iter_mut().find(|task| task.id == self.id) {
+			task.propagation = Some(propagation);
+		}
+	}
+
+	/// Returns whether the task is still running.
+	pub fn is_running(&self) -> bool {
+		TASKS.lock().recover().iter().any(|task| task.id == self.id)
+	}
+}
+
+/// Returns the id and message of the innermost running task that is the
+/// task with id `ancestor` or is nested under it.
+fn innermost(ancestor: u64) -> Option<(u64, String)> {
+	let tasks = TASKS.lock().recover();
+
+	tasks.iter()
+		.rev()
+		.find(|task| task.id == ancestor || lineage(&tasks, task.parent, ancestor))
+		.map(|task| (task.id, task.message.clone()))
+}
+
+/// Returns whether the task with id `parent`, or any task it is nested
+/// under, is the task with id `ancestor`.
+fn lineage(tasks: &[Task], mut parent: Option<u64>, ancestor: u64) -> bool {
+	while let Some(id) = parent {
+		if id == ancestor {
+			return true;
+		}
+
+		parent = tasks.iter().find(|task| task.id == id).and_then(|task| task.parent);
+	}
+
+	false
+}
+
+/// Returns a handle to the innermost task started by this thread, which
+/// is the one that ending macros apply to, if there is one.
+pub fn handle() -> Option<TaskHandle> {
+	let tasks = TASKS.lock().recover();
+	let task = &tasks[crate::current(&tasks)?];
+
+	Some(TaskHandle {
+		id: task.id,
+		token: task.token.clone(),
+	})
+}
@@ -0,0 +1,350 @@
+use alloc::{format, string::String};
+#[cfg(feature = "std")]
+use core::str::Chars;
+
+/// A parsed JSON value, for reading machine-readable output of other tools.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Value {
+	Null,
+	Bool(bool),
+	Number(f64),
+	String(String),
+	Array(Vec<Value>),
+	Object(Vec<(String, Value)>),
+}
+
+#[cfg(feature = "std")]
+impl Value {
+	/// Returns the value of a key in an object, if this is one and has it.
+	pub fn get(&self, key: &str) -> Option<&Value> {
+		match self {
+			Self::Object(entries) => entries.iter()
+				.find(|(name, _)| name == key)
+				.map(|(_, value)| value),
+			_ => None,
+		}
+	}
+
+	pub fn as_str(&self) -> Option<&str> {
+		match self {
+			Self::String(string) => Some(string),
+			_ => None,
+		}
+	}
+
+	pub fn as_f64(&self) -> Option<f64> {
+		match self {
+			Self::Number(value) => Some(*value),
+			_ => None,
+		}
+	}
+
+	pub fn as_bool(&self) -> Option<bool> {
+		match self {
+			Self::Bool(value) => Some(*value),
+			_ => None,
+		}
+	}
+
+	pub fn as_array(&self) -> &[Value] {
+		match self {
+			Self::Array(values) => values,
+			_ => &[],
+		}
+	}
+}
+
+/// Writes `text` as a JSON string, with quotes around it.
+pub(crate) fn string(text: &str) -> String {
+	let mut out = String::with_capacity(text.len() + 2);
+	out.push('"');
+
+	for c in text.chars() {
+		match c {
+			'"' => out.push_str("\\\""),
+			'\\' => out.push_str("\\\\"),
+			'\n' => out.push_str("\\n"),
+			'\r' => out.push_str("\\r"),
+			'\t' => out.push_str("\\t"),
+			c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+			c => out.push(c),
+		}
+	}
+
+	out.push('"');
+	out
+}
+
+/// Parses a single JSON value, returning `None` if `text` isn't valid JSON
+/// or has anything other than whitespace after the value, or is nested
+/// more deeply than [`MAX_DEPTH`].
+#[cfg(feature = "std")]
+pub(crate) fn parse(text: &str) -> Option<Value> {
+	let mut parser = Parser { chars: text.chars(), peeked: None, depth: 0 };
+	let value = parser.value()?;
+
+	parser.skip_whitespace();
+	parser.peek().is_none().then_some(value)
+}
+
+/// How deeply arrays and objects may be nested in parsed text, which may
+/// come from anyone, since each level is parsed with another call.
+#[cfg(feature = "std")]
+const MAX_DEPTH: usize = 128;
+
+#[cfg(feature = "std")]
+struct Parser<'a> {
+	chars: Chars<'a>,
+	peeked: Option<char>,
+	// how many arrays and objects the parser is inside
+	depth: usize,
+}
+
+#[cfg(feature = "std")]
+impl Parser<'_> {
+	fn peek(&mut self) -> Option<char> {
+		if self.peeked.is_none() {
+			self.peeked = self.chars.next();
+		}
+
+		self.peeked
+	}
+
+	fn next(&mut self) -> Option<char> {
+		self.peek();
+		self.peeked.take()
+	}
+
+	fn skip_whitespace(&mut self) {
+		while self.peek().is_some_and(|c| matches!(c, ' ' | '\t' | '\n' | '\r')) {
+			self.next();
+		}
+	}
+
+	fn expect(&mut self, word: &str) -> Option<()> {
+		word.chars().all(|c| self.next() == Some(c)).then_some(())
+	}
+
+	fn value(&mut self) -> Option<Value> {
+		self.skip_whitespace();
+
+		match self.peek()? {
+			'n' => self.expect("null").map(|_| Value::Null),
+			't' => self.expect("true").map(|_| Value::Bool(true)),
+			'f' => self.expect("false").map(|_| Value::Bool(false)),
+			'"' => self.string().map(Value::String),
+			'[' => self.nested(Self::array),
+			'{' => self.nested(Self::object),
+			_ => self.number(),
+		}
+	}
+
+	/// Parses an array or object with `parse`, one level more deeply.
+	fn nested(&mut self, parse: fn(&mut Self) -> Option<Value>) -> Option<Value> {
+		if self.depth >= MAX_DEPTH {
+			return None;
+		}
+
+		self.depth += 1;
+		let value = parse(self);
+		self.depth -= 1;
+		value
+	}
+
+	fn number(&mut self) -> Option<Value> {
+		let mut number = String::new();
+
+		while let Some(c) = self.peek().filter(|c| matches!(c, '0'..='9' | '-' | '+' | '.' | 'e' | 'E')) {
+			number.push(c);
+			self.next();
+		}
+
+		number.parse().ok().map(Value::Number)
+	}
+
+	fn string(&mut self) -> Option<String> {
+		self.next();
+		let mut string = String::new();
+
+		loop {
+			match self.next()? {
+				'"' => return Some(string),
+				'\\' => match self.next()? {
+					'"' => string.push('"'),
+					'\\' => string.push('\\'),
+					'/' => string.push('/'),
+					'b' => string.push('\x08'),
+					'f' => string.push('\x0c'),
+					'n' => string.push('\n'),
+					'r' => string.push('\r'),
+					't' => string.push('\t'),
+					'u' => {
+						let high = self.hex()?;
+
+						// characters outside the basic plane are escaped as
+						// a pair of surrogates, which only make sense together
+						let code = if (0xd800..0xdc00).contains(&high) {
+							self.expect("\\u")?;
+							let low = self.hex()?;
+							0x10000 + ((high - 0xd800) << 10) + (low.checked_sub(0xdc00)? & 0x3ff)
+						} else {
+							high
+						};
+
+						string.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+					},
+					_ => return None,
+				},
+				c => string.push(c),
+			}
+		}
+	}
+
+	fn hex(&mut self) -> Option<u32> {
+		(0..4).try_fold(0, |code, _| Some(code * 16 + self.next()?.to_digit(16)?))
+	}
+
+	fn array(&mut self) -> Option<Value> {
+		self.next();
+		let mut values = Vec::new();
+
+		self.skip_whitespace();
+
+		if self.peek() == Some(']') {
+			self.next();
+			return Some(Value::Array(values));
+		}
+
+		loop {
+			values.push(self.value()?);
+			self.skip_whitespace();
+
+			match self.next()? {
+				',' => continue,
+				']' => return Some(Value::Array(values)),
+				_ => return None,
+			}
+		}
+	}
+
+	fn object(&mut self) -> Option<Value> {
+		self.next();
+		let mut entries = Vec::new();
+
+		self.skip_whitespace();
+
+		if self.peek() == Some('}') {
+			self.next();
+			return Some(Value::Object(entries));
+		}
+
+		loop {
+			self.skip_whitespace();
+
+			if self.peek() != Some('"') {
+				return None;
+			}
+
+			let key = self.string()?;
+			self.skip_whitespace();
+
+			if self.next()? != ':' {
+				return None;
+			}
+
+			entries.push((key, self.value()?));
+			self.skip_whitespace();
+
+			match self.next()? {
+				',' => continue,
+				'}' => return Some(Value::Object(entries)),
+				_ => return None,
+			}
+		}
+	}
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_every_kind_of_value() {
+		assert_eq!(
+			parse(r#" {"a": [null, true, false, -1.5e3, "x"], "b": {}} "#),
+			Some(Value::Object(vec![
+				(String::from("a"), Value::Array(vec![
+					Value::Null,
+					Value::Bool(true),
+					Value::Bool(false),
+					Value::Number(-1500.0),
+					Value::String(String::from("x")),
+				])),
+				(String::from("b"), Value::Object(Vec::new())),
+			])),
+		);
+	}
+
+	#[test]
+	fn reads_escapes() {
+		assert_eq!(
+			parse(r#""\"\\\/\b\f\n\r\t\u00e9""#).as_ref().and_then(Value::as_str),
+			Some("\"\\/\x08\x0c\n\r\té"),
+		);
+
+		assert_eq!(parse(r#""\q""#), None);
+		assert_eq!(parse(r#""\u12""#), None);
+		assert_eq!(parse(r#""unterminated"#), None);
+	}
+
+	#[test]
+	fn joins_surrogate_pairs() {
+		assert_eq!(parse(r#""\ud83d\ude00""#).as_ref().and_then(Value::as_str), Some("😀"));
+
+		// a high surrogate must be followed by a low one
+		assert_eq!(parse(r#""\ud83d""#), None);
+		assert_eq!(parse(r#""\ud83d\u0041""#), None);
+
+		// a lone low surrogate isn't a character
+		assert_eq!(parse(r#""\ude00""#).as_ref().and_then(Value::as_str), Some("\u{fffd}"));
+	}
+
+	#[test]
+	fn reads_numbers() {
+		assert_eq!(parse("0"), Some(Value::Number(0.0)));
+		assert_eq!(parse("-12"), Some(Value::Number(-12.0)));
+		assert_eq!(parse("3.25"), Some(Value::Number(3.25)));
+		assert_eq!(parse("1E2"), Some(Value::Number(100.0)));
+		assert_eq!(parse("1791970200000"), Some(Value::Number(1_791_970_200_000.0)));
+		assert_eq!(parse("1-2"), None);
+		assert_eq!(parse("--1"), None);
+		assert_eq!(parse("."), None);
+	}
+
+	#[test]
+	fn rejects_trailing_data() {
+		assert_eq!(parse("1 2"), None);
+		assert_eq!(parse("{} x"), None);
+		assert_eq!(parse("[1,]"), None);
+		assert_eq!(parse(r#"{"a":1,}"#), None);
+		assert_eq!(parse("true\n"), Some(Value::Bool(true)));
+		assert_eq!(parse(""), None);
+	}
+
+	#[test]
+	fn limits_nesting() {
+		let nested = |depth| "[".repeat(depth) + &"]".repeat(depth);
+
+		assert!(parse(&nested(MAX_DEPTH)).is_some());
+		assert_eq!(parse(&nested(MAX_DEPTH + 1)), None);
+		assert_eq!(parse(&"[".repeat(200_000)), None);
+		assert_eq!(parse(&r#"{"a":"#.repeat(200_000)), None);
+	}
+
+	#[test]
+	fn written_strings_are_read_back() {
+		let text = "quote \" slash \\ newline \n tab \t bell \x07 é 😀";
+		assert_eq!(parse(&string(text)).as_ref().and_then(Value::as_str), Some(text));
+	}
+}
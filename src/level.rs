@@ -0,0 +1,193 @@
+use std::{env, fmt::Arguments, str::FromStr, sync::OnceLock};
+
+use crate::{config::CONFIG, style, sync::Recover};
+
+/// How important a log line is, from most to least.
+///
+/// Tasks report their own warnings and errors with `warn!` and `fail!`,
+/// so those levels mostly serve as filters that hide every log line
+/// from a module.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+	/// Errors, shown in red.
+	Error,
+	/// Warnings, shown in yellow.
+	Warn,
+	/// General information, printed with `info!`.
+	Info,
+	/// Details for debugging, printed dimmed with `debug!`.
+	Debug,
+	/// Very detailed tracing, printed dimmed with `trace!`.
+	Trace,
+}
+
+impl FromStr for Level {
+	type Err = ();
+
+	fn from_str(name: &str) -> Result<Self, ()> {
+		match name.to_ascii_lowercase().as_str() {
+			"error" => Ok(Self::Error),
+			"warn" | "warning" => Ok(Self::Warn),
+			"info" => Ok(Self::Info),
+			"debug" => Ok(Self::Debug),
+			"trace" => Ok(Self::Trace),
+			_ => Err(()),
+		}
+	}
+}
+
+/// The most detailed level shown, or `None` if nothing is shown.
+type LevelFilter = Option<Level>;
+
+fn parse_filter(name: &str) -> Option<LevelFilter> {
+	match name.trim() {
+		"off" => Some(None),
+		name => name.parse().ok().map(Some),
+	}
+}
+
+/// The directives read from `JEFLOG_LOG`.
+#[derive(Default)]
+struct Filter {
+	/// The level for targets without a directive of their own.
+	default: Option<LevelFilter>,
+	/// Levels for specific targets (usually module paths), along with
+	/// every target nested inside them.
+	targets: Vec<(String, LevelFilter)>,
+}
+
+impl Filter {
+	/// Parses directives in the style of `env_logger`, such as
+	/// `info,mycrate=debug,hyper=off`. Directives that can't be
+	/// parsed are ignored.
+	fn parse(directives: &str) -> Self {
+		let mut filter = Filter::default();
+
+		for directive in directives.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+			match directive.split_once('=') {
+				Some((target, level)) => {
+					if let Some(level) = parse_filter(level) {
+						filter.targets.push((target.trim().to_owned(), level));
+					}
+				},
+				None => {
+					if let Some(level) = parse_filter(directive) {
+						filter.default = Some(level);
+					}
+				},
+			}
+		}
+
+		filter
+	}
+
+	/// Returns the level given for `target` by a directive naming it or
+	/// one of its parent modules, if there is one. The most specific
+	/// matching directive wins.
+	fn target(&self, target: &str) -> Option<LevelFilter> {
+		self.targets.iter()
+			.filter(|(path, _)| {
+				target == path || target.strip_prefix(path.as_str()).is_some_and(|rest| rest.starts_with("::"))
+			})
+			.max_by_key(|(path, _)| path.len())
+			.map(|(_, level)| *level)
+	}
+}
+
+fn filter() -> &'static Filter {
+	static FILTER: OnceLock<Filter> = OnceLock::new();
+
+	FILTER.get_or_init(|| {
+		env::var("JEFLOG_LOG")
+			.map(|directives| Filter::parse(&directives))
+			.unwrap_or_default()
+	})
+}
+
+/// Returns whether a log line at `level` for `target` would be shown.
+pub(crate) fn enabled(level: Level, target: &str) -> bool {
+	let filter = filter();
+
+	let max = filter.target(target)
+		.or(filter.default)
+		.unwrap_or_else(|| Some(CONFIG.read().recover().level));
+
+	max.is_some_and(|max| level <= max)
+}
+
+/// Returns whether tasks with `target` are shown. Unlike log lines,
+/// tasks are only hidden by a directive naming their target, at a level
+/// less detailed than info.
+pub(crate) fn task_enabled(target: &str) -> bool {
+	filter()
+		.target(target)
+		.is_none_or(|max| max.is_some_and(|max| max >= Level::Info))
+}
+
+/// Sets the most detailed level of log line that is shown, for modules
+/// that `JEFLOG_LOG` doesn't name a level for. By default, this is
+/// [`Level::Info`].
+pub fn set_level(level: Level) {
+	CONFIG.write().recover().level = level;
+}
+
+#[doc(hidden)]
+pub fn __log__(level: Level, target: Option<&str>, module: &str, message: Arguments) {
+	// the message is only formatted once it's known to be shown, so
+	// that lines which are filtered out, or written before jeflog is
+	// initialized, cost next to nothing
+	if !crate::initialized() || !enabled(level, target.unwrap_or(module)) {
+		return;
+	}
+
+	let mut message = style::clean(&message.to_string());
+
+	// only targets given explicitly are shown, since module paths are long
+	if let Some(target) = target {
+		message = format!("{} {message}", style::tag(target));
+	}
+
+	let line = match level {
+		Level::Error => format!("\x1b[31m{message}\x1b[0m"),
+		Level::Warn => format!("\x1b[33m{message}\x1b[0m"),
+		Level::Info => message,
+		Level::Debug => format!("\x1b[2m{message}\x1b[0m"),
+		Level::Trace => format!("\x1b[2;90m{message}\x1b[0m"),
+	};
+
+	crate::print_line(&line);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn levels_are_read_by_name() {
+		assert_eq!("WARNING".parse(), Ok(Level::Warn));
+		assert_eq!("trace".parse(), Ok(Level::Trace));
+		assert_eq!("loud".parse::<Level>(), Err(()));
+		assert_eq!(parse_filter(" off "), Some(None));
+		assert_eq!(parse_filter("debug"), Some(Some(Level::Debug)));
+		assert_eq!(parse_filter("loud"), None);
+	}
+
+	#[test]
+	fn directives_apply_to_their_targets_and_those_inside() {
+		let filter = Filter::parse("info, app=debug, app::net=off, app::db=loud, ,=trace");
+
+		assert_eq!(filter.default, Some(Some(Level::Info)));
+		assert_eq!(filter.target("app"), Some(Some(Level::Debug)));
+		assert_eq!(filter.target("app::ui::menu"), Some(Some(Level::Debug)));
+		assert_eq!(filter.target("app::net::tls"), Some(None));
+		assert_eq!(filter.target("app::db"), Some(Some(Level::Debug)));
+		assert_eq!(filter.target("apple"), None);
+	}
+
+	#[test]
+	fn directives_that_cannot_be_read_are_ignored() {
+		let filter = Filter::parse("loud,app=");
+		assert_eq!(filter.default, None);
+		assert!(filter.targets.is_empty());
+	}
+}
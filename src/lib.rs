@@ -1,197 +1,191 @@
-use std::{
-	io::{self, Write},
-	sync::{atomic::{AtomicBool, Ordering}, Mutex}, thread, time::Duration,
-};
-
-#[derive(Clone, Copy, Debug)]
-struct Task {
-	pub row_offset: i32
-}
-
-static TASKS: Mutex<Vec<Task>> = Mutex::new(Vec::new());
-static SPINNING: AtomicBool = AtomicBool::new(false);
-
-/// Begins a task or subtask with a spinner.
-#[macro_export]
-macro_rules! task {
-	($($tokens:tt)*) => {
-		$crate::__start_task__(format!($($tokens)*));
-	}
-}
-
-/// Indicates that the most recently created task has passed by
-/// replacing the spinner with a green check mark.
-#[macro_export]
-macro_rules! pass {
-	($($tokens:tt)*) => {
-		$crate::__end_task__("\x1b[32;1m✔\x1b[0m", format!($($tokens)*));
-	}
-}
-
-/// Indicates that the most recently created task has passed with a
-/// warning by replacing the spinner with a yellow triangle.
-#[macro_export]
-macro_rules! warn {
-	($($tokens:tt)*) => {
-		$crate::__end_task__("\x1b[33;1m▲\x1b[0m", format!($($tokens)*));
-	}
-}
-
-/// Indicates that the most recently created task has failed by
-/// replacing the spinner with a red x.
-#[macro_export]
-macro_rules! fail {
-	($($tokens:tt)*) => {
-		$crate::__end_task__("\x1b[31;1m✘\x1b[0m", format!($($tokens)*));
-	}
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+mod background;
+#[cfg(feature = "browse")]
+mod browse;
+#[cfg(feature = "std")]
+mod cargo;
+#[cfg(feature = "std")]
+mod command;
+#[cfg(feature = "std")]
+mod config;
+#[cfg(feature = "std")]
+mod demo;
+#[cfg(feature = "std")]
+mod diff;
+mod events;
+#[cfg(feature = "std")]
+mod format;
+#[cfg(feature = "std")]
+mod handle;
+mod json;
+#[cfg(feature = "std")]
+mod level;
+#[cfg(feature = "std")]
+mod locale;
+#[cfg(feature = "metrics")]
+mod monitor;
+mod node;
+#[cfg(feature = "std")]
+mod output;
+#[cfg(feature = "std")]
+mod path;
+#[cfg(feature = "std")]
+mod prompt;
+#[cfg(feature = "std")]
+mod redact;
+#[cfg(feature = "std")]
+mod replay;
+#[cfg(feature = "std")]
+mod runner;
+#[cfg(feature = "std")]
+mod scope;
+#[cfg(feature = "std")]
+mod session;
+#[cfg(all(feature = "std", unix))]
+mod socket;
+#[cfg(feature = "std")]
+mod style;
+#[cfg(feature = "std")]
+mod sync;
+#[cfg(feature = "std")]
+mod table;
+#[cfg(feature = "std")]
+mod taskbar;
+#[cfg(feature = "std")]
+mod term;
+#[cfg(feature = "std")]
+pub mod test;
+#[cfg(feature = "std")]
+mod throttle;
+#[cfg(feature = "std")]
+mod time;
+#[cfg(feature = "std")]
+mod title;
+#[cfg(feature = "std")]
+mod transfer;
+mod tracker;
+#[cfg(feature = "std")]
+mod tree;
+#[cfg(feature = "ratatui")]
+mod tui;
+#[cfg(feature = "std")]
+mod vars;
+#[cfg(feature = "web")]
+mod web;
+#[cfg(feature = "std")]
+mod writer;
+
+#[cfg(feature = "std")]
+pub use background::{background, Background};
+#[cfg(feature = "browse")]
+pub use browse::browse;
+#[cfg(feature = "std")]
+pub use cargo::cargo;
+#[cfg(feature = "std")]
+pub use command::Process;
+#[cfg(feature = "std")]
+pub use config::{Config, ConfigBuilder};
+#[cfg(feature = "std")]
+pub use demo::demo;
+pub use events::Event;
+#[cfg(feature = "std")]
+pub use events::{add_sink, Sink};
+#[cfg(feature = "std")]
+pub use format::{child_env, force_interactive, set_color, set_format, Format};
+#[cfg(feature = "std")]
+pub use handle::{handle, CancelToken, TaskHandle};
+#[cfg(feature = "std")]
+pub use level::{__log__, set_level, Level};
+#[cfg(feature = "std")]
+pub use locale::set_localizer;
+pub use node::Propagation;
+#[cfg(feature = "std")]
+pub use output::Stream;
+#[cfg(feature = "std")]
+pub use path::shorten_path;
+#[cfg(feature = "std")]
+pub use prompt::{__confirm__, __prompt__};
+#[cfg(feature = "std")]
+pub use redact::{add_redaction, Redaction};
+#[cfg(feature = "std")]
+pub use replay::{replay, Recording};
+#[cfg(feature = "std")]
+pub use runner::{parallel, Runner};
+#[cfg(feature = "std")]
+pub use scope::{scope, spawn, Scope};
+#[cfg(feature = "std")]
+pub use session::{session, warnings, Found, Profile, Record, Session, Warning};
+#[cfg(all(feature = "std", unix))]
+pub use socket::serve_socket;
+#[cfg(feature = "std")]
+pub use style::{link, set_connectors, set_sanitize, set_theme, Color, Connectors, Sanitize, Theme, DEPTH_PALETTE};
+#[cfg(feature = "std")]
+pub use table::table;
+#[cfg(feature = "std")]
+pub use throttle::{__Once__, __Throttle__};
+#[cfg(feature = "std")]
+pub use time::{set_clock, set_duration_style, set_timestamp_format, DurationStyle, Instant, TimestampFormat};
+#[cfg(feature = "std")]
+pub use transfer::{MultiProgress, Transfer};
+pub use tracker::{JsonLines, Output, Tracker};
+#[cfg(feature = "std")]
+pub use tree::*;
+#[cfg(feature = "ratatui")]
+pub use tui::TaskTree;
+#[cfg(feature = "std")]
+pub use vars::{set_var, unset_var};
+#[cfg(feature = "web")]
+pub use web::serve_web;
+#[cfg(feature = "std")]
+pub use writer::{stdout, Stdout};
+
+/// The final state of a task.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+	/// The task succeeded, shown as a green check mark.
+	Pass,
+	/// The task succeeded with a warning, shown as a yellow triangle.
+	Warn,
+	/// The task failed, shown as a red x.
+	Fail,
+	/// The task was deliberately aborted, shown as a gray circle.
+	Cancel,
 }
 
-#[doc(hidden)]
-pub fn __start_task__(message: String) {
-	// this can never panic because mutex locks can only
-	// fail if the thread holding the lock panics.
-	// this is guaranteed as long as:
-	//   1. TASKS is never locked outside of jeflog
-	//   2. jeflog code never panics
-	// as long as these two invariants are satisfied
-	// (and they are by design) then locks of TASKS
-	// cannot panic.
-	let mut tasks = TASKS.lock().unwrap();
-
-	if tasks.len() > 0 {
-		// adjust the offset (from bottom row) of each task
-		for task in tasks.iter_mut() {
-			task.row_offset += 1;
+impl Status {
+	#[cfg(feature = "std")]
+	fn symbol(self) -> &'static str {
+		match self {
+			Self::Pass => "\x1b[32;1m✔\x1b[0m",
+			Self::Warn => "\x1b[33;1m▲\x1b[0m",
+			Self::Fail => "\x1b[31;1m✘\x1b[0m",
+			Self::Cancel => "\x1b[90;1m⦸\x1b[0m",
 		}
-
-		println!();
 	}
 
-	if let Some(last_row) = tasks.last().map(|task| task.row_offset) {
-		print!("\x1b[s");
-
-		if last_row > 1 {
-			print!("\x1b[{}A\x1b[{}G┣", last_row - 1, (tasks.len() - 1) * 5 + 3);
+	/// The status in lowercase, as it is written for scripts.
+	pub(crate) fn name(self) -> &'static str {
+		match self {
+			Self::Pass => "pass",
+			Self::Warn => "warn",
+			Self::Fail => "fail",
+			Self::Cancel => "cancel",
 		}
-
-		for _ in 1..last_row {
-			print!("\x1b[1D\x1b[1B┃");
-		}
-
-		print!("\x1b[u");
 	}
 
-	tasks.push(Task { row_offset: 0 });
-
-	if tasks.len() > 1 {
-		print!("{}", " ".repeat((tasks.len() - 2) * 5 + 2) + "┗━ ");
-	}
-
-	// attempt to print message, ignore if flush fails
-	print!("\x1b[33;1m-\x1b[0m {message}");
-	_ = io::stdout().flush();
-
-	// atomically check if the spinner is running
-	// if not, then start the spinner
-	if SPINNING.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed) == Ok(false) {
-		thread::spawn(spin);
-	}
-}
-
-#[doc(hidden)]
-pub fn __end_task__(symbol: &str, message: String) {
-	let mut tasks = TASKS.lock().unwrap();
-
-	if let Some(Task { row_offset: row }) = tasks.pop() {
-		let column = tasks.len() * 5 + 1;
-		// replace spinner with symbol:
-		// \x1b[s         : save cursor's current position
-		// \x1b[{row}A    : move the cursor up to correct row
-		// \x1b[{column}G : move the cursor to correct column
-		// {symbol}       : print the symbol replacing the spinner
-		// \x1b[K         : clear the current line
-		// {message}      : print the ending message overwriting the old message
-
-		print!("\x1b[s");
-
-		if row > 0 {
-			print!("\x1b[{row}A");
-		}
-
-		print!("\x1b[{column}G{symbol} \x1b[K{message}");
-
-		// restore the cursor's position if not the last task
-		if row != 0 {
-			print!("\x1b[u");
-		}
-
-		if tasks.len() == 0 {
-			println!();
-		}
-
-		_ = io::stdout().flush();
-	} else {
-		// if no task is running, just print the symbol and message
-		println!("{symbol} {message}");
-	}
-}
-
-fn spin() {
-	let mut spinner = '-';
-
-	loop {
-		let tasks = TASKS.lock().unwrap();
-
-		// kill the thread if there are no more tasks
-		if tasks.len() == 0 {
-			break;
+	/// Reads a status written by [`name`](Self::name).
+	#[cfg(feature = "std")]
+	pub(crate) fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"pass" => Some(Self::Pass),
+			"warn" => Some(Self::Warn),
+			"fail" => Some(Self::Fail),
+			"cancel" => Some(Self::Cancel),
+			_ => None,
 		}
-
-		let mut column = 1;
-
-		for Task { row_offset: row } in tasks.iter() {
-			// replace spinner with new spinner:
-			// \x1b[s         : save the cursor's current position
-			// \x1b[{row}A    : move the cursor up to correct row
-			// \x1b[{column}G : move the cursor to correct column
-			// \x1b[33;1m     : set the foreground color to yellow and font to bold
-			// {spinner}      : print the updated spinner character
-			// \x1b[0m        : reset all formatting
-			// \x1b[u         : restore saved cursor position
-
-			print!("\x1b[s");
-
-			if *row > 0 {
-				print!("\x1b[{row}A");
-			}
-
-			print!("\x1b[{column}G\x1b[33;1m{spinner}\x1b[0m\x1b[u");
-			
-			column += 5;
-		}
-
-		// most systems flush stdout by newlines
-		// since no newlines were printed, we need
-		// to flush stdout explicitly
-		_ = io::stdout().flush();
-
-		// update spinner to next spinner character (clockwise)
-		spinner = match spinner {
-			'-' => '\\',
-			'\\' => '|',
-			'|' => '/',
-			'/' => '-',
-			_ => '-', // this is not possible, but Rust demands it
-		};
-
-		// drop tasks before the wait so other threads may use it
-		drop(tasks);
-
-		// wait for 100ms; this can be changed to make the spinner go faster
-		thread::sleep(Duration::from_millis(100));
 	}
-
-	// if the loop has ended, then the spinner has stopped and
-	// will need to be restarted if another task starts
-	SPINNING.store(false, Ordering::Relaxed);
 }
@@ -0,0 +1,49 @@
+use std::{fmt::Display, sync::RwLock};
+
+use crate::sync::Recover;
+
+type Localizer = Box<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+// the localizer set by `set_localizer`, if any
+static LOCALIZER: RwLock<Option<Localizer>> = RwLock::new(None);
+
+/// Translates the text that jeflog writes itself, such as the summaries
+/// of collapsed subtasks, hints, stall warnings, the accessible format's
+/// sentences, and durations written out in words, so that a translated
+/// program shows no English of its own.
+///
+/// Each text is passed to `localize` in English, as a template in which
+/// values are named in braces, such as `"{count} more done"`, and the
+/// translation should keep them, as in `"{count} weitere erledigt"`. A
+/// text with a count has a template for one and another for the rest,
+/// such as `"{count} warning"` and `"{count} warnings"`. Returning `None`
+/// keeps the English, so only the texts that have translations need them.
+/// It is called while the tree is being drawn, so it shouldn't use jeflog.
+///
+/// Messages given to tasks and lines are never passed to it, since they
+/// are written by the program, which can translate them before giving them.
+pub fn set_localizer(localize: impl Fn(&str) -> Option<String> + Send + Sync + 'static) {
+	*LOCALIZER.write().recover() = Some(Box::new(localize));
+}
+
+/// Translates one of jeflog's own texts, filling each value named in it.
+pub(crate) fn text(template: &str, values: &[(&str, &dyn Display)]) -> String {
+	let mut text = LOCALIZER
+		.read()
+		.recover()
+		.as_ref()
+		.and_then(|localize| localize(template))
+		.unwrap_or_else(|| template.to_owned());
+
+	for (name, value) in values {
+		text = text.replace(&format!("{{{name}}}"), &value.to_string());
+	}
+
+	text
+}
+
+/// Translates a text with a count, picking the template for one or for the
+/// rest, in which the count is named `count`.
+pub(crate) fn count(count: u64, one: &str, other: &str) -> String {
+	text(if count == 1 { one } else { other }, &[("count", &count)])
+}
@@ -0,0 +1,37 @@
+use std::{sync::Once, time::Duration};
+
+use metrics::Unit;
+
+use crate::{format, Status};
+
+/// Describes the metrics jeflog records, the first time any is recorded.
+fn describe() {
+	static DESCRIBED: Once = Once::new();
+
+	DESCRIBED.call_once(|| {
+		metrics::describe_counter!("jeflog_tasks_started_total", "Tasks that have started.");
+		metrics::describe_counter!("jeflog_tasks_ended_total", "Tasks that have ended, by status.");
+
+		metrics::describe_histogram!(
+			"jeflog_task_duration_seconds",
+			Unit::Seconds,
+			"How long tasks ran for, by status.",
+		);
+	});
+}
+
+/// Records that a task started with `message`.
+pub(crate) fn started(message: &str) {
+	describe();
+	metrics::counter!("jeflog_tasks_started_total", "task" => format::plain(message)).increment(1);
+}
+
+/// Records that a task started with `message` ended with `status`
+/// after running for `duration`.
+pub(crate) fn ended(message: &str, status: Status, duration: Duration) {
+	describe();
+
+	let labels = [("task", format::plain(message)), ("status", status.name().to_owned())];
+	metrics::counter!("jeflog_tasks_ended_total", &labels).increment(1);
+	metrics::histogram!("jeflog_task_duration_seconds", &labels).record(duration.as_secs_f64());
+}
@@ -0,0 +1,124 @@
+use alloc::string::String;
+use core::time::Duration;
+
+use crate::{Event, Status};
+
+/// What happens to a task that ends normally after one of its subtasks,
+/// or any task beneath it, has failed. This is set by [`set_propagation`](crate::set_propagation),
+/// or for a [`Tracker`](crate::Tracker), by its
+/// [`with_propagation`](crate::Tracker::with_propagation).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Propagation {
+	/// The task ends with the status it is given. This is the default.
+	Off,
+	/// A task that passes is shown as having warned instead.
+	Warn,
+	/// A task that passes or warns is shown as having failed instead.
+	Fail,
+}
+
+impl Propagation {
+	/// Returns the status a task ends with when it is ended with `status`,
+	/// given whether any task beneath it failed.
+	pub(crate) fn settle(self, status: Status, failed_descendant: bool) -> Status {
+		if !failed_descendant {
+			return status;
+		}
+
+		// cancelled tasks are left alone, since they never finished
+		match (self, status) {
+			(Self::Fail, Status::Pass | Status::Warn) => Status::Fail,
+			(Self::Warn, Status::Pass) => Status::Warn,
+			(_, status) => status,
+		}
+	}
+}
+
+/// Where a task is nested, which is what its events need to place it in
+/// the tree.
+///
+/// The tasks drawn in a terminal and those of a [`Tracker`](crate::Tracker)
+/// build their events the same way through this, and end the same way
+/// after a failure beneath them, so one can be swapped for the other
+/// without anything reading the events noticing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Node {
+	pub id: u64,
+	pub parent: Option<u64>,
+	pub depth: usize,
+}
+
+impl Node {
+	/// The event of the task starting at `time`.
+	pub fn start(self, time: Duration, message: String) -> Event {
+		Event::Start {
+			id: self.id,
+			parent: self.parent,
+			depth: self.depth,
+			time,
+			message,
+		}
+	}
+
+	/// The event of the task ending at `time` with `status`, after
+	/// running for `duration`.
+	pub fn end(self, time: Duration, status: Status, duration: Duration, message: String) -> Event {
+		Event::End {
+			id: self.id,
+			parent: self.parent,
+			depth: self.depth,
+			time,
+			status,
+			duration,
+			message,
+		}
+	}
+
+	/// The event of the task reporting that it is `current` out of `total`
+	/// units along at `time`.
+	pub fn progress(self, time: Duration, current: u64, total: u64) -> Event {
+		Event::Progress {
+			id: self.id,
+			parent: self.parent,
+			depth: self.depth,
+			time,
+			current,
+			total,
+		}
+	}
+
+	/// The event of the task being found at `time` to have stalled, after
+	/// going `idle` without any activity.
+	#[cfg(feature = "std")]
+	pub fn stall(self, time: Duration, idle: Duration) -> Event {
+		Event::Stall {
+			id: self.id,
+			parent: self.parent,
+			depth: self.depth,
+			time,
+			idle,
+		}
+	}
+}
+
+/// Returns whether a task that ended with `status` counts as a failure
+/// beneath the task it is nested under, which it does if it failed or if
+/// anything beneath it did, even if it ended otherwise.
+pub(crate) fn fails_parent(status: Status, failed_descendant: bool) -> bool {
+	status == Status::Fail || failed_descendant
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn failures_are_propagated_as_set() {
+		assert_eq!(Propagation::Off.settle(Status::Pass, true), Status::Pass);
+		assert_eq!(Propagation::Warn.settle(Status::Pass, true), Status::Warn);
+		assert_eq!(Propagation::Warn.settle(Status::Warn, true), Status::Warn);
+		assert_eq!(Propagation::Fail.settle(Status::Warn, true), Status::Fail);
+		assert_eq!(Propagation::Fail.settle(Status::Cancel, true), Status::Cancel);
+		assert_eq!(Propagation::Fail.settle(Status::Pass, false), Status::Pass);
+	}
+}
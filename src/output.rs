@@ -0,0 +1,201 @@
+use std::{
+	env,
+	fs::File,
+	io::{self, IsTerminal, Write},
+	path::PathBuf,
+	sync::{
+		atomic::{AtomicU64, AtomicUsize, Ordering},
+		Mutex, OnceLock,
+	},
+};
+
+use crate::{config::CONFIG, sync::Recover, time::Instant};
+
+struct Mirror {
+	file: File,
+	opened: Instant,
+}
+
+// opened lazily on the first write, so the environment
+// variable only needs to be set before the first task starts
+static MIRROR: OnceLock<Option<Mutex<Mirror>>> = OnceLock::new();
+
+// how many frontends are drawing the task tree themselves, during
+// which nothing is written to the terminal they own
+static MUTED: AtomicUsize = AtomicUsize::new(0);
+
+// how many lines have been written, which moves every row
+// on the screen up by as many rows
+static LINES: AtomicU64 = AtomicU64::new(0);
+
+#[cfg(test)]
+thread_local! {
+	// what has been written on this thread, which tests read back
+	// rather than it going to the terminal
+	static CAPTURED: std::cell::RefCell<String> = const { std::cell::RefCell::new(String::new()) };
+}
+
+/// The stream that jeflog draws on, chosen with
+/// [`ConfigBuilder::output`](crate::ConfigBuilder::output).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stream {
+	/// Standard output. This is the default.
+	Stdout,
+	/// Standard error, which leaves standard output to the lines printed
+	/// with [`data!`](crate::data).
+	Stderr,
+}
+
+/// Stops writing to the terminal until a matching call to [`unmute`].
+#[cfg_attr(not(any(feature = "browse", feature = "ratatui")), allow(dead_code))]
+pub(crate) fn mute() {
+	MUTED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Undoes one call to [`mute`].
+#[cfg_attr(not(any(feature = "browse", feature = "ratatui")), allow(dead_code))]
+pub(crate) fn unmute() {
+	MUTED.fetch_sub(1, Ordering::Relaxed);
+}
+
+/// Returns whether writing to the terminal is muted.
+pub(crate) fn muted() -> bool {
+	MUTED.load(Ordering::Relaxed) > 0
+}
+
+/// Writes rendered text to the terminal and flushes it immediately.
+///
+/// Every byte jeflog prints goes through this function (or through a
+/// crossterm frame, which calls [`written`] itself), so that it can also
+/// be mirrored to a render trace when debugging layouts.
+pub(crate) fn emit(text: &str) {
+	if text.is_empty() || muted() {
+		return;
+	}
+
+	let text = &*crate::format::uncolored(text);
+
+	#[cfg(test)]
+	CAPTURED.with_borrow_mut(|captured| captured.push_str(text));
+
+	if !cfg!(test) {
+		// standard output goes nowhere in the browser, so its console is used
+		#[cfg(all(target_family = "wasm", target_os = "unknown"))]
+		crate::format::console::log(text);
+
+		// output is best effort; a closed stream should not take down the program
+		let mut stream = stream();
+		_ = stream.write_all(text.as_bytes());
+		_ = stream.flush();
+	}
+
+	written(text);
+}
+
+/// Takes what has been written on this thread since the last call.
+#[cfg(test)]
+pub(crate) fn captured() -> String {
+	CAPTURED.take()
+}
+
+/// Locks the stream that jeflog draws on, which is standard output
+/// unless [`set_stderr`](crate::set_stderr) is enabled.
+pub(crate) fn stream() -> Box<dyn Write> {
+	if on_stderr() {
+		Box::new(io::stderr().lock())
+	} else {
+		Box::new(io::stdout().lock())
+	}
+}
+
+/// Returns whether jeflog draws on standard error.
+pub(crate) fn on_stderr() -> bool {
+	CONFIG.read().recover().stderr
+}
+
+/// Returns whether the stream that jeflog draws on is a terminal.
+pub(crate) fn is_terminal() -> bool {
+	if on_stderr() {
+		io::stderr().is_terminal()
+	} else {
+		io::stdout().is_terminal()
+	}
+}
+
+/// Writes a line of data to standard output exactly as it is given,
+/// regardless of where jeflog draws.
+pub(crate) fn data(line: &str) {
+	let mut stdout = io::stdout().lock();
+	_ = writeln!(stdout, "{line}");
+	_ = stdout.flush();
+
+	LINES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Returns how many lines jeflog has written so far, which tells whether
+/// a row it drew earlier is still where it was drawn.
+pub(crate) fn lines() -> u64 {
+	LINES.load(Ordering::Relaxed)
+}
+
+/// Takes back `lines` of the lines just written, which only made room on
+/// the screen and were moved back over, so that nothing was moved up.
+pub(crate) fn unwritten(lines: u64) {
+	LINES.fetch_sub(lines, Ordering::Relaxed);
+}
+
+/// Counts the lines in text that was written to the terminal, and mirrors
+/// it to the render trace.
+pub(crate) fn written(text: &str) {
+	LINES.fetch_add(text.matches('\n').count() as u64, Ordering::Relaxed);
+	trace(text);
+}
+
+/// Mirrors text that was written to the terminal to the render trace,
+/// if `JEFLOG_DEBUG_RENDER` is set.
+fn trace(text: &str) {
+	if let Some(mirror) = MIRROR.get_or_init(open_mirror) {
+		let mut mirror = mirror.lock().recover();
+		let elapsed = mirror.opened.elapsed().as_secs_f64();
+
+		_ = writeln!(mirror.file, "[{elapsed:>10.3}] {}", escape(text));
+	}
+}
+
+/// Opens the render trace named by `JEFLOG_DEBUG_RENDER`, if any.
+///
+/// A value of `1` writes the trace to `jeflog-render.log` in the current
+/// directory, and any other non-empty value (besides `0`) is used as the path.
+fn open_mirror() -> Option<Mutex<Mirror>> {
+	let value = env::var_os("JEFLOG_DEBUG_RENDER")?;
+
+	let path = if value == "1" {
+		PathBuf::from("jeflog-render.log")
+	} else if value.is_empty() || value == "0" {
+		return None;
+	} else {
+		PathBuf::from(value)
+	};
+
+	let file = File::create(path).ok()?;
+	Some(Mutex::new(Mirror { file, opened: Instant::now() }))
+}
+
+/// Makes escape sequences and other control characters visible,
+/// so that a single write fits on a single line of the trace.
+fn escape(text: &str) -> String {
+	let mut escaped = String::with_capacity(text.len());
+
+	for c in text.chars() {
+		match c {
+			'\x1b' => escaped.push_str("\\e"),
+			'\n' => escaped.push_str("\\n"),
+			'\r' => escaped.push_str("\\r"),
+			'\\' => escaped.push_str("\\\\"),
+			c if c.is_control() => escaped.push_str(&format!("\\x{:02x}", c as u32)),
+			c => escaped.push(c),
+		}
+	}
+
+	escaped
+}
@@ -0,0 +1,89 @@
+use crate::{config::CONFIG, style, sync::Recover};
+
+/// Shortens `path` to fit within `width` cells by replacing components
+/// in its middle with `…`, as in `/home/…/deep/file.rs`, since the end
+/// of a path is usually the part worth reading.
+///
+/// The first component is kept along with as many of the last ones as
+/// fit. A file name that doesn't fit by itself is cut from its start.
+/// Paths that already fit are returned as they are.
+pub fn shorten_path(path: impl AsRef<str>, width: usize) -> String {
+	let path = path.as_ref();
+
+	if style::width(path) <= width {
+		return path.to_owned();
+	}
+
+	let separator = if path.contains('/') { '/' } else { '\\' };
+	let components = path.split(separator).collect::<Vec<_>>();
+
+	// a leading separator belongs to the first component, as does a
+	// drive letter such as `C:`
+	let head = match components.iter().position(|component| !component.is_empty()) {
+		Some(index) => components[..=index].join(&separator.to_string()),
+		None => return path.to_owned(),
+	};
+
+	let head_components = head.matches(separator).count() + 1;
+	let mut tail = Vec::new();
+	let mut used = style::width(&head) + 3;
+
+	for component in components[head_components..].iter().rev() {
+		let cost = style::width(component) + usize::from(!tail.is_empty());
+
+		// the components kept from the end are never the head's
+		if used + cost > width || tail.len() + head_components + 1 >= components.len() {
+			break;
+		}
+
+		used += cost;
+		tail.push(*component);
+	}
+
+	tail.reverse();
+
+	if !tail.is_empty() {
+		return format!("{head}{separator}…{separator}{}", tail.join(&separator.to_string()));
+	}
+
+	// without room for the head, only the end of the file name is kept
+	let name = components.last().copied().unwrap_or_default();
+
+	if style::width(name) + 2 <= width {
+		return format!("…{separator}{name}");
+	}
+
+	let mut kept = name.chars().rev().collect::<Vec<_>>();
+
+	while !kept.is_empty() && style::width(&kept.iter().collect::<String>()) + 1 > width {
+		kept.pop();
+	}
+
+	format!("…{}", kept.iter().rev().collect::<String>())
+}
+
+/// Shortens every path in `text` that is wider than the limit set by
+/// [`set_max_path_width`](crate::set_max_path_width), if there is one.
+///
+/// A path is any word with a separator in it, other than links and
+/// words that contain markup or escape sequences.
+pub(crate) fn shorten_all(text: &str) -> String {
+	let Some(width) = CONFIG.read().recover().max_path_width else {
+		return text.to_owned();
+	};
+
+	text.split(' ')
+		.map(|word| {
+			let path = (word.contains('/') || word.contains('\\'))
+				&& !word.contains("://")
+				&& !word.contains(['{', '\x1b']);
+
+			if path {
+				shorten_path(word, width)
+			} else {
+				word.to_owned()
+			}
+		})
+		.collect::<Vec<_>>()
+		.join(" ")
+}
@@ -0,0 +1,75 @@
+use std::io::{self, IsTerminal};
+
+use crate::{format, locale, redact, style, sync::Recover, Format, TASKS};
+
+/// Asks a question beneath the running tasks and waits for a line of
+/// input, returned without its line ending.
+///
+/// Rendering is paused until the question is answered, so the spinners
+/// don't draw over what is being typed.
+#[macro_export]
+macro_rules! prompt {
+	($($tokens:tt)*) => {
+		$crate::__prompt__(format!($($tokens)*))
+	}
+}
+
+/// Asks a yes or no question beneath the running tasks, returning `true`
+/// only if it is answered with `y` or `yes`, or with their translations
+/// if a [localizer](crate::set_localizer) gives them.
+///
+/// As with `prompt!`, rendering is paused until the question is answered.
+#[macro_export]
+macro_rules! confirm {
+	($($tokens:tt)*) => {
+		$crate::__confirm__(format!($($tokens)*))
+	}
+}
+
+#[doc(hidden)]
+pub fn __confirm__(question: String) -> bool {
+	let answer = __prompt__(format!("{question} {}", locale::text("[y/N]", &[])));
+	let answer = answer.trim().to_lowercase();
+
+	// the answers in English are taken whatever language the question is in
+	matches!(answer.as_str(), "y" | "yes") || [locale::text("y", &[]), locale::text("yes", &[])].contains(&answer)
+}
+
+#[doc(hidden)]
+pub fn __prompt__(question: String) -> String {
+	// holding the lock for the whole prompt keeps the spinner, and every
+	// other thread, from drawing until the question has been answered
+	let mut tasks = TASKS.lock().recover();
+	let question = style::markup(&style::sanitize(&redact::apply(&question)));
+	let enclosing = crate::enclosing(&tasks);
+	let interactive = format::current() == Format::Interactive;
+
+	if interactive {
+		format::interactive::ask(&mut tasks, enclosing, &question);
+	} else {
+		let depth = enclosing.map_or(0, |index| tasks[index].depth + 1) + format::base_depth();
+
+		match format::current() {
+			Format::Porcelain => format::porcelain::ask(depth, &question),
+			Format::Json => format::json::ask(enclosing.map(|index| tasks[index].id), depth, &question),
+			_ => format::sequential::ask(depth, &question),
+		}
+	}
+
+	// an unreadable standard input is treated as an empty answer
+	let mut answer = String::new();
+	_ = io::stdin().read_line(&mut answer);
+
+	// the terminal echoes the newline that ends the answer, but nothing
+	// does if input ended without one or isn't coming from a terminal
+	let echoed = answer.ends_with('\n') && io::stdin().is_terminal();
+
+	// a question in the porcelain and JSON formats is already on a line of its own
+	match format::current() {
+		Format::Interactive => format::interactive::answered(&tasks, echoed),
+		Format::Porcelain | Format::Json => {},
+		_ => format::sequential::answered(echoed),
+	}
+
+	answer.trim_end_matches(['\r', '\n']).to_owned()
+}
@@ -0,0 +1,84 @@
+use std::sync::RwLock;
+
+use crate::sync::Recover;
+
+/// Text that is masked wherever it appears in messages, added with
+/// [`add_redaction`].
+///
+/// This is implemented for literal strings, which are replaced
+/// wherever they appear, and for functions from the text of a message
+/// to its redacted form, for secrets that can't be matched literally.
+pub trait Redaction: Send + Sync + 'static {
+	/// Returns the text with any secrets it contains masked.
+	fn redact(&self, text: &str) -> String;
+}
+
+impl Redaction for &'static str {
+	fn redact(&self, text: &str) -> String {
+		mask(text, self)
+	}
+}
+
+impl Redaction for String {
+	fn redact(&self, text: &str) -> String {
+		mask(text, self)
+	}
+}
+
+impl<F> Redaction for F
+where
+	F: Fn(&str) -> String + Send + Sync + 'static,
+{
+	fn redact(&self, text: &str) -> String {
+		self(text)
+	}
+}
+
+/// Replaces every occurrence of a literal secret in the text.
+fn mask(text: &str, secret: &str) -> String {
+	// an empty secret would match between every character
+	if secret.is_empty() {
+		return text.to_owned();
+	}
+
+	text.replace(secret, "****")
+}
+
+static REDACTIONS: RwLock<Vec<Box<dyn Redaction>>> = RwLock::new(Vec::new());
+
+/// Masks a secret wherever it would appear in task messages, log lines,
+/// or output written through [`stdout`](crate::stdout).
+///
+/// Since messages are redacted before they are printed or recorded, the
+/// secret doesn't reach the terminal, the render log, or the session.
+pub fn add_redaction(redaction: impl Redaction) {
+	REDACTIONS.write().recover().push(Box::new(redaction));
+}
+
+/// Applies every redaction to a message, in the order they were added.
+pub(crate) fn apply(message: &str) -> String {
+	REDACTIONS
+		.read()
+		.recover()
+		.iter()
+		.fold(message.to_owned(), |message, redaction| redaction.redact(&message))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn literal_secrets_are_masked_everywhere() {
+		assert_eq!("hunter2".redact("pass=hunter2, again hunter2"), "pass=****, again ****");
+		assert_eq!(String::new().redact("nothing to hide"), "nothing to hide");
+	}
+
+	#[test]
+	fn redactions_are_applied_in_order() {
+		add_redaction("7d1e0c-test-token");
+		add_redaction(|text: &str| text.replace("****@", "user@"));
+
+		assert_eq!(apply("sent 7d1e0c-test-token@host"), "sent user@host");
+	}
+}
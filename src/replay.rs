@@ -0,0 +1,368 @@
+use std::{collections::HashMap, thread, time::Duration};
+
+use crate::{
+	json::{self, Value},
+	style, time, Record, Session, Status,
+};
+
+/// A run recorded earlier, which [`replay`] draws again.
+///
+/// A recording is made from a [`Session`], or from the JSON text of one
+/// written by [`Session::to_json`], or from the lines of events written
+/// by the JSON format or by [`Event::to_json`](crate::Event::to_json),
+/// such as a file attached to a bug report. Text that can't be read as
+/// either is an empty recording, as are the lines in it that aren't events.
+#[derive(Clone, Debug, Default)]
+pub struct Recording {
+	// what happened, and how long after the recording began
+	steps: Vec<(Duration, Step)>,
+}
+
+#[derive(Clone, Debug)]
+enum Step {
+	Start { id: u64, parent: Option<u64>, message: String },
+	Text { task: Option<u64>, text: String },
+	Progress { id: u64, current: u64, total: u64 },
+	End { id: u64, status: Status, message: String, hint: Option<String> },
+}
+
+/// A recorded task, read from a session or from its JSON.
+struct Task {
+	message: String,
+	end_message: String,
+	status: Status,
+	// when the task started, since the Unix epoch or the clock's fixed
+	// point, if it was recorded
+	started: Option<Duration>,
+	duration: Duration,
+	hint: Option<String>,
+	output: Vec<String>,
+	children: Vec<Task>,
+}
+
+impl Task {
+	fn from_record(record: &Record) -> Self {
+		Self {
+			message: record.message.clone(),
+			end_message: record.end_message.clone(),
+			status: record.status,
+			started: Some(record.started.since_origin()),
+			duration: record.duration,
+			hint: record.hint.clone(),
+			output: record.output.clone(),
+			children: record.children.iter().map(Self::from_record).collect(),
+		}
+	}
+
+	fn from_json(task: &Value) -> Self {
+		let text = |key| task.get(key).and_then(Value::as_str).map(str::to_owned);
+		let message = text("message").unwrap_or_default();
+
+		let started = task.get("started").and_then(|started| match started {
+			Value::Number(millis) => Some(Duration::from_millis(*millis as u64)),
+			Value::String(started) => time::parse_rfc3339(started),
+			_ => None,
+		});
+
+		Self {
+			end_message: text("end_message").unwrap_or_else(|| message.clone()),
+			message,
+			status: text("status").as_deref().and_then(Status::from_name).unwrap_or(Status::Pass),
+			started,
+			duration: Duration::from_millis(task.get("duration_ms").and_then(Value::as_f64).unwrap_or(0.0) as u64),
+			hint: text("hint"),
+			output: task.get("output")
+				.map_or(&[][..], Value::as_array)
+				.iter()
+				.filter_map(Value::as_str)
+				.map(str::to_owned)
+				.collect(),
+			children: task.get("children").map_or(&[][..], Value::as_array).iter().map(Self::from_json).collect(),
+		}
+	}
+
+	/// Returns when the first of `tasks` or their subtasks started, if any
+	/// did when it was recorded.
+	fn origin(tasks: &[Self]) -> Option<Duration> {
+		tasks.iter()
+			.flat_map(|task| task.started.into_iter().chain(Self::origin(&task.children)))
+			.min()
+	}
+}
+
+impl Recording {
+	/// Lays out the steps of `tasks`, nested under `parent`. A task that
+	/// wasn't recorded starting starts as the one before it ends, or as
+	/// `parent` starts if it's the first, and its lines are spread evenly
+	/// over the time it ran, since they were recorded without times.
+	///
+	/// Returns when the last of them ends, which is never before the last of
+	/// their subtasks does, even though each time was rounded when recorded.
+	fn lay_out(&mut self, tasks: &[Task], parent: Option<u64>, mut after: Duration, origin: Duration) -> Duration {
+		let mut last = after;
+
+		for task in tasks {
+			let id = self.steps.len() as u64;
+			let start = task.started.map_or(after, |started| started.saturating_sub(origin));
+			let mut end = start.saturating_add(task.duration);
+
+			self.steps.push((start, Step::Start {
+				id,
+				parent,
+				message: task.message.clone(),
+			}));
+
+			let count = task.output.len() as f64;
+
+			for (index, text) in task.output.iter().enumerate() {
+				let time = start.saturating_add(task.duration.mul_f64((index as f64 + 1.0) / (count + 1.0)));
+
+				self.steps.push((time, Step::Text {
+					task: Some(id),
+					text: text.clone(),
+				}));
+			}
+
+			end = end.max(self.lay_out(&task.children, Some(id), start, origin));
+
+			self.steps.push((end, Step::End {
+				id,
+				status: task.status,
+				message: task.end_message.clone(),
+				hint: task.hint.clone(),
+			}));
+
+			after = end;
+			last = last.max(end);
+		}
+
+		last
+	}
+
+	fn from_tasks(tasks: &[Task]) -> Self {
+		let mut recording = Self::default();
+		recording.lay_out(tasks, None, Duration::ZERO, Task::origin(tasks).unwrap_or_default());
+		recording
+	}
+
+	/// Reads lines of events, each at the time it was sent, or at the time
+	/// of the one before it if it has none.
+	fn from_events(text: &str) -> Self {
+		let mut recording = Self::default();
+		let mut origin = None;
+		let mut time = Duration::ZERO;
+
+		for event in text.lines().filter_map(json::parse) {
+			let number = |key| event.get(key).and_then(Value::as_f64).map(|number| number as u64);
+			let text = |key| event.get(key).and_then(Value::as_str).map(str::to_owned).unwrap_or_default();
+
+			if let Some(millis) = number("time_ms") {
+				let origin = *origin.get_or_insert(millis);
+				time = time.max(Duration::from_millis(millis.saturating_sub(origin)));
+			}
+
+			let step = match (event.get("event").and_then(Value::as_str), number("id")) {
+				(Some("start"), Some(id)) => Step::Start {
+					id,
+					parent: number("parent"),
+					message: text("message"),
+				},
+				(Some("end"), Some(id)) => Step::End {
+					id,
+					status: Status::from_name(&text("status")).unwrap_or(Status::Pass),
+					message: text("message"),
+					hint: None,
+				},
+				(Some("progress"), Some(id)) => Step::Progress {
+					id,
+					current: number("current").unwrap_or(0),
+					total: number("total").unwrap_or(0),
+				},
+				(Some("text"), _) => Step::Text {
+					task: number("task"),
+					text: text("text"),
+				},
+				_ => continue,
+			};
+
+			recording.steps.push((time, step));
+		}
+
+		recording
+	}
+}
+
+impl From<&Session> for Recording {
+	fn from(session: &Session) -> Self {
+		Self::from_tasks(&session.tasks.iter().map(Task::from_record).collect::<Vec<_>>())
+	}
+}
+
+impl From<Session> for Recording {
+	fn from(session: Session) -> Self {
+		Self::from(&session)
+	}
+}
+
+impl From<&str> for Recording {
+	fn from(text: &str) -> Self {
+		match json::parse(text) {
+			Some(session) if session.get("tasks").is_some() => {
+				let tasks = session.get("tasks").map_or(&[][..], Value::as_array);
+				Self::from_tasks(&tasks.iter().map(Task::from_json).collect::<Vec<_>>())
+			},
+			_ => Self::from_events(text),
+		}
+	}
+}
+
+impl From<String> for Recording {
+	fn from(text: String) -> Self {
+		Self::from(text.as_str())
+	}
+}
+
+/// Draws a recorded run again, as tasks started and ended when they were
+/// recorded, so that a run shared in a bug report can be watched as it
+/// happened. The call returns once the whole run has been drawn.
+///
+/// The recording is given as anything a [`Recording`] is made from, such
+/// as a [`Session`] or the text of a file holding one as JSON. It is played
+/// `speed` times as fast as it was recorded, so `1.0` keeps its timing and
+/// `10.0` plays it ten times as fast, and a `speed` that isn't a positive
+/// number plays it without waiting at all. Tasks that never ended in the
+/// recording are cancelled at its end, so that none is left running.
+///
+/// The tasks are drawn in whichever format is set, beneath the task running
+/// on this thread, if any, which makes a recording of events written in the
+/// JSON format readable in the interactive format, for example.
+pub fn replay(recording: impl Into<Recording>, speed: f64) {
+	let mut steps = recording.into().steps;
+	steps.sort_by_key(|&(time, _)| time);
+
+	let outer = crate::enclosing_id();
+	let mut last = Duration::ZERO;
+
+	// the id each recorded task is running with, and its message,
+	// in the order they started
+	let mut running = HashMap::new();
+	let mut order = Vec::new();
+
+	for (time, step) in steps {
+		if speed > 0.0 && speed.is_finite() {
+			// a speed so slow that the wait can't be held waits for good
+			let wait = time.saturating_sub(last).as_secs_f64() / speed;
+			thread::sleep(Duration::try_from_secs_f64(wait).unwrap_or(Duration::MAX));
+		}
+
+		last = time;
+
+		match step {
+			Step::Start { id, parent, message } => {
+				let parent = parent.and_then(|parent| running.get(&parent)).map_or(outer, |&(parent, _)| Some(parent));
+				running.insert(id, (crate::begin_detached(parent, message.clone()), message));
+				order.push(id);
+			},
+			Step::Text { task, text } => {
+				let task = task.and_then(|task| running.get(&task)).map_or(outer, |&(task, _)| Some(task));
+				crate::print_line_in(task, &style::sanitize(&text));
+			},
+			Step::Progress { id, current, total } => {
+				if let Some(&(id, _)) = running.get(&id) {
+					crate::progress_of(id, current, total, String::new());
+				}
+			},
+			Step::End { id, status, message, hint } => {
+				if let Some((id, _)) = running.remove(&id) {
+					crate::end_task_by_id_with_hint(
+						id,
+						status,
+						style::clean(&message),
+						hint.map(|hint| style::clean(&hint)),
+					);
+				}
+			},
+		}
+	}
+
+	// the innermost tasks are ended before the tasks they are nested under
+	for id in order.into_iter().rev() {
+		if let Some((id, message)) = running.remove(&id) {
+			crate::end_task_by_id(id, Status::Cancel, style::clean(&message));
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn kinds(recording: &Recording) -> Vec<(u128, &'static str)> {
+		recording.steps.iter()
+			.map(|(time, step)| {
+				let kind = match step {
+					Step::Start { .. } => "start",
+					Step::Text { .. } => "text",
+					Step::Progress { .. } => "progress",
+					Step::End { .. } => "end",
+				};
+
+				(time.as_millis(), kind)
+			})
+			.collect()
+	}
+
+	#[test]
+	fn sessions_are_laid_out_from_their_start_times() {
+		let recording = Recording::from(
+			r#"{"tasks":[{"message":"a","end_message":"a done","status":"pass","started":1000,"duration_ms":100,"output":["x"],"children":[]},{"message":"b","status":"fail","started":"1970-01-01T00:00:01.500Z","duration_ms":50,"children":[]}]}"#,
+		);
+
+		assert_eq!(kinds(&recording), [(0, "start"), (50, "text"), (100, "end"), (500, "start"), (550, "end")]);
+
+		let Step::End { status, message, .. } = &recording.steps[4].1 else {
+			panic!("the last step should end b");
+		};
+
+		assert_eq!((*status, message.as_str()), (Status::Fail, "b"));
+	}
+
+	#[test]
+	fn tasks_without_start_times_follow_their_siblings() {
+		let recording = Recording::from(
+			r#"{"tasks":[{"message":"a","duration_ms":10,"children":[{"message":"b","duration_ms":30}]},{"message":"c","duration_ms":5}]}"#,
+		);
+
+		// a ends no sooner than b, though it was recorded as shorter
+		assert_eq!(kinds(&recording), [(0, "start"), (0, "start"), (30, "end"), (30, "end"), (30, "start"), (35, "end")]);
+	}
+
+	#[test]
+	fn events_are_read_line_by_line() {
+		let recording = Recording::from(concat!(
+			r#"{"event":"start","id":4,"parent":null,"depth":0,"time_ms":100,"message":"a"}"#, "\n",
+			"not an event\n",
+			r#"{"event":"progress","id":4,"time_ms":150,"current":1,"total":2}"#, "\n",
+			r#"{"event":"text","task":4,"text":"hello"}"#, "\n",
+			r#"{"event":"summary","time_ms":170}"#, "\n",
+			r#"{"event":"end","id":4,"time_ms":180,"status":"warn","message":"a done"}"#, "\n",
+		));
+
+		assert_eq!(kinds(&recording), [(0, "start"), (50, "progress"), (50, "text"), (80, "end")]);
+	}
+
+	#[test]
+	fn huge_durations_do_not_overflow() {
+		let recording = Recording::from(
+			r#"{"tasks":[{"message":"a","started":1e300,"duration_ms":1.8e19,"output":["x","y"]},{"message":"b","duration_ms":1.8e19}]}"#,
+		);
+
+		assert_eq!(recording.steps.len(), 6);
+	}
+
+	#[test]
+	fn unreadable_text_is_an_empty_recording() {
+		assert!(Recording::from("[[[").steps.is_empty());
+		assert!(Recording::from("").steps.is_empty());
+	}
+}
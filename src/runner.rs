@@ -0,0 +1,491 @@
+use std::{
+	collections::HashMap,
+	fmt::Display,
+	panic::{self, AssertUnwindSafe},
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		mpsc,
+		Mutex,
+	},
+	thread,
+};
+
+use crate::{locale, style, sync::Recover, Status};
+
+type Work<'a> = Box<dyn FnOnce() -> Result<(), String> + Send + 'a>;
+
+struct Job<'a> {
+	name: String,
+	dependencies: Vec<String>,
+	work: Work<'a>,
+}
+
+/// How busy the threads running jobs are, which is shown after the message
+/// of the task they are nested under, if there is one, so that it is clear
+/// why jobs that haven't started yet are waiting.
+struct Pool {
+	parent: Option<u64>,
+	workers: usize,
+	counts: Mutex<Counts>,
+}
+
+#[derive(Default)]
+struct Counts {
+	busy: usize,
+	queued: usize,
+	blocked: usize,
+}
+
+impl Pool {
+	fn new(parent: Option<u64>, workers: usize) -> Self {
+		Self {
+			parent,
+			workers,
+			counts: Mutex::default(),
+		}
+	}
+
+	/// Changes the counts and shows them as they are now.
+	fn update(&self, change: impl FnOnce(&mut Counts)) {
+		let mut counts = self.counts.lock().recover();
+		change(&mut counts);
+
+		let Some(parent) = self.parent else {
+			return;
+		};
+
+		let mut note = locale::text("{busy}/{workers} workers busy, {queued} queued", &[
+			("busy", &counts.busy),
+			("workers", &self.workers),
+			("queued", &counts.queued),
+		]);
+
+		if counts.blocked > 0 {
+			note += &locale::text(", {count} waiting on others", &[("count", &counts.blocked)]);
+		}
+
+		// the lock is held so that notes are shown in the order they change
+		crate::note_of(parent, note);
+	}
+
+	/// Stops showing the counts, once every job has ended.
+	fn clear(&self) {
+		if let Some(parent) = self.parent {
+			crate::note_of(parent, String::new());
+		}
+	}
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+	Waiting,
+	Running,
+	Passed,
+	Failed,
+}
+
+/// Runs named pieces of work on a pool of threads, each starting once
+/// the work it depends on has passed.
+///
+/// Each job is shown as a task while it runs, nested under the task that
+/// was running where [`Runner::run`] was called, and any tasks a job starts
+/// itself are nested under its own. A job passes if it returns `Ok`, and
+/// fails with its error otherwise, in which case every job depending on it
+/// is cancelled. Jobs should not end their own task.
+#[derive(Default)]
+pub struct Runner<'a> {
+	jobs: Vec<Job<'a>>,
+}
+
+impl<'a> Runner<'a> {
+	/// Creates a runner without any jobs.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Adds a job that runs `work` once every job named in `dependencies`
+	/// has passed. Names should be unique, as dependencies on a repeated
+	/// name refer to the first job added with it.
+	pub fn add<F, E>(&mut self, name: impl Into<String>, dependencies: &[&str], work: F) -> &mut Self
+	where
+		F: FnOnce() -> Result<(), E> + Send + 'a,
+		E: Display,
+	{
+		self.jobs.push(Job {
+			name: name.into(),
+			dependencies: dependencies.iter().map(|&name| name.to_owned()).collect(),
+			work: Box::new(move || work().map_err(|error| error.to_string())),
+		});
+
+		self
+	}
+
+	/// Runs every job, with at most `parallelism` of them running at once,
+	/// and returns once all of them have ended. This is [`Status::Pass`] if
+	/// every job passed, and [`Status::Fail`] otherwise.
+	///
+	/// While the jobs run, the task this was called in shows how many of
+	/// the threads are busy and how many jobs are waiting for one, or for
+	/// the jobs they depend on, as in `4/8 workers busy, 12 queued`.
+	///
+	/// Jobs that depend on a job that was never added, or that depend on
+	/// each other in a cycle, fail without being run.
+	pub fn run(self, parallelism: usize) -> Status {
+		let count = self.jobs.len();
+		let mut names = Vec::with_capacity(count);
+		let mut dependencies = Vec::with_capacity(count);
+		let mut works = Vec::with_capacity(count);
+
+		for job in self.jobs {
+			names.push(job.name);
+			dependencies.push(job.dependencies);
+			works.push(Some(job.work));
+		}
+
+		// the first job with a name is the one it refers to
+		let indices = names.iter()
+			.enumerate()
+			.rev()
+			.map(|(index, name)| (name.as_str(), index))
+			.collect::<HashMap<_, _>>();
+
+		let mut states = vec![State::Waiting; count];
+		let mut waiting = vec![0; count];
+		let mut dependents = vec![Vec::new(); count];
+		let mut unknown = Vec::new();
+
+		for (job, dependencies) in dependencies.iter().enumerate() {
+			for dependency in dependencies {
+				match indices.get(dependency.as_str()) {
+					Some(&index) => {
+						waiting[job] += 1;
+						dependents[index].push(job);
+					},
+					None => unknown.push((job, dependency)),
+				}
+			}
+		}
+
+		for (job, dependency) in unknown {
+			if states[job] == State::Waiting {
+				fail(&names[job], locale::text("depends on unknown task {task}", &[("task", &format!("{dependency:?}"))]));
+				states[job] = State::Failed;
+				cancel_dependents(job, &names, &dependents, &mut states);
+			}
+		}
+
+		let parent = crate::enclosing_id();
+		let (work_sender, work_receiver) = mpsc::channel::<(usize, Work)>();
+		let (done_sender, done_receiver) = mpsc::channel();
+		let work_receiver = Mutex::new(work_receiver);
+		let workers = parallelism.clamp(1, count.max(1));
+		let pool = Pool::new(parent, workers);
+		let blocked = |states: &[State]| states.iter().filter(|&&state| state == State::Waiting).count();
+
+		thread::scope(|scope| {
+			for _ in 0..workers {
+				let work_receiver = &work_receiver;
+				let done_sender = done_sender.clone();
+				let names = &names;
+				let pool = &pool;
+
+				scope.spawn(move || {
+					crate::inherit(parent);
+
+					// the lock is only held while waiting for the next job
+					while let Ok((job, work)) = {
+						let receiver = work_receiver.lock().recover();
+						receiver.recv()
+					} {
+						pool.update(|counts| {
+							counts.queued -= 1;
+							counts.busy += 1;
+						});
+
+						let id = crate::begin_on_thread(names[job].clone());
+
+						let (status, message) = match panic::catch_unwind(AssertUnwindSafe(work)) {
+							Ok(Ok(())) => (Status::Pass, names[job].clone()),
+							Ok(Err(error)) => (Status::Fail, format!("{}: {error}", names[job])),
+							Err(_) => (Status::Fail, format!("{}: {}", names[job], locale::text("panicked", &[]))),
+						};
+
+						end(id, status, &message);
+						let passed = status == Status::Pass;
+
+						pool.update(|counts| counts.busy -= 1);
+
+						if done_sender.send((job, passed)).is_err() {
+							break;
+						}
+					}
+				});
+			}
+
+			drop(done_sender);
+			let mut running = 0;
+
+			let mut dispatch = |job: usize, states: &mut [State]| {
+				if let Some(work) = works[job].take() {
+					states[job] = State::Running;
+					pool.update(|counts| counts.queued += 1);
+					_ = work_sender.send((job, work));
+					1
+				} else {
+					0
+				}
+			};
+
+			for job in 0..count {
+				if waiting[job] == 0 && states[job] == State::Waiting {
+					running += dispatch(job, &mut states);
+				}
+			}
+
+			pool.update(|counts| counts.blocked = blocked(&states));
+
+			while running > 0 {
+				let Ok((job, passed)) = done_receiver.recv() else {
+					break;
+				};
+
+				running -= 1;
+
+				if passed {
+					states[job] = State::Passed;
+
+					for &dependent in &dependents[job] {
+						waiting[dependent] -= 1;
+
+						if waiting[dependent] == 0 && states[dependent] == State::Waiting {
+							running += dispatch(dependent, &mut states);
+						}
+					}
+				} else {
+					states[job] = State::Failed;
+					cancel_dependents(job, &names, &dependents, &mut states);
+				}
+
+				pool.update(|counts| counts.blocked = blocked(&states));
+			}
+
+			// closing the channel lets the workers finish
+			drop(work_sender);
+		});
+
+		pool.clear();
+
+		// anything still waiting once nothing else can run is stuck
+		// on a cycle of jobs that depend on each other
+		for job in 0..count {
+			if states[job] == State::Waiting {
+				fail(&names[job], locale::text("is part of a dependency cycle", &[]));
+				states[job] = State::Failed;
+			}
+		}
+
+		if states.iter().all(|&state| state == State::Passed) {
+			Status::Pass
+		} else {
+			Status::Fail
+		}
+	}
+}
+
+/// Ends the task of a job, along with any tasks the job left running
+/// inside it, whichever task is innermost on the thread.
+fn end(id: u64, status: Status, message: &str) {
+	crate::cancel_subtasks(id);
+	crate::end_task_by_id(id, status, style::clean(message));
+}
+
+/// Shows a job that failed without being run.
+fn fail(name: &str, reason: String) {
+	let id = crate::begin_on_thread(name.to_owned());
+	end(id, Status::Fail, &format!("{name}: {reason}"));
+}
+
+/// Cancels every job that depends on `failed`, directly or indirectly,
+/// and hasn't ended yet.
+fn cancel_dependents(failed: usize, names: &[String], dependents: &[Vec<usize>], states: &mut [State]) {
+	let mut pending = dependents[failed].clone();
+
+	while let Some(job) = pending.pop() {
+		if states[job] != State::Waiting {
+			continue;
+		}
+
+		states[job] = State::Failed;
+		let id = crate::begin_on_thread(names[job].clone());
+		end(id, Status::Cancel, &format!("{}: {}", names[job], locale::text("{task} failed", &[("task", &names[failed])])));
+		pending.extend_from_slice(&dependents[job]);
+	}
+}
+
+/// Runs `work` once for each of `names` on a pool of threads, showing each
+/// run as a task named after it, and returns what every run returned, in
+/// the order of `names`, as in
+/// `jeflog::parallel(["lint", "test", "docs"], |name| check(name))`.
+///
+/// The tasks are siblings nested under the task running where this was
+/// called, and each passes if its run returns `Ok`, and fails with its
+/// error otherwise. Unlike a [`Runner`], every run goes ahead whether or
+/// not the others fail. A run that panics is shown as failed, and the
+/// panic is passed on once the other runs have ended. As with a
+/// [`Runner`], the task this is called in shows how busy the threads are.
+pub fn parallel<N, F, T, E>(names: impl IntoIterator<Item = N>, work: F) -> Vec<Result<T, E>>
+where
+	N: Into<String>,
+	F: Fn(&str) -> Result<T, E> + Sync,
+	T: Send,
+	E: Display + Send,
+{
+	let names = names.into_iter().map(Into::into).collect::<Vec<String>>();
+	let results = Mutex::new((0..names.len()).map(|_| None).collect::<Vec<_>>());
+	let next = AtomicUsize::new(0);
+	let panicked = Mutex::new(None);
+	let parent = crate::enclosing_id();
+
+	let workers = thread::available_parallelism()
+		.map_or(1, usize::from)
+		.clamp(1, names.len().max(1));
+
+	let pool = Pool::new(parent, workers);
+	pool.update(|counts| counts.queued = names.len());
+
+	thread::scope(|scope| {
+		for _ in 0..workers {
+			scope.spawn(|| {
+				crate::inherit(parent);
+
+				loop {
+					let index = next.fetch_add(1, Ordering::Relaxed);
+
+					let Some(name) = names.get(index) else {
+						break;
+					};
+
+					pool.update(|counts| {
+						counts.queued -= 1;
+						counts.busy += 1;
+					});
+
+					let id = crate::begin_on_thread(name.clone());
+					let result = panic::catch_unwind(AssertUnwindSafe(|| work(name)));
+					pool.update(|counts| counts.busy -= 1);
+
+					match result {
+						Ok(result) => {
+							match &result {
+								Ok(_) => end(id, Status::Pass, name),
+								Err(error) => end(id, Status::Fail, &format!("{name}: {error}")),
+							}
+
+							results.lock().recover()[index] = Some(result);
+						},
+						Err(payload) => {
+							end(id, Status::Fail, &format!("{name}: {}", locale::text("panicked", &[])));
+
+							// the worker goes on to the next run, and the first
+							// panic is passed on once every run has ended
+							panicked.lock().recover().get_or_insert(payload);
+						},
+					}
+				}
+			});
+		}
+	});
+
+	pool.clear();
+
+	if let Some(payload) = panicked.into_inner().recover() {
+		panic::resume_unwind(payload);
+	}
+
+	// every run has stored its result, since none of them panicked
+	results.into_inner()
+		.recover()
+		.into_iter()
+		.flatten()
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Adds a job to `runner` that notes its name in `ran` when it runs,
+	/// and fails if `fails`.
+	fn job<'a>(runner: &mut Runner<'a>, ran: &'a Mutex<Vec<&'static str>>, name: &'static str, dependencies: &[&str], fails: bool) {
+		runner.add(name, dependencies, move || {
+			ran.lock().recover().push(name);
+			if fails { Err("broken") } else { Ok(()) }
+		});
+	}
+
+	#[test]
+	fn jobs_run_after_what_they_depend_on() {
+		let ran = Mutex::new(Vec::new());
+		let mut runner = Runner::new();
+		job(&mut runner, &ran, "link", &["compile", "assets"], false);
+		job(&mut runner, &ran, "compile", &[], false);
+		job(&mut runner, &ran, "assets", &[], false);
+		job(&mut runner, &ran, "package", &["link"], false);
+
+		assert_eq!(runner.run(4), Status::Pass);
+
+		let ran = ran.into_inner().recover();
+		let position = |name| ran.iter().position(|&job| job == name).unwrap();
+		assert_eq!(ran.len(), 4);
+		assert!(position("compile") < position("link") && position("assets") < position("link"));
+		assert!(position("link") < position("package"));
+	}
+
+	#[test]
+	fn failures_keep_dependents_from_running() {
+		let ran = Mutex::new(Vec::new());
+		let mut runner = Runner::new();
+		job(&mut runner, &ran, "fetch", &[], true);
+		job(&mut runner, &ran, "build", &["fetch"], false);
+		job(&mut runner, &ran, "deploy", &["build"], false);
+		job(&mut runner, &ran, "lint", &[], false);
+
+		assert_eq!(runner.run(1), Status::Fail);
+
+		let mut ran = ran.into_inner().recover();
+		ran.sort_unstable();
+		assert_eq!(ran, ["fetch", "lint"]);
+	}
+
+	#[test]
+	fn cycles_and_unknown_dependencies_are_never_run() {
+		let ran = Mutex::new(Vec::new());
+		let mut runner = Runner::new();
+		job(&mut runner, &ran, "a", &["b"], false);
+		job(&mut runner, &ran, "b", &["a"], false);
+		job(&mut runner, &ran, "c", &["missing"], false);
+		job(&mut runner, &ran, "d", &[], false);
+
+		assert_eq!(runner.run(2), Status::Fail);
+		assert_eq!(ran.into_inner().recover(), ["d"]);
+	}
+
+	#[test]
+	fn parallel_runs_keep_their_order_and_pass_on_panics() {
+		let results = parallel(["1", "2", "x"], |name| name.parse::<u32>());
+		assert_eq!(results[0], Ok(1));
+		assert_eq!(results[1], Ok(2));
+		assert!(results[2].is_err());
+
+		let ran = AtomicUsize::new(0);
+		let panicked = panic::catch_unwind(|| {
+			parallel(["a", "b", "c"], |name| {
+				ran.fetch_add(1, Ordering::Relaxed);
+				if name == "b" { panic!("b broke") } else { Ok::<_, String>(()) }
+			})
+		});
+
+		assert_eq!(ran.load(Ordering::Relaxed), 3);
+		assert_eq!(panicked.unwrap_err().downcast_ref::<&str>(), Some(&"b broke"));
+	}
+}
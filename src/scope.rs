@@ -0,0 +1,55 @@
+use std::thread::{self, JoinHandle, ScopedJoinHandle};
+
+/// A scope for spawning threads whose tasks are nested under the task
+/// running where they were spawned, created by [`scope`].
+#[derive(Clone, Copy)]
+pub struct Scope<'scope, 'env: 'scope> {
+	inner: &'scope thread::Scope<'scope, 'env>,
+}
+
+impl<'scope> Scope<'scope, '_> {
+	/// Spawns a scoped thread, just as [`thread::Scope::spawn`] does. Tasks
+	/// it starts are nested under the task that was running on this thread
+	/// when it was spawned, rather than being shown at the top level.
+	pub fn spawn<F, T>(&self, work: F) -> ScopedJoinHandle<'scope, T>
+	where
+		F: FnOnce() -> T + Send + 'scope,
+		T: Send + 'scope,
+	{
+		let parent = crate::enclosing_id();
+
+		self.inner.spawn(move || {
+			crate::inherit(parent);
+			work()
+		})
+	}
+}
+
+/// Creates a scope for spawning threads, just as [`thread::scope`] does,
+/// except that tasks started by threads spawned in it are nested under the
+/// task that was running when each thread was spawned.
+///
+/// Like any other task, a task started by a spawned thread is ended by that
+/// thread, and may end before or after tasks running on other threads.
+pub fn scope<'env, F, T>(work: F) -> T
+where
+	F: for<'scope> FnOnce(&Scope<'scope, 'env>) -> T,
+{
+	thread::scope(|inner| work(&Scope { inner }))
+}
+
+/// Spawns a thread, just as [`thread::spawn`] does, except that tasks it
+/// starts are nested under the task that was running on this thread when
+/// it was spawned.
+pub fn spawn<F, T>(work: F) -> JoinHandle<T>
+where
+	F: FnOnce() -> T + Send + 'static,
+	T: Send + 'static,
+{
+	let parent = crate::enclosing_id();
+
+	thread::spawn(move || {
+		crate::inherit(parent);
+		work()
+	})
+}
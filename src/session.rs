@@ -0,0 +1,338 @@
+use std::{
+	cmp::Reverse,
+	fmt::{self, Display, Formatter, Write},
+	sync::Mutex,
+	time::Duration,
+};
+
+use crate::{config::CONFIG, format::plain, json, locale, sync::Recover, time::{self, Instant}, Status, Task};
+
+/// A completed task, along with every subtask it contained.
+#[derive(Clone, Debug)]
+pub struct Record {
+	/// The message the task was started with.
+	pub message: String,
+
+	/// The message the task was ended with.
+	pub end_message: String,
+
+	/// The target the task was started with, if any.
+	pub target: Option<String>,
+
+	/// How the task ended.
+	pub status: Status,
+
+	/// When the task was started.
+	pub started: Instant,
+
+	/// How long the task ran for.
+	pub duration: Duration,
+
+	/// The CPU time the whole process consumed while the task ran.
+	/// This is only measured with the `cpu-time` feature enabled.
+	pub cpu_time: Option<Duration>,
+
+	/// The hint on how to fix the task's failure, if it was given one.
+	pub hint: Option<String>,
+
+	/// The lines printed inside the task, without styles, in the order they
+	/// were printed, including those that were held back or only tailed.
+	/// Lines printed inside its subtasks are in their own records. Only as
+	/// many of the most recent lines are kept as [`set_max_output`](crate::set_max_output)
+	/// allows, after a line saying how many were let go.
+	pub output: Vec<String>,
+
+	/// The subtasks of this task, in the order they were started.
+	pub children: Vec<Record>,
+}
+
+impl Record {
+	/// The time spent in this task's subtasks.
+	///
+	/// This is capped at the task's own duration, since subtasks that
+	/// ran in parallel could otherwise add up to more than their parent.
+	pub fn children_time(&self) -> Duration {
+		self.children.iter()
+			.map(|child| child.duration)
+			.sum::<Duration>()
+			.min(self.duration)
+	}
+
+	/// The time spent in this task outside of any of its subtasks.
+	pub fn self_time(&self) -> Duration {
+		self.duration - self.children_time()
+	}
+}
+
+/// Every task that has been completed so far, arranged as a tree.
+#[derive(Clone, Debug, Default)]
+pub struct Session {
+	/// The top-level tasks, in the order they were started.
+	pub tasks: Vec<Record>,
+}
+
+impl Session {
+	/// Creates a report of how much time each task spent in itself
+	/// versus in its subtasks, which can be printed or written to a file.
+	pub fn profile(&self) -> Profile<'_> {
+		Profile { session: self }
+	}
+
+	/// Writes the session as JSON, for tools that read a run afterward,
+	/// as an object whose `tasks` are the top-level tasks. Each task has
+	/// its `message` and `end_message` without styles, along with its
+	/// `target`, `status`, `hint`, `output`, and `children`, the time it
+	/// `started` in the format set by
+	/// [`set_timestamp_format`](crate::set_timestamp_format), and its
+	/// `duration_ms` and `cpu_time_ms`. Fields that are absent are `null`.
+	pub fn to_json(&self) -> String {
+		fn write(out: &mut String, record: &Record, origin: Duration, now: Instant) {
+			let optional = |text: &Option<String>| text.as_deref().map_or_else(|| String::from("null"), json::string);
+
+			// the system's clock is read once, and each task's start is found
+			// from how long ago it was, so that tasks stay in order even if
+			// the system's clock is changed while they run
+			let started = origin.saturating_sub(now - record.started);
+
+			_ = write!(
+				out,
+				r#"{{"message":{},"end_message":{},"target":{},"status":"{}","started":{},"duration_ms":{},"cpu_time_ms":{},"hint":{},"output":["#,
+				json::string(&plain(&record.message)),
+				json::string(&plain(&record.end_message)),
+				optional(&record.target),
+				record.status.name(),
+				time::timestamp(started),
+				record.duration.as_millis(),
+				record.cpu_time.map_or_else(|| String::from("null"), |cpu| cpu.as_millis().to_string()),
+				optional(&record.hint),
+			);
+
+			for (index, line) in record.output.iter().enumerate() {
+				if index > 0 {
+					out.push(',');
+				}
+
+				out.push_str(&json::string(line));
+			}
+
+			out.push_str(r#"],"children":["#);
+
+			for (index, child) in record.children.iter().enumerate() {
+				if index > 0 {
+					out.push(',');
+				}
+
+				write(out, child, origin, now);
+			}
+
+			out.push_str("]}");
+		}
+
+		let origin = time::wall_clock();
+		let now = Instant::now();
+		let mut out = String::from(r#"{"tasks":["#);
+
+		for (index, record) in self.tasks.iter().enumerate() {
+			if index > 0 {
+				out.push(',');
+			}
+
+			write(&mut out, record, origin, now);
+		}
+
+		out.push_str("]}");
+		out
+	}
+
+	/// Counts how many tasks, along with their subtasks, ended with each
+	/// status, in the order pass, warn, fail, and cancel.
+	pub(crate) fn counts(&self) -> [usize; 4] {
+		let mut counts = [0; 4];
+
+		for found in self.find(|_| true) {
+			counts[found.record.status as usize] += 1;
+		}
+
+		counts
+	}
+
+	/// Returns the `count` tasks that took longest, whether or not they
+	/// were nested under others, slowest first.
+	pub(crate) fn slowest(&self, count: usize) -> Vec<Found<'_>> {
+		let mut found = self.find(|_| true);
+		found.sort_by_key(|found| Reverse(found.record.duration));
+		found.truncate(count);
+		found
+	}
+
+	/// How long it has been since the first task started.
+	pub(crate) fn elapsed(&self) -> Duration {
+		self.tasks.first().map_or(Duration::ZERO, |record| record.started.elapsed())
+	}
+
+	/// Returns every task for which `predicate` returns true, outer tasks
+	/// before the tasks nested under them, in the order they were started.
+	pub fn find(&self, predicate: impl Fn(&Record) -> bool) -> Vec<Found<'_>> {
+		fn walk<'a>(records: &'a [Record], path: &mut Vec<&'a str>, predicate: &dyn Fn(&Record) -> bool, found: &mut Vec<Found<'a>>) {
+			for record in records {
+				path.push(&record.message);
+
+				if predicate(record) {
+					found.push(Found { path: path.clone(), record });
+				}
+
+				walk(&record.children, path, predicate, found);
+				path.pop();
+			}
+		}
+
+		let mut found = Vec::new();
+		walk(&self.tasks, &mut Vec::new(), &predicate, &mut found);
+		found
+	}
+
+	/// Returns every task whose start or end message, hint, or output
+	/// contains `pattern`, with styles removed, just as [`find`](Self::find)
+	/// does, as in `jeflog::session().grep("timeout")`.
+	pub fn grep(&self, pattern: &str) -> Vec<Found<'_>> {
+		self.find(|record| {
+			[&record.message, &record.end_message]
+				.into_iter()
+				.chain(&record.hint)
+				.chain(&record.output)
+				.any(|text| plain(text).contains(pattern))
+		})
+	}
+}
+
+/// A task found in a [`Session`] by [`Session::find`] or [`Session::grep`].
+#[derive(Clone, Debug)]
+pub struct Found<'a> {
+	/// The messages of the task and every task it was nested under,
+	/// from the outermost inward.
+	pub path: Vec<&'a str>,
+
+	/// The task's record, including its subtasks.
+	pub record: &'a Record,
+}
+
+/// A hierarchical time profile of a session, created by [`Session::profile`].
+pub struct Profile<'a> {
+	session: &'a Session,
+}
+
+impl Profile<'_> {
+	fn write_record(f: &mut Formatter, record: &Record, depth: usize) -> fmt::Result {
+		let total = record.duration.as_secs_f64();
+
+		// a task that took no measurable time is considered all self time
+		let (own, children) = if total > 0.0 {
+			let own = record.self_time().as_secs_f64() / total * 100.0;
+			(own, 100.0 - own)
+		} else {
+			(100.0, 0.0)
+		};
+
+		let indent = CONFIG.read().recover().theme.connectors.indent(depth);
+
+		writeln!(
+			f,
+			"{:>10} {:>7.1}% {:>9.1}%  {indent}{}",
+			time::format(record.duration),
+			own,
+			children,
+			record.message,
+		)?;
+
+		for child in &record.children {
+			Self::write_record(f, child, depth + 1)?;
+		}
+
+		Ok(())
+	}
+}
+
+impl Display for Profile<'_> {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		writeln!(f, "{:>10} {:>8} {:>10}  task", "total", "self", "children")?;
+
+		for record in &self.session.tasks {
+			Self::write_record(f, record, 0)?;
+		}
+
+		Ok(())
+	}
+}
+
+/// A task that ended with a warning.
+#[derive(Clone, Debug)]
+pub struct Warning {
+	/// The messages of the task and every task it was nested under,
+	/// from the outermost inward.
+	pub path: Vec<String>,
+
+	/// The message the task was ended with.
+	pub message: String,
+}
+
+// every warning given so far, and how many of them have been printed
+static WARNINGS: Mutex<(Vec<Warning>, usize)> = Mutex::new((Vec::new(), 0));
+
+/// Remembers that `task` ended with a warning, given the tasks still running.
+pub(crate) fn warned(tasks: &[Task], task: &Task, message: &str) {
+	let mut path = vec![task.message.clone()];
+	let mut parent = task.parent;
+
+	while let Some(ancestor) = parent.and_then(|id| tasks.iter().find(|ancestor| ancestor.id == id)) {
+		path.push(ancestor.message.clone());
+		parent = ancestor.parent;
+	}
+
+	path.reverse();
+
+	WARNINGS.lock().recover().0.push(Warning {
+		path,
+		message: message.to_owned(),
+	});
+}
+
+/// Prints every warning given since the last digest, if there are any.
+pub(crate) fn digest() {
+	// the lock is released before printing, which locks the running tasks
+	let new = {
+		let mut warnings = WARNINGS.lock().recover();
+		let (all, printed) = &mut *warnings;
+		let new = all[*printed..].to_vec();
+		*printed = all.len();
+		new
+	};
+
+	if new.is_empty() {
+		return;
+	}
+
+	let count = locale::count(new.len() as u64, "{count} warning:", "{count} warnings:");
+	crate::print_line(&format!("\x1b[33;1m▲\x1b[0m {count}"));
+
+	for warning in &new {
+		crate::print_line(&format!(
+			"     {}\x1b[2m:\x1b[0m {}",
+			warning.path.join(" \x1b[2m›\x1b[0m "),
+			warning.message,
+		));
+	}
+}
+
+/// Returns every warning given so far, in the order they were given.
+pub fn warnings() -> Vec<Warning> {
+	WARNINGS.lock().recover().0.clone()
+}
+
+pub(crate) static SESSION: Mutex<Session> = Mutex::new(Session { tasks: Vec::new() });
+
+/// Returns a snapshot of every task completed so far.
+///
+/// Tasks that are still running are not included.
+pub fn session() -> Session {
+	SESSION.lock().recover().clone()
+}
@@ -0,0 +1,65 @@
+use std::{
+	fs,
+	io::{self, Write},
+	os::unix::{
+		fs::FileTypeExt,
+		net::{UnixListener, UnixStream},
+	},
+	path::Path,
+	thread,
+};
+
+use crate::events;
+
+/// Publishes every event of the task tree on a Unix domain socket at
+/// `path`, as lines of JSON written by
+/// [`Event::to_json`](crate::Event::to_json), so that another process can
+/// attach to a long-running job and follow it live, as with
+/// `socat - UNIX-CONNECT:/tmp/job.sock`.
+///
+/// Each client is first sent a `start` event for every task that is
+/// running when it connects, along with its progress, and then every event
+/// from there on. Clients that read too slowly only hold up themselves. A
+/// stale socket left at `path` by an earlier run is replaced, but a socket
+/// that another process is still serving, or any other file, is an error.
+pub fn serve_socket(path: impl AsRef<Path>) -> io::Result<()> {
+	let path = path.as_ref();
+
+	// a socket is only stale if nothing is listening on it anymore, since
+	// one that is still answered belongs to a job that is still running
+	if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+		match UnixStream::connect(path) {
+			Ok(_) => {
+				return Err(io::Error::new(
+					io::ErrorKind::AddrInUse,
+					format!("{} is already being served", path.display()),
+				));
+			},
+			Err(error) if error.kind() == io::ErrorKind::ConnectionRefused => fs::remove_file(path)?,
+			Err(_) => {},
+		}
+	}
+
+	let listener = UnixListener::bind(path)?;
+
+	thread::spawn(move || {
+		for stream in listener.incoming().flatten() {
+			attach(stream);
+		}
+	});
+
+	Ok(())
+}
+
+/// Starts sending events to a client that has just connected.
+fn attach(mut stream: UnixStream) {
+	let events = events::subscribe();
+
+	thread::spawn(move || {
+		for event in events {
+			if writeln!(stream, "{}", event.to_json()).is_err() {
+				break;
+			}
+		}
+	});
+}
@@ -0,0 +1,624 @@
+use std::{borrow::Cow, iter};
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::{config::CONFIG, format, path, redact, sync::Recover, vars, Format};
+
+/// Returns how many terminal cells `text` takes up, counting wide
+/// characters like CJK and emoji as two cells and combining characters as
+/// none. The text should already be [`plain`](crate::format::plain).
+pub(crate) fn width(text: &str) -> usize {
+	text.width()
+}
+
+/// Cuts `text` short to fit within `room` cells, without splitting any
+/// character that is made of several code points, like a flag or an emoji
+/// with a skin tone. The text should already be [`plain`](crate::format::plain).
+pub(crate) fn truncate(text: &str, room: usize) -> String {
+	let mut used = 0;
+
+	text.graphemes(true)
+		.take_while(|grapheme| {
+			used += grapheme.width();
+			used <= room
+		})
+		.collect()
+}
+
+/// Breaks `text` into pieces that each fit within `room` cells, for rows
+/// that are wrapped by jeflog rather than by the terminal. Escape sequences
+/// take up no cells and stay within the piece they're found in, and no
+/// character made of several code points is split.
+pub(crate) fn wrap(text: &str, room: usize) -> Vec<String> {
+	let room = room.max(1);
+	let mut pieces = vec![String::new()];
+	let mut used = 0;
+
+	for piece in self::pieces(text) {
+		let run = match piece {
+			Piece::Escape(escape) => {
+				pieces.last_mut().unwrap().push_str(escape);
+				continue;
+			},
+			Piece::Text(run) => run,
+		};
+
+		for grapheme in run.graphemes(true) {
+			if used + grapheme.width() > room && used > 0 {
+				pieces.push(String::new());
+				used = 0;
+			}
+
+			used += grapheme.width();
+			pieces.last_mut().unwrap().push_str(grapheme);
+		}
+	}
+
+	pieces
+}
+
+/// A run of text without escape sequences, or one escape sequence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Piece<'a> {
+	Text(&'a str),
+	Escape(&'a str),
+}
+
+impl<'a> Piece<'a> {
+	/// Returns the piece as it was written.
+	pub fn as_str(self) -> &'a str {
+		match self {
+			Self::Text(piece) | Self::Escape(piece) => piece,
+		}
+	}
+
+	/// Returns the parameters of the piece if it is an SGR sequence, which
+	/// colors and styles the text after it, such as `1;31` for `\x1b[1;31m`.
+	pub fn sgr(self) -> Option<&'a str> {
+		match self {
+			Self::Escape(escape) => escape.strip_prefix("\x1b[")?.strip_suffix('m'),
+			Self::Text(_) => None,
+		}
+	}
+}
+
+/// Splits `text` into runs of text and the escape sequences between them,
+/// which is how every part of jeflog picks escape sequences out of text.
+/// A sequence that is cut off by the end of the text runs to the end.
+pub(crate) fn pieces(text: &str) -> impl Iterator<Item = Piece<'_>> {
+	let mut rest = text;
+
+	iter::from_fn(move || {
+		if rest.is_empty() {
+			return None;
+		}
+
+		let escape = rest.starts_with('\x1b');
+
+		let length = if escape {
+			escape_length(rest)
+		} else {
+			rest.find('\x1b').unwrap_or(rest.len())
+		};
+
+		let (piece, after) = rest.split_at(length);
+		rest = after;
+
+		Some(if escape { Piece::Escape(piece) } else { Piece::Text(piece) })
+	})
+}
+
+/// Returns the length in bytes of the escape sequence `text` starts with.
+fn escape_length(text: &str) -> usize {
+	let mut chars = text.char_indices().skip(1);
+
+	match chars.next() {
+		// control sequences end with a byte in @ through ~
+		Some((_, '[')) => chars
+			.find(|&(_, c)| ('@'..='~').contains(&c))
+			.map_or(text.len(), |(index, _)| index + 1),
+		// operating system commands end with BEL or ST (ESC \\)
+		Some((_, ']')) => {
+			let mut previous = ' ';
+
+			chars
+				.find(|&(_, c)| {
+					let end = c == '\x07' || (previous == '\x1b' && c == '\\');
+					previous = c;
+					end
+				})
+				.map_or(text.len(), |(index, _)| index + 1)
+		},
+		// every other escape is a single character
+		Some((index, c)) => index + c.len_utf8(),
+		None => text.len(),
+	}
+}
+
+/// Prepares a message given by the application for printing, filling
+/// in its variables, redacting it, shortening its paths, sanitizing it,
+/// and replacing its markup.
+pub(crate) fn clean(message: &str) -> String {
+	markup(&sanitize(&path::shorten_all(&redact::apply(&vars::substitute(message)))))
+}
+
+/// Wraps `text` in an OSC 8 hyperlink to `url`, which is clickable in
+/// terminals that support it and shows as plain `text` in ones that don't.
+///
+/// Formats that are not read by a terminal, like CI annotations, keep
+/// only the text.
+pub fn link(url: impl AsRef<str>, text: impl AsRef<str>) -> String {
+	// \x1b]8;;{url}\x1b\\ : begin a hyperlink to url
+	// \x1b]8;;\x1b\\      : end the hyperlink
+	format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url.as_ref(), text.as_ref())
+}
+
+/// Returns the SGR parameters for a markup tag, if it names a style.
+fn sgr(tag: &str) -> Option<&'static str> {
+	Some(match tag {
+		"bold" => "1",
+		"dim" => "2",
+		"italic" => "3",
+		"underline" => "4",
+		"black" => "30",
+		"red" => "31",
+		"green" => "32",
+		"yellow" => "33",
+		"blue" => "34",
+		"magenta" => "35",
+		"cyan" => "36",
+		"white" => "37",
+		"gray" => "90",
+		_ => return None,
+	})
+}
+
+/// Replaces style markup like `{red}error{/red}` or `{bold}...{/bold}`
+/// in a message with the escape sequences it stands for.
+///
+/// Braces that don't form a known tag are left untouched, so messages
+/// that happen to contain them are printed as they were written.
+pub(crate) fn markup(message: &str) -> String {
+	let mut out = String::with_capacity(message.len());
+	let mut open = Vec::new();
+	let mut rest = message;
+
+	while let Some(start) = rest.find('{') {
+		out.push_str(&rest[..start]);
+		rest = &rest[start..];
+
+		let Some(end) = rest.find('}') else {
+			break;
+		};
+
+		let tag = &rest[1..end];
+		let (closing, name) = match tag.strip_prefix('/') {
+			Some(name) => (true, name),
+			None => (false, tag),
+		};
+
+		match sgr(name) {
+			Some(code) if !closing => {
+				open.push(code);
+				out.push_str(&format!("\x1b[{code}m"));
+			},
+			Some(code) => {
+				// styles can't be turned off one at a time portably,
+				// so reset everything and reapply what is still open
+				if let Some(index) = open.iter().rposition(|open| *open == code) {
+					open.remove(index);
+				}
+
+				out.push_str("\x1b[0m");
+
+				for code in &open {
+					out.push_str(&format!("\x1b[{code}m"));
+				}
+			},
+			None => {
+				// not a tag, so keep the brace and look for one after it
+				out.push('{');
+				rest = &rest[1..];
+				continue;
+			},
+		}
+
+		rest = &rest[end + 1..];
+	}
+
+	out.push_str(rest);
+
+	// styles left open would otherwise leak into the next line
+	if !open.is_empty() {
+		out.push_str("\x1b[0m");
+	}
+
+	out
+}
+
+/// Returns whether the icons of tasks are shown, which they aren't with
+/// ASCII connectors, in terminals that may not have the glyphs, or in the
+/// formats meant to be read by screen readers and scripts.
+pub(crate) fn icons() -> bool {
+	CONFIG.read().recover().theme.connectors != Connectors::Ascii
+		&& !matches!(format::current(), Format::Accessible | Format::Porcelain)
+}
+
+/// Formats a target as a tag to put before a message, such as
+/// `[network]`. Each target is always given the same color, so that
+/// lines from the same target are easy to pick out.
+pub(crate) fn tag(target: &str) -> String {
+	// red, yellow, and green are left out, since they already
+	// mean failure, warning, and success
+	const PALETTE: [u8; 6] = [36, 35, 34, 96, 95, 94];
+
+	// a simple, stable hash (FNV-1a), so colors don't change between runs
+	let hash = target.bytes().fold(0x811c9dc5u32, |hash, byte| {
+		(hash ^ u32::from(byte)).wrapping_mul(0x01000193)
+	});
+
+	let color = PALETTE[hash as usize % PALETTE.len()];
+	format!("\x1b[{color}m[{target}]\x1b[0m")
+}
+
+/// A color of the terminal's palette, which follows its color scheme.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Color {
+	Black,
+	Red,
+	Green,
+	Yellow,
+	Blue,
+	Magenta,
+	Cyan,
+	White,
+	Gray,
+}
+
+impl Color {
+	/// Finds a color by its name in lowercase, as in `magenta`.
+	pub(crate) fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"black" => Some(Self::Black),
+			"red" => Some(Self::Red),
+			"green" => Some(Self::Green),
+			"yellow" => Some(Self::Yellow),
+			"blue" => Some(Self::Blue),
+			"magenta" => Some(Self::Magenta),
+			"cyan" => Some(Self::Cyan),
+			"white" => Some(Self::White),
+			"gray" | "grey" => Some(Self::Gray),
+			_ => None,
+		}
+	}
+
+	/// The SGR parameter that sets this as the foreground color.
+	pub(crate) fn code(self) -> u8 {
+		match self {
+			Self::Black => 30,
+			Self::Red => 31,
+			Self::Green => 32,
+			Self::Yellow => 33,
+			Self::Blue => 34,
+			Self::Magenta => 35,
+			Self::Cyan => 36,
+			Self::White => 37,
+			Self::Gray => 90,
+		}
+	}
+}
+
+/// The colors that spinners and connectors cycle through by depth when
+/// [`set_depth_colors`](crate::set_depth_colors) is enabled, unless another
+/// palette is given to [`set_depth_palette`](crate::set_depth_palette). Red,
+/// yellow, and green are left out, since they already mean failure,
+/// warning, and success.
+pub const DEPTH_PALETTE: [Color; 4] = [Color::Cyan, Color::Magenta, Color::Blue, Color::Gray];
+
+/// The glyphs that connect each task to its subtasks in the tree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Connectors {
+	/// Heavy lines, as in `┣━` and `┗━`. This is the default.
+	Heavy,
+	/// Light lines, as in `├─` and `└─`.
+	Light,
+	/// Light lines with rounded corners, as in `├─` and `╰─`.
+	Rounded,
+	/// Double lines, as in `╠═` and `╚═`.
+	Double,
+	/// Dashed lines, as in `├┄` and `└┄`.
+	Dotted,
+	/// Plain ASCII, as in `|-` and `` `- ``, for terminals and fonts
+	/// without box-drawing characters.
+	Ascii,
+}
+
+impl Connectors {
+	/// The line running down from a task to its subtasks.
+	pub(crate) fn vertical(self) -> &'static str {
+		match self {
+			Self::Heavy => "┃",
+			Self::Light | Self::Rounded => "│",
+			Self::Double => "║",
+			Self::Dotted => "┆",
+			Self::Ascii => "|",
+		}
+	}
+
+	/// Where a subtask's corner meets the line to a later subtask.
+	pub(crate) fn junction(self) -> &'static str {
+		match self {
+			Self::Heavy => "┣",
+			Self::Light | Self::Rounded | Self::Dotted => "├",
+			Self::Double => "╠",
+			Self::Ascii => "|",
+		}
+	}
+
+	/// The corner leading to a subtask, which is two cells wide.
+	pub(crate) fn corner(self) -> &'static str {
+		match self {
+			Self::Heavy => "┗━",
+			Self::Light => "└─",
+			Self::Rounded => "╰─",
+			Self::Double => "╚═",
+			Self::Dotted => "└┄",
+			Self::Ascii => "`-",
+		}
+	}
+
+	/// Indents a line as a subtask `depth` levels deep, ending in its corner.
+	pub(crate) fn indent(self, depth: usize) -> String {
+		match depth {
+			0 => String::new(),
+			depth => format!("{}{} ", " ".repeat((depth - 1) * 5 + 2), self.corner()),
+		}
+	}
+}
+
+/// Sets the glyphs that connect each task to its subtasks.
+pub fn set_connectors(connectors: Connectors) {
+	CONFIG.write().recover().theme.connectors = connectors;
+}
+
+/// How the task tree looks, which is its connectors and whether, and with
+/// which colors, its spinners and connectors are colored by depth, so that
+/// a whole look can be chosen at once with [`set_theme`] or
+/// [`ConfigBuilder::theme`](crate::ConfigBuilder::theme).
+///
+/// A theme starts from one of the presets, and any part of it can be
+/// changed, as in `Theme::ROUNDED.with_depth_colors(true)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Theme {
+	pub(crate) connectors: Connectors,
+	pub(crate) depth_colors: bool,
+	pub(crate) depth_palette: Cow<'static, [Color]>,
+}
+
+impl Theme {
+	/// Heavy connectors without colors by depth, which is the default.
+	pub const DEFAULT: Self = Self::plain(Connectors::Heavy);
+
+	/// Light connectors without colors by depth.
+	pub const LIGHT: Self = Self::plain(Connectors::Light);
+
+	/// Light, rounded connectors without colors by depth.
+	pub const ROUNDED: Self = Self::plain(Connectors::Rounded);
+
+	/// ASCII connectors without colors by depth, for terminals and fonts
+	/// without box-drawing characters.
+	pub const ASCII: Self = Self::plain(Connectors::Ascii);
+
+	/// Heavy connectors, with spinners and connectors colored by depth from
+	/// [`DEPTH_PALETTE`].
+	pub const COLORFUL: Self = Self {
+		connectors: Connectors::Heavy,
+		depth_colors: true,
+		depth_palette: Cow::Borrowed(&DEPTH_PALETTE),
+	};
+
+	const fn plain(connectors: Connectors) -> Self {
+		Self {
+			connectors,
+			depth_colors: false,
+			depth_palette: Cow::Borrowed(&DEPTH_PALETTE),
+		}
+	}
+
+	/// Returns the theme with other connectors.
+	pub fn with_connectors(mut self, connectors: Connectors) -> Self {
+		self.connectors = connectors;
+		self
+	}
+
+	/// Returns the theme with spinners and connectors colored by depth, or not.
+	pub fn with_depth_colors(mut self, enabled: bool) -> Self {
+		self.depth_colors = enabled;
+		self
+	}
+
+	/// Returns the theme with another palette of colors cycled through by
+	/// depth, starting from top-level tasks.
+	pub fn with_depth_palette(mut self, palette: &[Color]) -> Self {
+		self.depth_palette = Cow::Owned(palette.to_vec());
+		self
+	}
+
+	/// The glyphs that connect tasks to their subtasks.
+	pub fn connectors(&self) -> Connectors {
+		self.connectors
+	}
+
+	/// Whether spinners and connectors are colored by depth.
+	pub fn depth_colors(&self) -> bool {
+		self.depth_colors
+	}
+
+	/// The colors cycled through by depth, starting from top-level tasks.
+	pub fn depth_palette(&self) -> &[Color] {
+		&self.depth_palette
+	}
+
+	/// Finds a preset by its name in lowercase, as in `rounded`.
+	pub(crate) fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"default" => Some(Self::DEFAULT),
+			"light" => Some(Self::LIGHT),
+			"rounded" => Some(Self::ROUNDED),
+			"ascii" => Some(Self::ASCII),
+			"colorful" => Some(Self::COLORFUL),
+			_ => None,
+		}
+	}
+}
+
+impl Default for Theme {
+	fn default() -> Self {
+		Self::DEFAULT
+	}
+}
+
+/// Sets how the task tree looks, replacing its connectors, colors by
+/// depth, and palette at once.
+pub fn set_theme(theme: Theme) {
+	CONFIG.write().recover().theme = theme;
+}
+
+/// How control characters in messages are handled before printing.
+///
+/// Messages often contain text from untrusted sources, like file names
+/// or the output of other programs. Carriage returns, newlines, and
+/// cursor movement in them would be sent to the terminal as-is, both
+/// corrupting the task tree and desynchronizing jeflog's idea of where
+/// each task is drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sanitize {
+	/// Messages are printed exactly as they are given.
+	Off,
+	/// Color and style sequences (SGR) and hyperlinks (OSC 8) are kept,
+	/// since they can't move the cursor, and every other control character
+	/// is replaced with a visible escape like `\r` or `\x1b`. This is the
+	/// default.
+	Styles,
+	/// Every control character is replaced with a visible escape.
+	All,
+}
+
+/// Sets how control characters in messages are handled before printing.
+pub fn set_sanitize(sanitize: Sanitize) {
+	CONFIG.write().recover().sanitize = sanitize;
+}
+
+/// Returns the length of the SGR or OSC 8 sequence at the start of
+/// `text`, which begins with an escape, if it is one.
+fn style_sequence(text: &str) -> Option<usize> {
+	if let Some(rest) = text.strip_prefix("\x1b[") {
+		let end = rest.find(|c: char| !c.is_ascii_digit() && c != ';')?;
+		return rest[end..].starts_with('m').then_some(end + 3);
+	}
+
+	let rest = text.strip_prefix("\x1b]8;")?;
+
+	// the link itself can't contain control characters of its own
+	let end = rest.find(|c: char| c.is_control())?;
+
+	match &rest[end..] {
+		tail if tail.starts_with('\x07') => Some(end + 5),
+		tail if tail.starts_with("\x1b\\") => Some(end + 6),
+		_ => None,
+	}
+}
+
+/// Replaces control characters in a message as configured by [`set_sanitize`].
+pub(crate) fn sanitize(message: &str) -> String {
+	let mode = CONFIG.read().recover().sanitize;
+
+	if mode == Sanitize::Off || !message.contains(|c: char| c.is_control()) {
+		return message.to_owned();
+	}
+
+	let mut clean = String::with_capacity(message.len());
+	let mut rest = message;
+
+	while let Some(c) = rest.chars().next() {
+		if mode == Sanitize::Styles && c == '\x1b' {
+			if let Some(length) = style_sequence(rest) {
+				clean.push_str(&rest[..length]);
+				rest = &rest[length..];
+				continue;
+			}
+		}
+
+		match c {
+			// tabs are expanded, as their width depends on the column
+			'\t' => clean.push_str("    "),
+			'\n' => clean.push_str("\\n"),
+			'\r' => clean.push_str("\\r"),
+			c if c.is_control() => clean.push_str(&format!("\\x{:02x}", c as u32)),
+			c => clean.push(c),
+		}
+
+		rest = &rest[c.len_utf8()..];
+	}
+
+	clean
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn markup_is_replaced_with_escapes() {
+		assert_eq!(markup("{red}error{/red}: disk"), "\x1b[31merror\x1b[0m: disk");
+		assert_eq!(markup("{bold}{red}a{/bold}b{/red}"), "\x1b[1m\x1b[31ma\x1b[0m\x1b[31mb\x1b[0m");
+		assert_eq!(markup("{green}left open"), "\x1b[32mleft open\x1b[0m");
+	}
+
+	#[test]
+	fn braces_that_are_not_tags_are_kept() {
+		assert_eq!(markup("{} {unknown} {/red"), "{} {unknown} {/red");
+		assert_eq!(markup("{{red}x{/red}}"), "{\x1b[31mx\x1b[0m}");
+	}
+
+	#[test]
+	fn control_characters_are_made_visible() {
+		assert_eq!(sanitize("a\tb\nc\rd\x07"), "a    b\\nc\\rd\\x07");
+		assert_eq!(sanitize("\x1b[2Jcleared"), "\\x1b[2Jcleared");
+	}
+
+	#[test]
+	fn styles_and_links_survive_sanitizing() {
+		let styled = "\x1b[1;31mred\x1b[0m";
+		assert_eq!(sanitize(styled), styled);
+
+		let link = link("https://example.com", "docs");
+		assert_eq!(sanitize(&link), link);
+	}
+
+	#[test]
+	fn text_is_split_around_escapes() {
+		let pieces = pieces("a\x1b[1mb\x1b]8;;x\x07c\x1b").collect::<Vec<_>>();
+
+		assert_eq!(pieces, [
+			Piece::Text("a"),
+			Piece::Escape("\x1b[1m"),
+			Piece::Text("b"),
+			Piece::Escape("\x1b]8;;x\x07"),
+			Piece::Text("c"),
+			Piece::Escape("\x1b"),
+		]);
+
+		assert_eq!(pieces[1].sgr(), Some("1"));
+		assert_eq!(pieces[3].sgr(), None);
+		assert_eq!(Piece::Escape("\x1b[2K").sgr(), None);
+	}
+
+	#[test]
+	fn wrapping_keeps_escapes_and_wide_characters_whole() {
+		assert_eq!(wrap("\x1b[1mabcd\x1b[0m", 2), ["\x1b[1mab", "cd\x1b[0m"]);
+		assert_eq!(wrap("日本語", 4), ["日本", "語"]);
+		assert_eq!(wrap("", 4), [""]);
+	}
+}
@@ -0,0 +1,18 @@
+use std::sync::{LockResult, PoisonError};
+
+/// Recovers the guard of a lock that was poisoned by a panic.
+///
+/// A panic on another thread while it held one of jeflog's locks, such as
+/// one raised by a redaction or by formatting a message, says nothing about
+/// the logging of every other thread. Rather than passing the panic on to
+/// them, they carry on with whatever state the lock was left in, which is
+/// at worst a task that is drawn oddly or never ends.
+pub(crate) trait Recover<T> {
+	fn recover(self) -> T;
+}
+
+impl<T> Recover<T> for LockResult<T> {
+	fn recover(self) -> T {
+		self.unwrap_or_else(PoisonError::into_inner)
+	}
+}
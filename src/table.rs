@@ -0,0 +1,73 @@
+use std::fmt::{Display, Write};
+
+use crate::{format::plain, redact, style};
+
+/// Prints a table beneath the running tasks, with a column for each
+/// header and the cells of each row lined up beneath them, as in
+/// `jeflog::table(&["crate", "size"], &[["jeflog", "42 KiB"]])`.
+///
+/// Cells may be styled with markup, like messages, and rows with more
+/// cells than there are headers have the extra cells left out.
+pub fn table<H, R, C>(headers: &[H], rows: &[R])
+where
+	H: Display,
+	R: AsRef<[C]>,
+	C: Display,
+{
+	let clean = |cell: String| style::markup(&style::sanitize(&redact::apply(&cell)));
+
+	let headers = headers.iter()
+		.map(|header| clean(header.to_string()))
+		.collect::<Vec<_>>();
+
+	let rows = rows.iter()
+		.map(|row| {
+			row.as_ref().iter()
+				.take(headers.len())
+				.map(|cell| clean(cell.to_string()))
+				.collect::<Vec<_>>()
+		})
+		.collect::<Vec<_>>();
+
+	// escape sequences take up no room, so only the plain text is measured
+	let width = |cell: &str| style::width(&plain(cell));
+	let mut widths = headers.iter().map(|header| width(header)).collect::<Vec<_>>();
+
+	for row in &rows {
+		for (column, cell) in row.iter().enumerate() {
+			widths[column] = widths[column].max(width(cell));
+		}
+	}
+
+	let line = |cells: &[String], style: &str| {
+		let mut line = String::new();
+
+		for (column, cell) in cells.iter().enumerate() {
+			if column > 0 {
+				line.push_str("  ");
+			}
+
+			match style {
+				"" => line.push_str(cell),
+				style => _ = write!(line, "{style}{cell}\x1b[0m"),
+			}
+
+			// the last column isn't padded, so no line ends in spaces
+			if column + 1 < cells.len() {
+				line.push_str(&" ".repeat(widths[column] - width(cell)));
+			}
+		}
+
+		line
+	};
+
+	let rule = widths.iter()
+		.map(|&width| "─".repeat(width))
+		.collect::<Vec<_>>()
+		.join("  ");
+
+	let mut lines = vec![line(&headers, "\x1b[1m"), format!("\x1b[2m{rule}\x1b[0m")];
+	lines.extend(rows.iter().map(|row| line(row, "")));
+
+	crate::print_block(&lines);
+}
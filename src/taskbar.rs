@@ -0,0 +1,42 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::{config::CONFIG, output, sync::Recover, Task};
+
+// whether a task has failed since the tree was last empty
+static FAILED: AtomicBool = AtomicBool::new(false);
+
+/// Remembers that a task has failed, so the taskbar shows an error
+/// state until every task has ended.
+pub(crate) fn mark_failed() {
+	FAILED.store(true, Ordering::Relaxed);
+}
+
+/// Reports the overall progress of the task tree to terminals that
+/// display it in the taskbar, such as Windows Terminal and ConEmu.
+pub(crate) fn update(tasks: &[&Task]) {
+	if !CONFIG.read().recover().taskbar {
+		return;
+	}
+
+	// \x1b]9;4;{state};{percent}\x07 : set taskbar progress, where state is
+	//   0 : no progress
+	//   1 : normal progress
+	//   2 : error
+	//   3 : indeterminate
+	if tasks.is_empty() {
+		FAILED.store(false, Ordering::Relaxed);
+		output::emit("\x1b]9;4;0;0\x07");
+		return;
+	}
+
+	// the outermost task that reports progress stands in for the whole tree
+	let percent = tasks.iter().find_map(|task| task.percent());
+
+	let sequence = match (FAILED.load(Ordering::Relaxed), percent) {
+		(true, percent) => format!("\x1b]9;4;2;{}\x07", percent.unwrap_or(100)),
+		(false, Some(percent)) => format!("\x1b]9;4;1;{percent}\x07"),
+		(false, None) => String::from("\x1b]9;4;3;0\x07"),
+	};
+
+	output::emit(&sequence);
+}
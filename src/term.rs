@@ -0,0 +1,369 @@
+use std::fmt::Write;
+
+use crate::output;
+
+/// A single instruction to the terminal.
+enum Op {
+	/// Print text, which may contain SGR sequences for color and style.
+	Print(String),
+	/// Save the cursor's current position.
+	Save,
+	/// Restore the cursor to the last saved position.
+	Restore,
+	/// Move the cursor up by some number of rows.
+	Up(u16),
+	/// Move the cursor down by some number of rows.
+	Down(u16),
+	/// Move the cursor left by some number of columns.
+	Left(u16),
+	/// Move the cursor to a column, counting from one.
+	Column(u16),
+	/// Move the cursor to the start of a row of the screen, counting from one.
+	Row(u16),
+	/// Scroll only the rows from the top of the screen through this one,
+	/// counting from one, or the whole screen for zero. This moves the
+	/// cursor to the top of the screen.
+	Region(u16),
+	/// Remove this many rows, starting with the cursor's, moving the rows
+	/// beneath them up in their place.
+	Delete(u16),
+	/// Clear from the cursor to the end of the line.
+	ClearLine,
+	/// Move the cursor to the start of the next line.
+	Newline,
+}
+
+/// A batch of terminal instructions, written all at once.
+///
+/// Renderers describe what to draw with a frame rather than writing
+/// escape sequences directly, so that the sequences can be produced by
+/// a different backend (like crossterm) where one is enabled.
+#[derive(Default)]
+pub(crate) struct Frame {
+	ops: Vec<Op>,
+}
+
+impl Frame {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn print(&mut self, text: impl Into<String>) {
+		self.ops.push(Op::Print(text.into()));
+	}
+
+	pub fn save(&mut self) {
+		self.ops.push(Op::Save);
+	}
+
+	pub fn restore(&mut self) {
+		self.ops.push(Op::Restore);
+	}
+
+	// terminals treat a movement of zero as a movement of one,
+	// so those are dropped rather than sent
+
+	pub fn up(&mut self, rows: usize) {
+		if rows > 0 {
+			self.ops.push(Op::Up(rows as u16));
+		}
+	}
+
+	pub fn down(&mut self, rows: usize) {
+		if rows > 0 {
+			self.ops.push(Op::Down(rows as u16));
+		}
+	}
+
+	pub fn left(&mut self, columns: usize) {
+		if columns > 0 {
+			self.ops.push(Op::Left(columns as u16));
+		}
+	}
+
+	pub fn column(&mut self, column: usize) {
+		self.ops.push(Op::Column(column.max(1) as u16));
+	}
+
+	pub fn row(&mut self, row: usize) {
+		self.ops.push(Op::Row(row.max(1) as u16));
+	}
+
+	pub fn region(&mut self, rows: usize) {
+		self.ops.push(Op::Region(rows as u16));
+	}
+
+	pub fn delete(&mut self, rows: usize) {
+		self.ops.push(Op::Delete(rows as u16));
+	}
+
+	pub fn clear_line(&mut self) {
+		self.ops.push(Op::ClearLine);
+	}
+
+	pub fn newline(&mut self) {
+		self.ops.push(Op::Newline);
+	}
+
+	/// Renders the frame as ANSI escape sequences.
+	pub fn to_ansi(&self) -> String {
+		let mut ansi = String::new();
+
+		// writes to a String cannot fail, so their results are ignored
+		for op in &self.ops {
+			match op {
+				Op::Print(text) => ansi.push_str(text),
+				Op::Save => ansi.push_str("\x1b[s"),
+				Op::Restore => ansi.push_str("\x1b[u"),
+				Op::Up(rows) => _ = write!(ansi, "\x1b[{rows}A"),
+				Op::Down(rows) => _ = write!(ansi, "\x1b[{rows}B"),
+				Op::Left(columns) => _ = write!(ansi, "\x1b[{columns}D"),
+				Op::Column(column) => _ = write!(ansi, "\x1b[{column}G"),
+				Op::Row(row) => _ = write!(ansi, "\x1b[{row};1H"),
+				Op::Region(0) => ansi.push_str("\x1b[r"),
+				Op::Region(rows) => _ = write!(ansi, "\x1b[1;{rows}r"),
+				Op::Delete(rows) => _ = write!(ansi, "\x1b[{rows}M"),
+				Op::ClearLine => ansi.push_str("\x1b[K"),
+				Op::Newline => ansi.push('\n'),
+			}
+		}
+
+		ansi
+	}
+
+	/// Writes the frame to the terminal.
+	#[cfg(not(feature = "crossterm"))]
+	pub fn emit(self) {
+		output::emit(&self.to_ansi());
+	}
+
+	/// Writes the frame to the terminal through crossterm, which also
+	/// supports legacy Windows consoles that don't understand ANSI.
+	#[cfg(feature = "crossterm")]
+	pub fn emit(self) {
+		use std::io::Write;
+
+		use crossterm::{cursor, queue, style::Print, terminal};
+
+		if self.ops.is_empty() || output::muted() {
+			return;
+		}
+
+		let mut stdout = output::stream();
+
+		// output is best effort, just as it is without crossterm
+		for op in &self.ops {
+			_ = match op {
+				Op::Print(text) => crossterm_print(&mut stdout, &crate::format::uncolored(text)),
+				Op::Save => queue!(stdout, cursor::SavePosition),
+				Op::Restore => queue!(stdout, cursor::RestorePosition),
+				Op::Up(rows) => queue!(stdout, cursor::MoveUp(*rows)),
+				Op::Down(rows) => queue!(stdout, cursor::MoveDown(*rows)),
+				Op::Left(columns) => queue!(stdout, cursor::MoveLeft(*columns)),
+				Op::Column(column) => queue!(stdout, cursor::MoveToColumn(column - 1)),
+				Op::Row(row) => queue!(stdout, cursor::MoveTo(0, row - 1)),
+				// crossterm has no commands for these, and few legacy consoles support them
+				Op::Region(0) => queue!(stdout, Print("\x1b[r")),
+				Op::Region(rows) => queue!(stdout, Print(format!("\x1b[1;{rows}r"))),
+				Op::Delete(rows) => queue!(stdout, Print(format!("\x1b[{rows}M"))),
+				Op::ClearLine => queue!(stdout, terminal::Clear(terminal::ClearType::UntilNewLine)),
+				Op::Newline => queue!(stdout, Print('\n')),
+			};
+		}
+
+		_ = stdout.flush();
+		drop(stdout);
+
+		output::written(&self.to_ansi());
+	}
+}
+
+/// Prints text through crossterm, translating the SGR sequences that
+/// jeflog uses for color and style into crossterm commands.
+#[cfg(feature = "crossterm")]
+fn crossterm_print(out: &mut impl std::io::Write, text: &str) -> std::io::Result<()> {
+	use crossterm::{
+		queue,
+		style::{Attribute, Color, Print, SetAttribute, SetBackgroundColor, SetForegroundColor},
+	};
+
+	use crate::style;
+
+	const NORMAL: [Color; 8] = [
+		Color::Black,
+		Color::DarkRed,
+		Color::DarkGreen,
+		Color::DarkYellow,
+		Color::DarkBlue,
+		Color::DarkMagenta,
+		Color::DarkCyan,
+		Color::Grey,
+	];
+
+	const BRIGHT: [Color; 8] = [
+		Color::DarkGrey,
+		Color::Red,
+		Color::Green,
+		Color::Yellow,
+		Color::Blue,
+		Color::Magenta,
+		Color::Cyan,
+		Color::White,
+	];
+
+	for piece in style::pieces(text) {
+		// anything other than SGR is passed through untouched
+		let Some(parameters) = piece.sgr() else {
+			queue!(out, Print(piece.as_str()))?;
+			continue;
+		};
+
+		let mut parameters = parameters.split(';').map(|parameter| parameter.parse::<usize>().unwrap_or(0));
+
+		while let Some(parameter) = parameters.next() {
+			match parameter {
+				0 => queue!(out, SetAttribute(Attribute::Reset))?,
+				1 => queue!(out, SetAttribute(Attribute::Bold))?,
+				2 => queue!(out, SetAttribute(Attribute::Dim))?,
+				3 => queue!(out, SetAttribute(Attribute::Italic))?,
+				4 => queue!(out, SetAttribute(Attribute::Underlined))?,
+				code @ 30..=37 => queue!(out, SetForegroundColor(NORMAL[code - 30]))?,
+				// the color that follows is read whole, so that none of its
+				// parts is taken for a style of its own
+				38 => if let Some(color) = extended(&mut parameters) {
+					queue!(out, SetForegroundColor(color))?;
+				},
+				39 => queue!(out, SetForegroundColor(Color::Reset))?,
+				48 => if let Some(color) = extended(&mut parameters) {
+					queue!(out, SetBackgroundColor(color))?;
+				},
+				49 => queue!(out, SetBackgroundColor(Color::Reset))?,
+				code @ 90..=97 => queue!(out, SetForegroundColor(BRIGHT[code - 90]))?,
+				_ => {},
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Reads the color given after `38` or `48` in an SGR sequence, which is
+/// either `5;n` for one of the 256 indexed colors or `2;r;g;b`, taking
+/// only as many of `parameters` as the color is written with.
+#[cfg(feature = "crossterm")]
+fn extended(parameters: &mut impl Iterator<Item = usize>) -> Option<crossterm::style::Color> {
+	use crossterm::style::Color;
+
+	let mode = parameters.next()?;
+	let mut component = || parameters.next().and_then(|value| u8::try_from(value).ok());
+
+	match mode {
+		5 => component().map(Color::AnsiValue),
+		2 => {
+			// every component is taken, even after one that is out of range
+			let [r, g, b] = [component(), component(), component()];
+			Some(Color::Rgb { r: r?, g: g?, b: b? })
+		},
+		_ => None,
+	}
+}
+
+/// Returns the size of the terminal as `(columns, rows)`, if known.
+#[cfg(feature = "crossterm")]
+pub(crate) fn size() -> Option<(u16, u16)> {
+	crossterm::terminal::size().ok()
+}
+
+/// Returns the size of the terminal as `(columns, rows)`, if known.
+#[cfg(not(feature = "crossterm"))]
+pub(crate) fn size() -> Option<(u16, u16)> {
+	#[cfg(any(target_os = "linux", target_os = "macos", target_os = "freebsd"))]
+	{
+		use std::os::raw::{c_int, c_ulong};
+
+		#[repr(C)]
+		struct Winsize {
+			rows: u16,
+			columns: u16,
+			x_pixels: u16,
+			y_pixels: u16,
+		}
+
+		extern "C" {
+			fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+		}
+
+		#[cfg(target_os = "linux")]
+		const TIOCGWINSZ: c_ulong = 0x5413;
+		#[cfg(any(target_os = "macos", target_os = "freebsd"))]
+		const TIOCGWINSZ: c_ulong = 0x40087468;
+
+		let mut size = Winsize { rows: 0, columns: 0, x_pixels: 0, y_pixels: 0 };
+
+		// the stream that jeflog draws on is asked, since the other may be
+		// redirected
+		let fd = if output::on_stderr() { 2 } else { 1 };
+
+		// SAFETY: `size` is a valid, writable winsize for the duration of the call
+		if unsafe { ioctl(fd, TIOCGWINSZ, &mut size) } == 0 && size.rows > 0 && size.columns > 0 {
+			return Some((size.columns, size.rows));
+		}
+	}
+
+	// shells export these, though they aren't always passed on to programs
+	let columns = std::env::var("COLUMNS").ok()?.parse().ok()?;
+	let rows = std::env::var("LINES").ok()?.parse().ok()?;
+
+	Some((columns, rows))
+}
+
+/// Asks the terminal where its cursor is, as `(column, row)` counting
+/// from zero. This waits on a response read from standard input.
+#[cfg(feature = "crossterm")]
+pub(crate) fn position() -> Option<(u16, u16)> {
+	crossterm::cursor::position().ok()
+}
+
+/// Returns the number of columns the terminal can show at once, or
+/// `usize::MAX` if the size of the terminal is unknown.
+pub(crate) fn width() -> usize {
+	size().map_or(usize::MAX, |(columns, _)| columns.max(1) as usize)
+}
+
+/// Returns the number of rows the terminal can show at once, or
+/// `usize::MAX` if the size of the terminal is unknown.
+pub(crate) fn height() -> usize {
+	size().map_or(usize::MAX, |(_, rows)| rows.max(1) as usize)
+}
+
+#[cfg(all(test, feature = "crossterm"))]
+mod tests {
+	use crossterm::{
+		queue,
+		style::{Attribute, Color, Print, SetAttribute, SetForegroundColor},
+	};
+
+	use super::*;
+
+	#[test]
+	fn extended_colors_are_read_whole() {
+		let printed = |text| {
+			let mut out = Vec::new();
+			crossterm_print(&mut out, text).unwrap();
+			out
+		};
+
+		let mut expected = Vec::new();
+		queue!(
+			expected,
+			SetForegroundColor(Color::AnsiValue(1)),
+			Print("indexed"),
+			SetForegroundColor(Color::Rgb { r: 0, g: 128, b: 255 }),
+			SetAttribute(Attribute::Bold),
+			Print("true color"),
+			SetAttribute(Attribute::Reset),
+		).unwrap();
+
+		assert_eq!(printed("\x1b[38;5;1mindexed\x1b[38;2;0;128;255;1mtrue color\x1b[0m"), expected);
+	}
+}
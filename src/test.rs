@@ -0,0 +1,162 @@
+//! Assertions on the outcome of tasks, for testing programs that use jeflog.
+//!
+//! Rather than matching against rendered output, which changes with the
+//! terminal and is full of escape sequences, these inspect the [`session`]
+//! of completed tasks. A task matches when its start or end message, with
+//! styles removed, equals the given text, or contains it when written as
+//! `assert_failed!(containing "timeout")`.
+//!
+//! Tasks are only recorded once jeflog is initialized, so a test starts
+//! with `let _guard = jeflog::init();`, just as the program it tests would.
+//! Since the session is shared by the whole process, tests that run in
+//! parallel can see each other's tasks. [`clear`] empties it between tests.
+
+use crate::{format::plain, session, sync::Recover, Record, Status};
+
+/// Removes every completed task from the session.
+pub fn clear() {
+	session::SESSION.lock().recover().tasks.clear();
+}
+
+/// Asserts that a task matching the message has passed.
+#[macro_export]
+macro_rules! assert_passed {
+	(containing $($tokens:tt)*) => {
+		$crate::test::__assert_task__($crate::Status::Pass, true, &format!($($tokens)*));
+	};
+	($($tokens:tt)*) => {
+		$crate::test::__assert_task__($crate::Status::Pass, false, &format!($($tokens)*));
+	};
+}
+
+/// Asserts that a task matching the message has ended with a warning.
+#[macro_export]
+macro_rules! assert_warned {
+	(containing $($tokens:tt)*) => {
+		$crate::test::__assert_task__($crate::Status::Warn, true, &format!($($tokens)*));
+	};
+	($($tokens:tt)*) => {
+		$crate::test::__assert_task__($crate::Status::Warn, false, &format!($($tokens)*));
+	};
+}
+
+/// Asserts that a task matching the message has failed.
+#[macro_export]
+macro_rules! assert_failed {
+	(containing $($tokens:tt)*) => {
+		$crate::test::__assert_task__($crate::Status::Fail, true, &format!($($tokens)*));
+	};
+	($($tokens:tt)*) => {
+		$crate::test::__assert_task__($crate::Status::Fail, false, &format!($($tokens)*));
+	};
+}
+
+/// Asserts that a task matching the message has been cancelled.
+#[macro_export]
+macro_rules! assert_cancelled {
+	(containing $($tokens:tt)*) => {
+		$crate::test::__assert_task__($crate::Status::Cancel, true, &format!($($tokens)*));
+	};
+	($($tokens:tt)*) => {
+		$crate::test::__assert_task__($crate::Status::Cancel, false, &format!($($tokens)*));
+	};
+}
+
+pub use crate::{assert_cancelled, assert_failed, assert_passed, assert_warned};
+
+fn matches(record: &Record, containing: bool, pattern: &str) -> bool {
+	[&record.message, &record.end_message]
+		.into_iter()
+		.map(|message| plain(message))
+		.any(|message| if containing { message.contains(pattern) } else { message == pattern })
+}
+
+#[doc(hidden)]
+pub fn __assert_task__(status: Status, containing: bool, pattern: &str) {
+	let session = session();
+
+	let found = session.find(|record| matches(record, containing, pattern))
+		.into_iter()
+		.map(|found| found.record)
+		.collect::<Vec<_>>();
+
+	if found.iter().any(|record| record.status == status) {
+		return;
+	}
+
+	let description = if containing {
+		format!("a task containing {pattern:?}")
+	} else {
+		format!("a task {pattern:?}")
+	};
+
+	if found.is_empty() {
+		panic!("expected {description} to end with {status:?}, but no such task was completed");
+	}
+
+	let statuses = found.iter()
+		.map(|record| format!("{:?}", record.status))
+		.collect::<Vec<_>>()
+		.join(", ");
+
+	panic!("expected {description} to end with {status:?}, but it ended with {statuses}");
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{cancel, enable, fail, pass, task, warn};
+
+	#[test]
+	fn tasks_are_matched_by_either_message() {
+		// each test names its tasks apart, since others may run alongside it
+		enable();
+		task!("test: compiling {}", "jeflog");
+		pass!("test: compiled in {}s", 2);
+		task!("test: fetching");
+		warn!("test: fetched from a mirror");
+		task!("test: uploading");
+		cancel!("test: upload interrupted");
+
+		assert_passed!("test: compiling jeflog");
+		assert_passed!("test: compiled in {}s", 2);
+		assert_warned!("test: fetching");
+		assert_cancelled!(containing "interrupted");
+	}
+
+	#[test]
+	fn tasks_are_matched_by_part_of_a_message() {
+		enable();
+		task!("test: resolving");
+		fail!("test: resolving timed out after 5s");
+
+		assert_failed!(containing "timed out");
+		assert_failed!(containing "after {}s", 5);
+	}
+
+	#[test]
+	#[should_panic(expected = "but it ended with Fail")]
+	fn tasks_that_ended_otherwise_fail_the_assertion() {
+		enable();
+		task!("test: linking");
+		fail!("test: linking");
+
+		assert_passed!("test: linking");
+	}
+
+	#[test]
+	#[should_panic(expected = "but no such task was completed")]
+	fn tasks_that_never_ended_fail_the_assertion() {
+		enable();
+		assert_warned!("test: never started");
+	}
+
+	#[test]
+	#[should_panic(expected = "but no such task was completed")]
+	fn exact_messages_must_match_whole() {
+		enable();
+		task!("test: packaging crates");
+		pass!("test: packaged");
+
+		assert_passed!("test: packaging");
+	}
+}
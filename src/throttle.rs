@@ -0,0 +1,46 @@
+use std::{
+	sync::{atomic::{AtomicBool, Ordering}, Mutex},
+	time::Duration,
+};
+
+use crate::{sync::Recover, time::Instant};
+
+/// Remembers whether a call site has logged yet, for `*_once!` macros.
+#[doc(hidden)]
+pub struct __Once__(AtomicBool);
+
+impl __Once__ {
+	#[allow(clippy::new_without_default)]
+	pub const fn new() -> Self {
+		Self(AtomicBool::new(false))
+	}
+
+	/// Returns `true` only the first time it is called.
+	pub fn first(&self) -> bool {
+		!self.0.swap(true, Ordering::Relaxed)
+	}
+}
+
+/// Remembers when a call site last logged, for `*_throttled!` macros.
+#[doc(hidden)]
+pub struct __Throttle__(Mutex<Option<Instant>>);
+
+impl __Throttle__ {
+	#[allow(clippy::new_without_default)]
+	pub const fn new() -> Self {
+		Self(Mutex::new(None))
+	}
+
+	/// Returns `true` if at least `interval` has passed since it last
+	/// returned `true`, or if it never has.
+	pub fn ready(&self, interval: Duration) -> bool {
+		let mut last = self.0.lock().recover();
+
+		if last.is_some_and(|last| last.elapsed() < interval) {
+			return false;
+		}
+
+		*last = Some(Instant::now());
+		true
+	}
+}
@@ -0,0 +1,425 @@
+use std::{
+	ops::Sub,
+	sync::{Mutex, OnceLock, RwLock},
+	time::Duration,
+};
+
+use crate::{config::CONFIG, locale, sync::Recover};
+
+// std has no clock in the browser, where the performance API is read instead
+#[cfg(all(target_family = "wasm", target_os = "unknown"))]
+use web_time::{Instant as SystemInstant, SystemTime, UNIX_EPOCH};
+#[cfg(not(all(target_family = "wasm", target_os = "unknown")))]
+use std::time::{Instant as SystemInstant, SystemTime, UNIX_EPOCH};
+
+use crate::json;
+
+// the clock set by `set_clock`, if any, in place of the system's
+static CLOCK: RwLock<Option<fn() -> Duration>> = RwLock::new(None);
+
+/// Sets the clock that every duration is measured with, in place of the
+/// system's monotonic clock, such as a count of timer ticks on a machine
+/// without one, or a simulated clock in tests that need durations and
+/// estimates to come out the same on every run.
+///
+/// The clock returns the time since any fixed point, and should never go
+/// backward. It should be set before any task starts, since times read
+/// from different clocks can't be compared.
+pub fn set_clock(clock: fn() -> Duration) {
+	*CLOCK.write().recover() = Some(clock);
+}
+
+/// A point in time, as read from the clock set by [`set_clock`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant(Duration);
+
+impl Instant {
+	/// Returns the current time.
+	pub fn now() -> Self {
+		static ORIGIN: OnceLock<SystemInstant> = OnceLock::new();
+
+		match *CLOCK.read().recover() {
+			Some(clock) => Self(clock()),
+			None => Self(ORIGIN.get_or_init(SystemInstant::now).elapsed()),
+		}
+	}
+
+	/// Returns how much time has passed since this instant, or zero
+	/// if the clock has gone backward since.
+	pub fn elapsed(&self) -> Duration {
+		Self::now() - *self
+	}
+
+	/// Returns the instant as the time since the clock's fixed point,
+	/// which is the first time the clock was read unless it was set.
+	pub(crate) fn since_origin(self) -> Duration {
+		self.0
+	}
+}
+
+impl Sub for Instant {
+	type Output = Duration;
+
+	fn sub(self, earlier: Self) -> Duration {
+		self.0.saturating_sub(earlier.0)
+	}
+}
+
+/// How durations are written wherever they are shown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DurationStyle {
+	/// Units are chosen by magnitude, as in `850ms`, `1.23s`, and `2m 3s`.
+	/// This is the default.
+	Auto,
+	/// Always in whole milliseconds, as in `1234ms`.
+	Milliseconds,
+	/// Always in seconds to two decimal places, as in `1.23s`.
+	Seconds,
+	/// In whole hours, minutes, and seconds, leaving out any leading
+	/// units that are zero, as in `1h 2m 3s` or `2m 3s`. Durations under
+	/// a second are written in milliseconds.
+	Components,
+	/// Spelled out in words, as in `2 minutes 3 seconds`.
+	Words,
+}
+
+/// Sets how durations are written, in task endings and profiles alike, as
+/// well as in live elapsed times and the estimates of transfers.
+pub fn set_duration_style(style: DurationStyle) {
+	CONFIG.write().recover().duration_style = style;
+}
+
+/// Formats a duration in the configured style.
+pub(crate) fn format(duration: Duration) -> String {
+	let style = CONFIG.read().recover().duration_style;
+	format_with(duration, style)
+}
+
+pub(crate) fn format_with(duration: Duration, style: DurationStyle) -> String {
+	let millis = duration.as_millis();
+	let seconds = duration.as_secs();
+
+	match style {
+		DurationStyle::Auto if millis < 1000 => format!("{millis}ms"),
+		DurationStyle::Auto if millis < 60_000 => format!("{:.2}s", duration.as_secs_f64()),
+		DurationStyle::Auto => format!("{}m {}s", seconds / 60, seconds % 60),
+		DurationStyle::Milliseconds => format!("{millis}ms"),
+		DurationStyle::Seconds => format!("{:.2}s", duration.as_secs_f64()),
+		DurationStyle::Components if seconds == 0 => format!("{millis}ms"),
+		DurationStyle::Components => components(seconds)
+			.map(|(amount, unit)| format!("{amount}{}", &unit[..1]))
+			.collect::<Vec<_>>()
+			.join(" "),
+		DurationStyle::Words if seconds == 0 => plural(millis as u64, "millisecond"),
+		DurationStyle::Words => components(seconds)
+			.map(|(amount, unit)| plural(amount, unit))
+			.collect::<Vec<_>>()
+			.join(" "),
+	}
+}
+
+/// Splits whole seconds into hours, minutes, and seconds, skipping
+/// leading units that are zero.
+fn components(seconds: u64) -> impl Iterator<Item = (u64, &'static str)> {
+	[(seconds / 3600, "hour"), (seconds / 60 % 60, "minute"), (seconds % 60, "second")]
+		.into_iter()
+		.skip_while(|&(amount, unit)| amount == 0 && unit != "second")
+}
+
+fn plural(amount: u64, unit: &str) -> String {
+	let (one, other) = match unit {
+		"hour" => ("{count} hour", "{count} hours"),
+		"minute" => ("{count} minute", "{count} minutes"),
+		"second" => ("{count} second", "{count} seconds"),
+		_ => ("{count} millisecond", "{count} milliseconds"),
+	};
+
+	locale::count(amount, one, other)
+}
+
+/// How points in time are written in exports, such as
+/// [`Session::to_json`](crate::Session::to_json), which are read by log
+/// ingestion systems that each expect their own.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TimestampFormat {
+	/// Milliseconds since the Unix epoch, written as a number, as in
+	/// `1791970200000`. This is the default.
+	EpochMillis,
+	/// RFC 3339 in UTC to the millisecond, as in `2026-10-14T09:30:00.000Z`.
+	Rfc3339,
+	/// A strftime pattern in UTC, such as `%Y-%m-%d %H:%M:%S`, which is
+	/// written as RFC 3339 if the pattern is invalid. This needs the
+	/// `chrono` feature.
+	#[cfg(feature = "chrono")]
+	Strftime(String),
+}
+
+/// Sets how points in time are written in exports.
+pub fn set_timestamp_format(format: TimestampFormat) {
+	CONFIG.write().recover().timestamps = format;
+}
+
+/// Returns the time by the system's clock, as the time since the Unix
+/// epoch, regardless of the clock set by [`set_clock`].
+pub(crate) fn wall_clock() -> Duration {
+	SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default()
+}
+
+/// Writes a time since the Unix epoch as a JSON value in the configured
+/// format, which is a number or a string.
+pub(crate) fn timestamp(since_epoch: Duration) -> String {
+	match &CONFIG.read().recover().timestamps {
+		TimestampFormat::EpochMillis => since_epoch.as_millis().to_string(),
+		TimestampFormat::Rfc3339 => json::string(&rfc3339(since_epoch)),
+		#[cfg(feature = "chrono")]
+		TimestampFormat::Strftime(pattern) => {
+			use std::fmt::Write;
+
+			let mut out = String::new();
+
+			// an invalid pattern fails to format rather than panicking
+			let formatted = chrono::DateTime::from_timestamp_millis(since_epoch.as_millis() as i64)
+				.is_some_and(|time| write!(out, "{}", time.format(pattern)).is_ok());
+
+			if !formatted {
+				out = rfc3339(since_epoch);
+			}
+
+			json::string(&out)
+		},
+	}
+}
+
+/// Writes a time since the Unix epoch in RFC 3339, in UTC.
+fn rfc3339(since_epoch: Duration) -> String {
+	let seconds = since_epoch.as_secs();
+	let (year, month, day) = civil(seconds / 86_400);
+
+	format!(
+		"{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+		seconds / 3600 % 24,
+		seconds / 60 % 60,
+		seconds % 60,
+		since_epoch.subsec_millis(),
+	)
+}
+
+/// Reads a time written by [`rfc3339`] back as the time since the Unix epoch.
+pub(crate) fn parse_rfc3339(text: &str) -> Option<Duration> {
+	let (date, time) = text.strip_suffix('Z')?.split_once('T')?;
+	let mut date = date.splitn(3, '-').map(str::parse::<u64>);
+	let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+
+	let (time, fraction) = time.split_once('.').unwrap_or((time, "0"));
+	let mut time = time.splitn(3, ':').map(str::parse::<u64>);
+	let (hours, minutes, seconds) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+
+	// the text may come from anywhere, so every field is checked before
+	// it is added up, and a leap second is allowed
+	if !(1970..=9999).contains(&year) || !(1..=12).contains(&month) || !(1..=31).contains(&day)
+		|| hours >= 24 || minutes >= 60 || seconds > 60
+	{
+		return None;
+	}
+
+	if fraction.is_empty() || !fraction.bytes().all(|byte| byte.is_ascii_digit()) {
+		return None;
+	}
+
+	// a fraction is read to the nanosecond, whatever its number of digits
+	let nanos = fraction.bytes()
+		.chain(std::iter::repeat(b'0'))
+		.take(9)
+		.fold(0, |nanos, digit| nanos * 10 + u32::from(digit - b'0'));
+
+	let seconds = days(year, month, day) * 86_400 + hours * 3600 + minutes * 60 + seconds;
+	Some(Duration::new(seconds, nanos))
+}
+
+/// Converts days since the Unix epoch to a year, month, and day in the
+/// proleptic Gregorian calendar, by Howard Hinnant's algorithm.
+fn civil(days: u64) -> (u64, u64, u64) {
+	let days = days + 719_468;
+	let era = days / 146_097;
+	let day_of_era = days % 146_097;
+	let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let month = (5 * day_of_year + 2) / 153;
+	let day = day_of_year - (153 * month + 2) / 5 + 1;
+	let month = if month < 10 { month + 3 } else { month - 9 };
+	let year = era * 400 + year_of_era + u64::from(month <= 2);
+
+	(year, month, day)
+}
+
+/// Converts a year, month, and day in the proleptic Gregorian calendar to
+/// days since the Unix epoch, undoing [`civil`].
+fn days(year: u64, month: u64, day: u64) -> u64 {
+	let year = year - u64::from(month <= 2);
+	let era = year / 400;
+	let year_of_era = year % 400;
+	let month = if month > 2 { month - 3 } else { month + 9 };
+	let day_of_year = (153 * month + 2) / 5 + day - 1;
+	let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+	era * 146_097 + day_of_era - 719_468
+}
+
+// when the last task started or ended, for showing the time between them
+static LAST_EVENT: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Returns the time since the previous task started or ended, such as
+/// ` +420ms`, to be shown after the line of a task that is starting or
+/// ending now. This is empty unless enabled with [`set_deltas`](crate::set_deltas).
+///
+/// With [`set_local_times`](crate::set_local_times), this is the local
+/// time instead, such as ` 14:03:07`.
+pub(crate) fn delta() -> String {
+	#[cfg(feature = "chrono")]
+	if CONFIG.read().recover().local_times {
+		return format!(" \x1b[2m{}\x1b[0m", chrono::Local::now().format("%H:%M:%S"));
+	}
+
+	if !CONFIG.read().recover().deltas {
+		return String::new();
+	}
+
+	let now = Instant::now();
+	let last = LAST_EVENT.lock().recover().replace(now);
+	let delta = last.map_or(Duration::ZERO, |last| now - last);
+
+	format!(" \x1b[2m+{}\x1b[0m", format(delta))
+}
+
+/// Formats a duration for display after a task's ending message,
+/// colored according to the configured thresholds. If the CPU time
+/// spent during the task is known, it is shown alongside.
+pub(crate) fn colored(duration: Duration, cpu: Option<Duration>) -> String {
+	let config = CONFIG.read().recover();
+
+	// green is fast, yellow is slow, red is very slow
+	let color = if duration >= config.red_after {
+		31
+	} else if duration >= config.yellow_after {
+		33
+	} else {
+		32
+	};
+
+	match cpu {
+		Some(cpu) => format!(
+			"\x1b[{color}m(wall {}, cpu {})\x1b[0m",
+			format_with(duration, config.duration_style),
+			format_with(cpu, config.duration_style),
+		),
+		None => format!("\x1b[{color}m({})\x1b[0m", format_with(duration, config.duration_style)),
+	}
+}
+
+/// Returns the CPU time consumed by the whole process so far, summed
+/// across all of its threads, or `None` if it cannot be measured.
+#[cfg(all(feature = "cpu-time", any(target_os = "linux", target_os = "macos", target_os = "freebsd")))]
+pub(crate) fn cpu_time() -> Option<Duration> {
+	use std::os::raw::{c_int, c_long};
+
+	#[repr(C)]
+	struct Timespec {
+		tv_sec: c_long,
+		tv_nsec: c_long,
+	}
+
+	extern "C" {
+		fn clock_gettime(clock: c_int, time: *mut Timespec) -> c_int;
+	}
+
+	#[cfg(target_os = "linux")]
+	const CLOCK_PROCESS_CPUTIME_ID: c_int = 2;
+	#[cfg(target_os = "macos")]
+	const CLOCK_PROCESS_CPUTIME_ID: c_int = 12;
+	#[cfg(target_os = "freebsd")]
+	const CLOCK_PROCESS_CPUTIME_ID: c_int = 15;
+
+	let mut time = Timespec { tv_sec: 0, tv_nsec: 0 };
+
+	// SAFETY: `time` is a valid, writable timespec for the duration of the call
+	if unsafe { clock_gettime(CLOCK_PROCESS_CPUTIME_ID, &mut time) } != 0 {
+		return None;
+	}
+
+	Some(Duration::new(time.tv_sec as u64, time.tv_nsec as u32))
+}
+
+#[cfg(not(all(feature = "cpu-time", any(target_os = "linux", target_os = "macos", target_os = "freebsd"))))]
+pub(crate) fn cpu_time() -> Option<Duration> {
+	None
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn rfc3339_round_trips() {
+		let time = Duration::from_millis(1_791_970_200_123);
+		assert_eq!(rfc3339(time), "2026-10-14T09:30:00.123Z");
+		assert_eq!(parse_rfc3339(&rfc3339(time)), Some(time));
+	}
+
+	#[test]
+	fn rfc3339_fractions_scale_by_their_digits() {
+		let second = Duration::from_secs(1_767_225_600);
+		assert_eq!(parse_rfc3339("2026-01-01T00:00:00.5Z"), Some(second + Duration::from_millis(500)));
+		assert_eq!(parse_rfc3339("2026-01-01T00:00:00.000001Z"), Some(second + Duration::from_micros(1)));
+		assert_eq!(parse_rfc3339("2026-01-01T00:00:00Z"), Some(second));
+	}
+
+	#[test]
+	fn rfc3339_rejects_fields_out_of_range() {
+		assert_eq!(parse_rfc3339("2026-01-01T9999999999999999:00:00Z"), None);
+		assert_eq!(parse_rfc3339("99999999999999-01-01T00:00:00Z"), None);
+		assert_eq!(parse_rfc3339("2026-01-01T24:00:00Z"), None);
+		assert_eq!(parse_rfc3339("2026-01-01T00:60:00Z"), None);
+		assert_eq!(parse_rfc3339("2026-01-01T00:00:61Z"), None);
+		assert_eq!(parse_rfc3339("2026-13-01T00:00:00Z"), None);
+		assert_eq!(parse_rfc3339("2026-01-01T00:00:00.Z"), None);
+		assert_eq!(parse_rfc3339("2026-01-01T00:00:00.-5Z"), None);
+		assert_eq!(parse_rfc3339("2026-01-01 00:00:00Z"), None);
+	}
+
+	#[test]
+	fn durations_are_written_in_each_style() {
+		let short = Duration::from_millis(420);
+		let long = Duration::from_millis(3_723_500);
+
+		assert_eq!(format_with(short, DurationStyle::Auto), "420ms");
+		assert_eq!(format_with(Duration::from_millis(1_500), DurationStyle::Auto), "1.50s");
+		assert_eq!(format_with(long, DurationStyle::Auto), "62m 3s");
+		assert_eq!(format_with(long, DurationStyle::Milliseconds), "3723500ms");
+		assert_eq!(format_with(short, DurationStyle::Seconds), "0.42s");
+		assert_eq!(format_with(long, DurationStyle::Components), "1h 2m 3s");
+		assert_eq!(format_with(Duration::from_secs(60), DurationStyle::Components), "1m 0s");
+		assert_eq!(format_with(short, DurationStyle::Components), "420ms");
+		assert_eq!(format_with(long, DurationStyle::Words), "1 hour 2 minutes 3 seconds");
+		assert_eq!(format_with(Duration::from_millis(1), DurationStyle::Words), "1 millisecond");
+	}
+
+	#[test]
+	fn dates_convert_both_ways() {
+		assert_eq!(civil(0), (1970, 1, 1));
+		assert_eq!(civil(11_016), (2000, 2, 29));
+		assert_eq!(civil(20_740), (2026, 10, 14));
+		assert_eq!(days(2000, 3, 1), 11_017);
+
+		for day in (0..3_000_000).step_by(997) {
+			let (year, month, day_of_month) = civil(day);
+			assert_eq!(days(year, month, day_of_month), day);
+		}
+	}
+
+	#[test]
+	fn rfc3339_is_written_in_utc_to_the_millisecond() {
+		assert_eq!(rfc3339(Duration::ZERO), "1970-01-01T00:00:00.000Z");
+		assert_eq!(rfc3339(Duration::from_millis(951_782_400_007)), "2000-02-29T00:00:00.007Z");
+	}
+}
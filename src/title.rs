@@ -0,0 +1,45 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::{config::CONFIG, format::plain, output, sync::Recover, Task};
+
+// whether the terminal's own title has been saved on its title stack
+static SAVED: AtomicBool = AtomicBool::new(false);
+
+/// Shows the current top-level task, and how far along it is, in the
+/// terminal's title. Once no tasks are left, the original title is restored.
+pub(crate) fn update(tasks: &[&Task]) {
+	if !CONFIG.read().recover().title {
+		return;
+	}
+
+	let Some(top) = tasks.first() else {
+		// \x1b[23;0t : pop the saved title off the title stack
+		if SAVED.swap(false, Ordering::Relaxed) {
+			output::emit("\x1b[23;0t");
+		}
+
+		return;
+	};
+
+	let mut out = String::new();
+
+	// \x1b[22;0t : push the current title onto the title stack
+	if !SAVED.swap(true, Ordering::Relaxed) {
+		out.push_str("\x1b[22;0t");
+	}
+
+	// the outermost task that reports progress stands in for the whole tree
+	let title = match tasks.iter().find_map(|task| task.percent()) {
+		Some(percent) => format!("{} ({percent}%)", plain(&top.message)),
+		None => plain(&top.message),
+	};
+
+	// control characters would terminate the sequence early
+	let title = title.chars()
+		.filter(|c| !c.is_control())
+		.collect::<String>();
+
+	// \x1b]2;{title}\x07 : set the window title
+	out.push_str(&format!("\x1b]2;{title}\x07"));
+	output::emit(&out);
+}
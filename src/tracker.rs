@@ -0,0 +1,203 @@
+use alloc::{string::ToString, vec::Vec};
+use core::{
+	fmt::{self, Display},
+	time::Duration,
+};
+
+use crate::{
+	node::{self, Node},
+	Event,
+	Propagation,
+	Status,
+};
+
+/// Where a [`Tracker`] sends the events of its tasks.
+pub trait Output {
+	/// Handles one event.
+	fn event(&mut self, event: &Event);
+}
+
+impl<F> Output for F
+where
+	F: FnMut(&Event),
+{
+	fn event(&mut self, event: &Event) {
+		self(event)
+	}
+}
+
+/// Writes every event as a line of JSON, as written by
+/// [`Event::to_json`], such as to a serial port.
+#[derive(Clone, Debug)]
+pub struct JsonLines<W>(pub W);
+
+impl<W: fmt::Write> Output for JsonLines<W> {
+	fn event(&mut self, event: &Event) {
+		// output is best effort, just as it is on a terminal
+		_ = writeln!(self.0, "{}", event.to_json());
+	}
+}
+
+/// Keeps track of nested tasks without the standard library, passing
+/// their events to an [`Output`] rather than drawing them in a terminal.
+///
+/// This is the core of jeflog that remains when the default `std` feature
+/// is turned off, for embedded targets with a heap but no operating system,
+/// which can send the events over a serial port with [`JsonLines`] to be
+/// shown elsewhere. Unlike the macros, a tracker is owned by whatever
+/// drives it, so each task is nested under the task started before it
+/// that is still running, and ending a task ends the innermost one.
+///
+/// Durations are measured with `clock`, which returns the time since
+/// any fixed point, such as a count of timer ticks since boot. Its events
+/// are built the same way as those of the tasks drawn in a terminal, and
+/// a failure beneath a task is propagated to it the same way, as set by
+/// [`with_propagation`](Self::with_propagation).
+#[derive(Debug)]
+pub struct Tracker<O> {
+	output: O,
+	clock: fn() -> Duration,
+	propagation: Propagation,
+	// every running task, outermost first
+	running: Vec<Running>,
+	next_id: u64,
+}
+
+/// A task of a [`Tracker`] that hasn't ended.
+#[derive(Debug)]
+struct Running {
+	node: Node,
+	started: Duration,
+	failed_descendant: bool,
+}
+
+impl<O: Output> Tracker<O> {
+	/// Creates a tracker that sends events to `output`, timing
+	/// tasks with `clock`.
+	pub fn new(output: O, clock: fn() -> Duration) -> Self {
+		Self {
+			output,
+			clock,
+			propagation: Propagation::Off,
+			running: Vec::new(),
+			next_id: 0,
+		}
+	}
+
+	/// Sets what happens to a task that ends normally after a task beneath
+	/// it has failed, which is nothing by default, like
+	/// [`set_propagation`](crate::set_propagation) does for the tasks drawn
+	/// in a terminal.
+	#[must_use]
+	pub fn with_propagation(mut self, propagation: Propagation) -> Self {
+		self.propagation = propagation;
+		self
+	}
+
+	/// Starts a task nested under the innermost running task, returning
+	/// the id its events are sent with.
+	pub fn start(&mut self, message: impl Display) -> u64 {
+		let node = Node {
+			id: self.next_id,
+			parent: self.parent(),
+			depth: self.running.len(),
+		};
+
+		let started = (self.clock)();
+		self.next_id += 1;
+		self.output.event(&node.start(started, message.to_string()));
+		self.running.push(Running { node, started, failed_descendant: false });
+		node.id
+	}
+
+	/// Ends the innermost running task with `status`, returning the status
+	/// it ended with, which may differ from `status` if a failure beneath it
+	/// was propagated to it. Nothing is sent if no task is running.
+	pub fn end(&mut self, status: Status, message: impl Display) -> Status {
+		let Some(task) = self.running.pop() else {
+			return status;
+		};
+
+		let status = self.propagation.settle(status, task.failed_descendant);
+		let time = (self.clock)();
+		let duration = time.saturating_sub(task.started);
+		self.output.event(&task.node.end(time, status, duration, message.to_string()));
+
+		if let Some(parent) = self.running.last_mut() {
+			parent.failed_descendant |= node::fails_parent(status, task.failed_descendant);
+		}
+
+		status
+	}
+
+	/// Sends a line of text printed beneath the innermost running task.
+	pub fn text(&mut self, text: impl Display) {
+		self.output.event(&Event::Text {
+			task: self.parent(),
+			depth: self.running.len(),
+			time: (self.clock)(),
+			text: text.to_string(),
+		});
+	}
+
+	/// Reports how far along the innermost running task is, as `current`
+	/// out of `total` units of work. It has no effect if no task is running.
+	pub fn progress(&mut self, current: u64, total: u64) {
+		if let Some(task) = self.running.last() {
+			self.output.event(&task.node.progress((self.clock)(), current.min(total), total));
+		}
+	}
+
+	/// The id of the innermost running task, which a new task or line is
+	/// nested under.
+	fn parent(&self) -> Option<u64> {
+		self.running.last().map(|task| task.node.id)
+	}
+
+	/// Returns the output, once the tracker is no longer needed.
+	pub fn into_output(self) -> O {
+		self.output
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn record(events: &mut Vec<Event>) -> impl FnMut(&Event) + '_ {
+		|event| events.push(event.clone())
+	}
+
+	#[test]
+	fn tasks_nest_under_the_innermost_running_task() {
+		let mut events = Vec::new();
+		let mut tracker = Tracker::new(record(&mut events), || Duration::ZERO);
+
+		let outer = tracker.start("outer");
+		let inner = tracker.start("inner");
+		tracker.progress(5, 2);
+		tracker.end(Status::Pass, "inner");
+		tracker.end(Status::Pass, "outer");
+		drop(tracker);
+
+		assert!(matches!(events[1], Event::Start { id, parent, depth: 1, .. } if id == inner && parent == Some(outer)));
+		assert!(matches!(events[2], Event::Progress { current: 2, total: 2, .. }));
+		assert!(matches!(events[4], Event::End { id, parent: None, depth: 0, .. } if id == outer));
+	}
+
+	#[test]
+	fn failures_beneath_a_task_are_propagated() {
+		let mut events = Vec::new();
+		let mut tracker = Tracker::new(record(&mut events), || Duration::ZERO).with_propagation(Propagation::Warn);
+
+		tracker.start("outer");
+		tracker.start("middle");
+		tracker.start("inner");
+		assert_eq!(tracker.end(Status::Fail, "inner"), Status::Fail);
+		assert_eq!(tracker.end(Status::Pass, "middle"), Status::Warn);
+		assert_eq!(tracker.end(Status::Pass, "outer"), Status::Warn);
+		drop(tracker);
+
+		assert!(matches!(events.last(), Some(Event::End { status: Status::Warn, .. })));
+	}
+}
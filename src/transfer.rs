@@ -0,0 +1,148 @@
+use std::{
+	fmt::Display,
+	time::Duration,
+};
+
+use crate::{config::CONFIG, locale, style, sync::Recover, time::{self, Instant}, Status};
+
+/// A set of transfers, such as downloads, shown as subtasks of the task
+/// that was running where it was created, each with a row of its own.
+///
+/// Every transfer shows a progress bar, followed by how many bytes have
+/// been transferred out of its total, its average rate, and how long it
+/// can be expected to take from there. Transfers can be added at any time,
+/// from any thread, and each one ends when it is finished, failed, or
+/// dropped. Unlike other tasks, transfers are never ended by the ending
+/// macros, so they can run alongside tasks of the threads doing them.
+#[derive(Clone, Debug)]
+pub struct MultiProgress {
+	parent: Option<u64>,
+}
+
+impl MultiProgress {
+	/// Creates a set of transfers nested under the task that a task
+	/// started by this thread would be nested under.
+	pub fn new() -> Self {
+		Self { parent: crate::enclosing_id() }
+	}
+
+	/// Adds a transfer of `total` bytes, shown by its name.
+	pub fn add(&self, name: impl Display, total: u64) -> Transfer {
+		let name = name.to_string();
+
+		Transfer {
+			id: crate::begin_detached(self.parent, name.clone()),
+			name,
+			total,
+			bytes: 0,
+			started: Instant::now(),
+			drawn: None,
+			ended: false,
+		}
+	}
+}
+
+impl Default for MultiProgress {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// One transfer of a [`MultiProgress`], which is cancelled if it is
+/// dropped before it is finished or failed.
+#[derive(Debug)]
+pub struct Transfer {
+	id: u64,
+	name: String,
+	total: u64,
+	bytes: u64,
+	started: Instant,
+	drawn: Option<Instant>,
+	ended: bool,
+}
+
+impl Transfer {
+	/// Reports that `bytes` bytes have been transferred so far.
+	///
+	/// The row is redrawn at most once per tick of the spinner, so this
+	/// can be called as often as data arrives.
+	pub fn set(&mut self, bytes: u64) {
+		self.bytes = bytes.min(self.total);
+
+		let tick = CONFIG.read().recover().tick;
+
+		if self.drawn.is_some_and(|drawn| drawn.elapsed() < tick) && self.bytes < self.total {
+			return;
+		}
+
+		self.drawn = Some(Instant::now());
+		crate::progress_of(self.id, self.bytes, self.total, self.note());
+	}
+
+	/// Reports that another `bytes` bytes have been transferred.
+	pub fn inc(&mut self, bytes: u64) {
+		self.set(self.bytes.saturating_add(bytes));
+	}
+
+	/// Ends the transfer as passing, with its size after its name.
+	pub fn finish(mut self) -> Status {
+		let message = format!("{} ({})", self.name, size(self.bytes));
+		self.end(Status::Pass, message)
+	}
+
+	/// Ends the transfer as failing, with `reason` after its name.
+	pub fn fail(mut self, reason: impl Display) -> Status {
+		let message = format!("{}: {reason}", self.name);
+		self.end(Status::Fail, message)
+	}
+
+	fn end(&mut self, status: Status, message: String) -> Status {
+		self.ended = true;
+		crate::end_task_by_id(self.id, status, style::clean(&message));
+		status
+	}
+
+	/// Describes how far along the transfer is, as shown after its bar.
+	fn note(&self) -> String {
+		let elapsed = self.started.elapsed().as_secs_f64();
+		let rate = if elapsed > 0.0 { self.bytes as f64 / elapsed } else { 0.0 };
+		let mut note = format!("{} / {} • {}/s", size(self.bytes), size(self.total), size(rate as u64));
+
+		// the rate says little until some data has arrived
+		if rate >= 1.0 && self.bytes < self.total {
+			let eta = Duration::from_secs(((self.total - self.bytes) as f64 / rate).ceil() as u64);
+			let eta = time::format(eta);
+			note += &format!(" • {}", locale::text("eta {duration}", &[("duration", &eta)]));
+		}
+
+		note
+	}
+}
+
+impl Drop for Transfer {
+	fn drop(&mut self) {
+		if !self.ended {
+			let message = self.name.clone();
+			self.end(Status::Cancel, message);
+		}
+	}
+}
+
+/// Formats a number of bytes in binary units, such as `4.2 MiB`.
+fn size(bytes: u64) -> String {
+	const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+
+	if bytes < 1024 {
+		return format!("{bytes} B");
+	}
+
+	let mut value = bytes as f64 / 1024.0;
+	let mut unit = 0;
+
+	while value >= 1024.0 && unit < UNITS.len() - 1 {
+		value /= 1024.0;
+		unit += 1;
+	}
+
+	format!("{value:.1} {}", UNITS[unit])
+}
@@ -0,0 +1,2161 @@
+use std::{
+	borrow::Cow,
+	cell::Cell,
+	fmt::{Arguments, Display, Write},
+	io::{self, IsTerminal},
+	mem,
+	process,
+	sync::{atomic::{AtomicBool, AtomicU64, Ordering}, Mutex},
+	thread::{self, ThreadId},
+	time::Duration,
+};
+
+#[cfg(feature = "metrics")]
+use crate::monitor;
+use crate::{
+	config::{CONFIG, MIN_TICK},
+	diff,
+	events,
+	format,
+	level,
+	locale,
+	node::{self, Node},
+	output,
+	redact,
+	session::{self, SESSION},
+	style,
+	sync::Recover,
+	taskbar,
+	term::Frame,
+	time::{self, Instant},
+	title,
+	CancelToken,
+	Color,
+	Event,
+	Format,
+	Propagation,
+	Record,
+	Status,
+};
+
+#[derive(Clone, Debug)]
+pub(crate) struct Task {
+	pub id: u64,
+	pub parent: Option<u64>,
+	pub depth: usize,
+	pub owner: Option<ThreadId>,
+	pub row_offset: i32,
+	pub rows: usize,
+	pub last_child_offset: Option<i32>,
+	pub summary_offset: Option<i32>,
+	pub interrupted: bool,
+	pub sibling_offset: Option<i32>,
+	pub parent_interrupted: bool,
+	pub collapsed: bool,
+	pub unshown: usize,
+	pub started: Instant,
+	pub cpu_started: Option<Duration>,
+	pub message: String,
+	pub target: Option<String>,
+	pub icon: Option<String>,
+	pub hidden: bool,
+	pub progress: Option<(u64, u64)>,
+	pub children: Vec<Record>,
+	pub failed_descendant: bool,
+	// how the task ends after a failure beneath it, if not as for every task
+	pub propagation: Option<Propagation>,
+	pub group: Option<usize>,
+	// how each subtask ended, including hidden ones, which a group ends by
+	pub ended: Vec<Status>,
+	pub held: Vec<String>,
+	pub output: Vec<String>,
+	// how many of the earliest lines of output were let go
+	pub omitted: usize,
+	pub delta: String,
+	// the GitLab section the task opened, which it closes as it ends
+	pub section: Option<usize>,
+	pub shown_elapsed: u64,
+	pub active: Instant,
+	pub stalled: bool,
+	pub token: CancelToken,
+	pub note: String,
+	pub background: bool,
+}
+
+impl Task {
+	/// Where the task is nested, which its events are built from.
+	pub fn node(&self) -> Node {
+		Node { id: self.id, parent: self.parent, depth: self.depth }
+	}
+
+	/// The task's message as it is shown, after its [`prefix`](Self::prefix).
+	pub fn label(&self) -> String {
+		self.prefix() + &self.message
+	}
+
+	/// What is shown before each of the task's messages: its icon, if it
+	/// has one and icons are shown, and its target's tag if it has one.
+	pub fn prefix(&self) -> String {
+		let mut prefix = String::new();
+
+		if let Some(icon) = self.icon.as_ref().filter(|_| style::icons()) {
+			prefix += icon;
+			prefix.push(' ');
+		}
+
+		if let Some(target) = &self.target {
+			prefix += &style::tag(target);
+			prefix.push(' ');
+		}
+
+		prefix
+	}
+
+	/// Moves the task, and the rows of the subtasks on either side of it,
+	/// up by `rows` rows to account for lines printed beneath them.
+	pub fn shift(&mut self, rows: i32) {
+		self.row_offset += rows;
+
+		if let Some(offset) = &mut self.last_child_offset {
+			*offset += rows;
+		}
+
+		if let Some(offset) = &mut self.summary_offset {
+			*offset += rows;
+		}
+
+		if let Some(offset) = &mut self.sibling_offset {
+			*offset += rows;
+		}
+	}
+
+	/// How much of the task is complete, if it has reported progress.
+	pub fn percent(&self) -> Option<u64> {
+		self.progress.map(|(current, total)| share(current, total, 100))
+	}
+}
+
+/// Scales how much of `total` units of work `current` is to `out_of`, as
+/// in a percentage when it is 100, without overflowing however large the
+/// units are. No work at all counts as complete.
+pub(crate) fn share(current: u64, total: u64, out_of: u64) -> u64 {
+	match total {
+		0 => out_of,
+		// the product of two u64s always fits in a u128
+		total => (u128::from(current.min(total)) * u128::from(out_of) / u128::from(total)) as u64,
+	}
+}
+
+// every running task, in the order they were started, so each
+// task always comes after the task it is nested under
+pub(crate) static TASKS: Mutex<Vec<Task>> = Mutex::new(Vec::new());
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+// whether a thread is watching for tasks that stall, which is only changed
+// while TASKS is locked so that a task can't start just as the watcher stops
+static WATCHING: AtomicBool = AtomicBool::new(false);
+
+// how many lines had been written when the current generation
+// started, if one has been started with `new_generation`
+static GENERATION: Mutex<Option<u64>> = Mutex::new(None);
+
+// tasks that have ended while a handle to them could still reopen them,
+// each with how many lines had been written when its line was drawn
+static ENDED: Mutex<Vec<(Task, Option<u64>)>> = Mutex::new(Vec::new());
+
+// whether the application has called `init`, without which nothing is shown
+static INITIALIZED: AtomicBool = AtomicBool::new(false);
+
+// whether the run's summary has been printed, which `finish` only does once
+static SUMMARIZED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+	// the task that tasks started on this thread are nested under
+	// while it has none of its own, such as a task on the thread
+	// that spawned it
+	static INHERITED: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// Returns the index of the innermost task started by this thread,
+/// which is the one that ending macros apply to.
+pub(crate) fn current(tasks: &[Task]) -> Option<usize> {
+	let thread = thread::current().id();
+	tasks.iter().rposition(|task| task.owner == Some(thread))
+}
+
+/// Returns the index of the task that a task started by this thread
+/// would be nested under, and that lines it prints are printed beneath.
+pub(crate) fn enclosing(tasks: &[Task]) -> Option<usize> {
+	current(tasks).or_else(|| {
+		let id = INHERITED.get()?;
+		tasks.iter().position(|task| task.id == id)
+	})
+}
+
+/// Returns the id of the task that a task started by this
+/// thread would be nested under.
+pub(crate) fn enclosing_id() -> Option<u64> {
+	let tasks = TASKS.lock().recover();
+	enclosing(&tasks).map(|index| tasks[index].id)
+}
+
+/// Returns how deeply the task that a task started by this thread would
+/// be nested under is nested itself.
+pub(crate) fn enclosing_depth() -> Option<usize> {
+	let tasks = TASKS.lock().recover();
+	enclosing(&tasks).map(|index| tasks[index].depth)
+}
+
+/// Returns the index of the task that a subtask of the task at index
+/// `parent` is drawn beneath, which is `parent` itself unless it is as
+/// deep as [`set_max_depth`] allows, in which case it is the innermost of
+/// its ancestors that isn't.
+pub(crate) fn shown_parent(tasks: &[Task], mut parent: Option<usize>) -> Option<usize> {
+	let Some(max) = CONFIG.read().recover().max_depth else {
+		return parent;
+	};
+
+	while let Some(index) = parent.filter(|&index| tasks[index].depth >= max) {
+		parent = tasks[index].parent.and_then(|id| tasks.iter().position(|task| task.id == id));
+	}
+
+	parent
+}
+
+/// Returns whether no task is drawn in the tree, which background tasks
+/// are drawn apart from.
+pub(crate) fn tree_empty(tasks: &[Task]) -> bool {
+	tasks.iter().all(|task| task.background)
+}
+
+/// Returns whether durations are left out of rows, as they are in trees
+/// laid out for narrow terminals, where they would crowd out messages.
+/// A tree that is yet to start is laid out for the terminal as it is now.
+fn narrow(starting: bool) -> bool {
+	format::current() == Format::Interactive && if starting {
+		format::interactive::too_narrow()
+	} else {
+		format::interactive::narrow()
+	}
+}
+
+/// Nests tasks started by this thread under the task with the given
+/// id while the thread has none of its own, returning the previous one.
+pub(crate) fn inherit(id: Option<u64>) -> Option<u64> {
+	INHERITED.replace(id)
+}
+
+/// Turns the arguments of a macro that starts or ends a task into its
+/// message, which is either a format string and its arguments or any
+/// single value that implements `Display`. The message is left unformatted
+/// until it's known that the task is shown.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __message__ {
+	($message:literal) => {
+		format_args!($message)
+	};
+	($message:expr) => {
+		format_args!("{}", $message)
+	};
+	($($tokens:tt)*) => {
+		format_args!($($tokens)*)
+	};
+}
+
+/// Begins a task or subtask with a spinner.
+///
+/// The message is either a format string and its arguments, as with
+/// `format!`, or any single value that implements `Display`, as in
+/// `task!(message)`, for messages built at runtime or translated.
+///
+/// A task may be given a target, as in `task!(target: "network", ...)`,
+/// which is shown as a colored tag before its message. Tasks for a target
+/// can be hidden, along with their subtasks, by setting it to a level less
+/// detailed than info in `JEFLOG_LOG`, such as `JEFLOG_LOG=network=off`.
+/// Hidden tasks aren't recorded in the [`session`](crate::session()).
+///
+/// A task may also be given an icon, as in `task!(icon: "📦", "packaging")`,
+/// which is shown before its messages, ahead of any tag, so that the phases
+/// of a long run are easy to pick out. A target is given before the icon,
+/// as in `task!(target: "network", icon: "🌐", ...)`. Icons are left out
+/// with [`Connectors::Ascii`](crate::Connectors::Ascii), and in the
+/// accessible and porcelain formats.
+#[macro_export]
+macro_rules! task {
+	(target: $target:expr, icon: $icon:expr, $($tokens:tt)*) => {
+		$crate::__start_with_icon__(Some($target), format_args!("{}", $icon), $crate::__message__!($($tokens)*));
+	};
+	(target: $target:expr, $($tokens:tt)*) => {
+		$crate::__start_task__(Some($target), $crate::__message__!($($tokens)*));
+	};
+	(icon: $icon:expr, $($tokens:tt)*) => {
+		$crate::__start_with_icon__(None, format_args!("{}", $icon), $crate::__message__!($($tokens)*));
+	};
+	($($tokens:tt)*) => {
+		$crate::__start_task__(None, $crate::__message__!($($tokens)*));
+	};
+}
+
+/// Begins a task that ends by itself once a given number of subtasks
+/// have ended, as in `task_group!(3, "building {} crates", 3)`.
+///
+/// The task passes if every subtask passed, fails if any of them failed,
+/// and warns otherwise, ending with the message it was started with. It
+/// may still be ended early with any of the usual macros.
+#[macro_export]
+macro_rules! task_group {
+	(target: $target:expr, $children:expr, $($tokens:tt)*) => {
+		$crate::__start_group__(Some($target), $children, $crate::__message__!($($tokens)*));
+	};
+	($children:expr, $($tokens:tt)*) => {
+		$crate::__start_group__(None, $children, $crate::__message__!($($tokens)*));
+	};
+}
+
+/// Indicates that the most recently created task has passed by
+/// replacing the spinner with a green check mark.
+///
+/// Like every macro that ends a task, this takes its message just as
+/// [`task!`] does, and evaluates to the [`Status`] the task ended with.
+/// This is usually the one given, but may be a warning or failure if a
+/// subtask failed and [`set_propagation`] is set.
+#[macro_export]
+macro_rules! pass {
+	($($tokens:tt)*) => {
+		$crate::__end_task__($crate::Status::Pass, $crate::__message__!($($tokens)*))
+	}
+}
+
+/// Passes the most recently created task, just as [`pass!`] does, but
+/// removes its line from the tree as though it had never started if it
+/// has no subtasks and printed nothing, for chatty checks where even a
+/// check mark for each is noise. Without a message, the task ends with
+/// the one it was started with.
+///
+/// Ending this way still records the task in the
+/// [`Session`](crate::Session) and sends its end to every
+/// [`Sink`](crate::Sink). Only the interactive format removes lines, and
+/// only while they're still on the screen; other formats, and tasks that
+/// did have something beneath them, end as with `pass!`.
+#[macro_export]
+macro_rules! pass_quiet {
+	() => {
+		$crate::__pass_quiet__(None)
+	};
+	($($tokens:tt)*) => {
+		$crate::__pass_quiet__(Some($crate::__message__!($($tokens)*)))
+	}
+}
+
+/// Indicates that the most recently created task has passed with a
+/// warning by replacing the spinner with a yellow triangle.
+#[macro_export]
+macro_rules! warn {
+	($($tokens:tt)*) => {
+		$crate::__end_task__($crate::Status::Warn, $crate::__message__!($($tokens)*))
+	}
+}
+
+/// Indicates that the most recently created task has failed by
+/// replacing the spinner with a red x.
+///
+/// A failure may be given a hint on how to fix it, as in
+/// `fail!(hint: "run `myctl login` first", "authentication failed")`,
+/// which is shown beneath the task and kept in its [`Record`].
+#[macro_export]
+macro_rules! fail {
+	(hint: $hint:expr, $($tokens:tt)*) => {
+		$crate::__fail_with_hint__(format_args!("{}", $hint), $crate::__message__!($($tokens)*))
+	};
+	($($tokens:tt)*) => {
+		$crate::__end_task__($crate::Status::Fail, $crate::__message__!($($tokens)*))
+	}
+}
+
+/// Fails the most recently created task because two texts that should
+/// have matched didn't, as in `fail_with_diff!(expected, actual, "config
+/// mismatch")`, showing a colored unified diff between them beneath it.
+#[macro_export]
+macro_rules! fail_with_diff {
+	($expected:expr, $actual:expr, $($tokens:tt)*) => {
+		$crate::__fail_with_diff__(
+			::core::convert::AsRef::<str>::as_ref(&$expected),
+			::core::convert::AsRef::<str>::as_ref(&$actual),
+			$crate::__message__!($($tokens)*),
+		)
+	};
+}
+
+/// Fails every running task at once, for fatal errors that end the
+/// program. The innermost task ends with the given message, and each
+/// task around it ends with the message it was started with.
+#[macro_export]
+macro_rules! fail_all {
+	($($tokens:tt)*) => {
+		$crate::__fail_all__($crate::__message__!($($tokens)*))
+	}
+}
+
+/// Indicates that the most recently created task was cancelled by
+/// replacing the spinner with a gray circle. Unlike `fail!`, this is
+/// meant for work that was deliberately aborted, such as after a user
+/// interrupt or when a task it depends on has failed.
+#[macro_export]
+macro_rules! cancel {
+	($($tokens:tt)*) => {
+		$crate::__end_task__($crate::Status::Cancel, $crate::__message__!($($tokens)*))
+	}
+}
+
+/// Prints a line of data, the output a program exists to produce, to
+/// standard output exactly as it is formatted, with no styles or indent,
+/// as with `data!("{name}\t{size}")`. Data is printed whether or not
+/// jeflog has been initialized.
+///
+/// With [`set_stderr`] enabled, tasks are drawn on standard error, so data
+/// can be piped onward while progress is still shown. Only while the tree
+/// is being drawn and standard output is a terminal too is each line
+/// printed among the tasks, as any other line is, so their rows stay in step.
+#[macro_export]
+macro_rules! data {
+	($($tokens:tt)*) => {
+		$crate::__data__(format!($($tokens)*))
+	}
+}
+
+/// Prints an informational line beneath the running tasks.
+///
+/// Which lines are shown can be controlled per module with the
+/// `JEFLOG_LOG` environment variable, in the style of `env_logger`,
+/// such as `JEFLOG_LOG=info,mycrate=debug,hyper=warn`. A line may also be
+/// given a target to filter by instead, as in `info!(target: "network", ...)`.
+#[macro_export]
+macro_rules! info {
+	(target: $target:expr, $($tokens:tt)*) => {
+		$crate::__log__($crate::Level::Info, Some($target), module_path!(), format_args!($($tokens)*));
+	};
+	($($tokens:tt)*) => {
+		$crate::__log__($crate::Level::Info, None, module_path!(), format_args!($($tokens)*));
+	};
+}
+
+/// Prints a dimmed debugging line beneath the running tasks. These are
+/// hidden unless enabled with `JEFLOG_LOG` or [`set_level`](crate::set_level).
+#[macro_export]
+macro_rules! debug {
+	(target: $target:expr, $($tokens:tt)*) => {
+		$crate::__log__($crate::Level::Debug, Some($target), module_path!(), format_args!($($tokens)*));
+	};
+	($($tokens:tt)*) => {
+		$crate::__log__($crate::Level::Debug, None, module_path!(), format_args!($($tokens)*));
+	};
+}
+
+/// Prints a dimmed, highly detailed tracing line beneath the running tasks.
+/// These are hidden unless enabled with `JEFLOG_LOG` or [`set_level`](crate::set_level).
+#[macro_export]
+macro_rules! trace {
+	(target: $target:expr, $($tokens:tt)*) => {
+		$crate::__log__($crate::Level::Trace, Some($target), module_path!(), format_args!($($tokens)*));
+	};
+	($($tokens:tt)*) => {
+		$crate::__log__($crate::Level::Trace, None, module_path!(), format_args!($($tokens)*));
+	};
+}
+
+/// Prints an informational line beneath the running tasks, like `info!`,
+/// but only the first time this call site is reached.
+#[macro_export]
+macro_rules! info_once {
+	(target: $target:expr, $($tokens:tt)*) => {{
+		static ONCE: $crate::__Once__ = $crate::__Once__::new();
+
+		if ONCE.first() {
+			$crate::__log__($crate::Level::Info, Some($target), module_path!(), format_args!($($tokens)*));
+		}
+	}};
+	($($tokens:tt)*) => {{
+		static ONCE: $crate::__Once__ = $crate::__Once__::new();
+
+		if ONCE.first() {
+			$crate::__log__($crate::Level::Info, None, module_path!(), format_args!($($tokens)*));
+		}
+	}};
+}
+
+/// Prints an informational line beneath the running tasks, like `info!`,
+/// but at most once per interval from this call site, such as in
+/// `info_throttled!(Duration::from_secs(5), "...")`. Lines from the same call
+/// site within the interval are dropped.
+#[macro_export]
+macro_rules! info_throttled {
+	($interval:expr, target: $target:expr, $($tokens:tt)*) => {{
+		static THROTTLE: $crate::__Throttle__ = $crate::__Throttle__::new();
+
+		if THROTTLE.ready($interval) {
+			$crate::__log__($crate::Level::Info, Some($target), module_path!(), format_args!($($tokens)*));
+		}
+	}};
+	($interval:expr, $($tokens:tt)*) => {{
+		static THROTTLE: $crate::__Throttle__ = $crate::__Throttle__::new();
+
+		if THROTTLE.ready($interval) {
+			$crate::__log__($crate::Level::Info, None, module_path!(), format_args!($($tokens)*));
+		}
+	}};
+}
+
+/// Prints a yellow warning line beneath the running tasks, but only the
+/// first time this call site is reached. Unlike `warn!`, this doesn't
+/// end the current task.
+#[macro_export]
+macro_rules! warn_once {
+	(target: $target:expr, $($tokens:tt)*) => {{
+		static ONCE: $crate::__Once__ = $crate::__Once__::new();
+
+		if ONCE.first() {
+			$crate::__log__($crate::Level::Warn, Some($target), module_path!(), format_args!($($tokens)*));
+		}
+	}};
+	($($tokens:tt)*) => {{
+		static ONCE: $crate::__Once__ = $crate::__Once__::new();
+
+		if ONCE.first() {
+			$crate::__log__($crate::Level::Warn, None, module_path!(), format_args!($($tokens)*));
+		}
+	}};
+}
+
+/// Prints a yellow warning line beneath the running tasks, but at most
+/// once per interval from this call site, such as in
+/// `warn_throttled!(Duration::from_secs(5), "...")`. Lines from the same call
+/// site within the interval are dropped.
+#[macro_export]
+macro_rules! warn_throttled {
+	($interval:expr, target: $target:expr, $($tokens:tt)*) => {{
+		static THROTTLE: $crate::__Throttle__ = $crate::__Throttle__::new();
+
+		if THROTTLE.ready($interval) {
+			$crate::__log__($crate::Level::Warn, Some($target), module_path!(), format_args!($($tokens)*));
+		}
+	}};
+	($interval:expr, $($tokens:tt)*) => {{
+		static THROTTLE: $crate::__Throttle__ = $crate::__Throttle__::new();
+
+		if THROTTLE.ready($interval) {
+			$crate::__log__($crate::Level::Warn, None, module_path!(), format_args!($($tokens)*));
+		}
+	}};
+}
+
+#[doc(hidden)]
+pub fn __start_task__(target: Option<&str>, message: Arguments) {
+	begin_task(target, None, None, start_message(target, message));
+}
+
+#[doc(hidden)]
+pub fn __start_with_icon__(target: Option<&str>, icon: Arguments, message: Arguments) {
+	let icon = start_message(target, icon);
+	begin_task(target, Some(icon), None, start_message(target, message));
+}
+
+#[doc(hidden)]
+pub fn __start_group__(target: Option<&str>, children: usize, message: Arguments) {
+	begin_task(target, None, Some(children), start_message(target, message));
+}
+
+/// Formats the message of a task started for `target`, unless the task
+/// is sure to be hidden, in which case nothing would show it and it is
+/// left empty. Whether it is nested in a hidden task isn't known until
+/// the tree is locked, and formatting then could deadlock on a message
+/// that starts tasks itself, so such a task's message is still formatted.
+fn start_message(target: Option<&str>, message: Arguments) -> String {
+	if initialized() && target.is_none_or(level::task_enabled) {
+		message.to_string()
+	} else {
+		String::new()
+	}
+}
+
+/// Formats and cleans the message a task ends with, unless the application
+/// has yet to initialize jeflog, in which case every task is hidden and it
+/// is left empty.
+fn end_message(message: Arguments) -> String {
+	if initialized() {
+		style::clean(&message.to_string())
+	} else {
+		String::new()
+	}
+}
+
+/// Who ends a task, which also decides what it is nested under.
+enum Owner {
+	/// The thread that started it, under the task enclosing that thread.
+	Thread,
+	/// Whoever holds its id, under the task with the given id if it is
+	/// still running. Such a task is never ended by an ending macro.
+	Detached(Option<u64>),
+	/// Whoever holds its id, like a detached task, but under no task at
+	/// all, and shown apart from the tree.
+	Background,
+}
+
+/// Begins a task, shown with `icon` if it is given, which ends by itself
+/// once `group` subtasks have ended if it is given.
+fn begin_task(target: Option<&str>, icon: Option<String>, group: Option<usize>, message: String) {
+	begin_owned(target, icon, group, message, Owner::Thread);
+}
+
+/// Begins a task owned by this thread, just as [`task!`] does, returning
+/// its id so that it can be ended by id whatever was started inside it.
+pub(crate) fn begin_on_thread(message: String) -> u64 {
+	begin_owned(None, None, None, message, Owner::Thread)
+}
+
+/// Begins a task that is only ended through its id, which is returned,
+/// nested under the task with id `parent` if it is still running.
+pub(crate) fn begin_detached(parent: Option<u64>, message: String) -> u64 {
+	begin_owned(None, None, None, message, Owner::Detached(parent))
+}
+
+/// Begins a task that runs in the background, as described by
+/// [`background`](crate::background), returning its id.
+pub(crate) fn begin_background(message: String) -> u64 {
+	begin_owned(None, None, None, message, Owner::Background)
+}
+
+/// Begins a task owned by `owner`, returning its id.
+fn begin_owned(target: Option<&str>, icon: Option<String>, group: Option<usize>, message: String, owner: Owner) -> u64 {
+	// a lock poisoned by a panic elsewhere is recovered rather than
+	// passing the panic on, so that one thread can't take down the
+	// logging of the rest
+	let mut tasks = TASKS.lock().recover();
+	let message = style::clean(&message);
+
+	let parent = match owner {
+		Owner::Thread => enclosing(&tasks),
+		Owner::Detached(parent) => parent.and_then(|id| tasks.iter().position(|task| task.id == id)),
+		Owner::Background => None,
+	};
+
+	if let Some(parent) = parent {
+		touch(&mut tasks, parent);
+	}
+
+	// a hidden task hides all of its subtasks as well, and tasks started
+	// before the application has initialized jeflog are only recorded
+	let hidden = !initialized()
+		|| parent.is_some_and(|parent| tasks[parent].hidden)
+		|| target.is_some_and(|target| !level::task_enabled(target));
+
+	let shown_parent = shown_parent(&tasks, parent);
+
+	let task = Task {
+		id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+		parent: parent.map(|parent| tasks[parent].id),
+		depth: shown_parent.map_or(0, |parent| tasks[parent].depth + 1),
+		owner: match owner {
+			Owner::Thread => Some(thread::current().id()),
+			Owner::Detached(_) | Owner::Background => None,
+		},
+		row_offset: 0,
+		rows: 1,
+		last_child_offset: None,
+		summary_offset: None,
+		interrupted: false,
+		sibling_offset: None,
+		parent_interrupted: false,
+		collapsed: false,
+		unshown: 0,
+		started: Instant::now(),
+		cpu_started: time::cpu_time(),
+		message,
+		target: target.map(str::to_owned),
+		icon: icon.map(|icon| style::sanitize(&icon)),
+		hidden,
+		progress: None,
+		children: Vec::new(),
+		failed_descendant: false,
+		propagation: None,
+		group,
+		ended: Vec::new(),
+		held: Vec::new(),
+		output: Vec::new(),
+		omitted: 0,
+		delta: String::new(),
+		section: None,
+		shown_elapsed: 0,
+		active: Instant::now(),
+		stalled: false,
+		token: CancelToken::default(),
+		note: String::new(),
+		background: matches!(owner, Owner::Background),
+	};
+
+	let id = task.id;
+	open(&mut tasks, task, parent, shown_parent);
+	id
+}
+
+/// Shows `task` starting, nested under the task at index `parent` and drawn
+/// beneath the task at index `shown_parent`, then adds it to `tasks`.
+fn open(tasks: &mut Vec<Task>, mut task: Task, parent: Option<usize>, shown_parent: Option<usize>) {
+	let hidden = task.hidden;
+
+	#[cfg(feature = "metrics")]
+	if !hidden {
+		monitor::started(&task.message);
+	}
+
+	watch();
+
+	if !hidden {
+		events::publish(|| task.node().start(task.started.since_origin(), format::plain(&task.message)));
+	}
+
+	// past the limit on running subtasks, a subtask is only counted in
+	// its parent's summary, along with any subtasks of its own
+	if !hidden && format::current() == Format::Interactive {
+		if let Some(parent) = parent {
+			let limit = CONFIG.read().recover().max_subtasks;
+
+			if tasks[parent].collapsed || limit.is_some_and(|limit| shown_subtasks(tasks, parent) >= limit) {
+				let collapsed = tasks[parent].collapsed;
+
+				tasks.push(Task { collapsed: true, ..task });
+
+				if !collapsed {
+					format::interactive::summarize(tasks, parent);
+				}
+
+				update_indicators(tasks);
+				return;
+			}
+		}
+	}
+
+	if !hidden {
+		// a task starting a tree decides how the tree is laid out
+		task.delta = if narrow(tree_empty(tasks)) { String::new() } else { time::delta() };
+		let label = task.label() + &task.delta;
+
+		// tasks printed as lines line up beneath those of a parent process
+		let depth = task.depth + format::base_depth();
+
+		// the titles of groups, sections, and blocks are left without the
+		// delta, which would keep a block from being closed by its name
+		match format::current() {
+			// background tasks are drawn once they're among the rest
+			Format::Interactive if task.background => {},
+			Format::Interactive => format::interactive::start(tasks, shown_parent, &mut task, &label),
+			Format::Sequential => format::sequential::start(depth, &label),
+			Format::GitHub => format::github::start(depth, &task.label(), &label),
+			Format::GitLab => task.section = format::gitlab::start(depth, &task.label(), &label),
+			Format::TeamCity => format::teamcity::start(&task.label()),
+			Format::Azure => format::azure::start(depth, &task.label(), &label),
+			Format::Accessible => format::accessible::start(depth, &task.label()),
+			Format::Porcelain => format::porcelain::start(depth, &task.label()),
+			Format::Console => format::console::start(depth, &label),
+			Format::Json => {},
+		}
+	}
+
+	let drawn_apart = task.background && !hidden && format::current() == Format::Interactive;
+	tasks.push(task);
+
+	if drawn_apart {
+		format::interactive::status(tasks);
+	}
+
+	update_indicators(tasks);
+}
+
+#[doc(hidden)]
+pub fn __end_task__(status: Status, message: Arguments) -> Status {
+	end_current(status, end_message(message))
+}
+
+#[doc(hidden)]
+pub fn __pass_quiet__(message: Option<Arguments>) -> Status {
+	let message = match message {
+		Some(message) => end_message(message),
+		None => {
+			let tasks = TASKS.lock().recover();
+			current(&tasks).map_or_else(String::new, |index| tasks[index].message.clone())
+		},
+	};
+
+	end_task_at(current, Status::Pass, message, None, &[], true)
+}
+
+#[doc(hidden)]
+pub fn __fail_with_hint__(hint: Arguments, message: Arguments) -> Status {
+	end_task_at(current, Status::Fail, end_message(message), Some(end_message(hint)), &[], false)
+}
+
+#[doc(hidden)]
+pub fn __fail_with_diff__(expected: &str, actual: &str, message: Arguments) -> Status {
+	// a hidden task's diff would go unseen
+	if !initialized() {
+		return end_current(Status::Fail, String::new());
+	}
+
+	// each line is cleaned on its own, since sanitizing escapes newlines
+	let clean = |text: &str| {
+		text.lines()
+			.map(|line| style::sanitize(&redact::apply(line)))
+			.collect::<Vec<_>>()
+			.join("\n")
+	};
+
+	let diff = diff::unified(&clean(expected), &clean(actual));
+	end_task_at(current, Status::Fail, end_message(message), None, &diff, false)
+}
+
+/// Begins a task or subtask with a spinner, just as [`task!`] does,
+/// for callers that can't use macros, such as code generators and
+/// bindings to other languages.
+pub fn start_task(message: impl Display) {
+	__start_task__(None, format_args!("{message}"));
+}
+
+/// Ends the innermost task started by this thread with the given status
+/// and message, just as [`pass!`], [`warn!`], [`fail!`], and [`cancel!`] do,
+/// returning the status it ended with.
+pub fn end_task(status: Status, message: impl Display) -> Status {
+	__end_task__(status, format_args!("{message}"))
+}
+
+/// Ends the innermost task started by this thread with a message
+/// that has already been redacted, sanitized, and styled.
+fn end_current(status: Status, message: String) -> Status {
+	end_task_at(current, status, message, None, &[], false)
+}
+
+/// Ends the task with the given id, if it is still running.
+pub(crate) fn end_task_by_id(id: u64, status: Status, message: String) {
+	end_task_at(|tasks| tasks.iter().position(|task| task.id == id), status, message, None, &[], false);
+}
+
+/// Cancels every task still running beneath the task with the given id,
+/// innermost first, such as those left open by work that returned early
+/// or panicked inside them.
+pub(crate) fn cancel_subtasks(id: u64) {
+	fn within<'a>(tasks: &'a [Task], mut task: &'a Task, id: u64) -> bool {
+		while let Some(parent) = task.parent {
+			if parent == id {
+				return true;
+			}
+
+			let Some(outer) = tasks.iter().find(|task| task.id == parent) else {
+				return false;
+			};
+
+			task = outer;
+		}
+
+		false
+	}
+
+	let innermost = || {
+		let tasks = TASKS.lock().recover();
+
+		tasks.iter()
+			.rev()
+			.find(|task| within(&tasks, task, id))
+			.map(|task| (task.id, task.message.clone()))
+	};
+
+	// the lock must be released before the task is ended, which takes it again
+	while let Some((subtask, message)) = innermost() {
+		end_task_by_id(subtask, Status::Cancel, message);
+	}
+}
+
+/// Ends the task with the given id just as [`end_task_by_id`] does, with
+/// a hint shown beneath it.
+pub(crate) fn end_task_by_id_with_hint(id: u64, status: Status, message: String, hint: Option<String>) {
+	end_task_at(|tasks| tasks.iter().position(|task| task.id == id), status, message, hint, &[], false);
+}
+
+/// Reopens the task with the given id if it has ended, as described by
+/// [`TaskHandle::reopen`](crate::TaskHandle::reopen).
+pub(crate) fn reopen_task(id: u64) -> bool {
+	let mut tasks = TASKS.lock().recover();
+	let mut ended = ENDED.lock().recover();
+	ended.retain(|(task, _)| task.token.is_shared());
+
+	let Some(index) = ended.iter().position(|(task, _)| task.id == id) else {
+		return false;
+	};
+
+	let parent = match ended[index].0.parent {
+		Some(parent) => match tasks.iter().position(|task| task.id == parent) {
+			Some(parent) => Some(parent),
+			None => return false,
+		},
+		None => None,
+	};
+
+	let (mut task, drawn) = ended.remove(index);
+	drop(ended);
+
+	if let Some(parent) = parent {
+		touch(&mut tasks, parent);
+	}
+
+	task.started = Instant::now();
+	task.cpu_started = time::cpu_time();
+	task.active = Instant::now();
+	task.shown_elapsed = 0;
+	task.stalled = false;
+	task.progress = None;
+	task.note.clear();
+	task.failed_descendant = false;
+	task.held.clear();
+	task.output.clear();
+	task.omitted = 0;
+	task.token.reset();
+
+	// the task's line can only be drawn on again if no line has been
+	// written since, which would have moved it up by a row
+	let in_place = format::current() == Format::Interactive
+		&& !task.hidden
+		&& !task.collapsed
+		&& drawn == Some(output::lines());
+
+	if !in_place {
+		task.row_offset = 0;
+		task.last_child_offset = None;
+		task.summary_offset = None;
+		task.interrupted = false;
+		task.collapsed = false;
+		task.delta.clear();
+
+		let shown_parent = shown_parent(&tasks, parent);
+		task.depth = shown_parent.map_or(0, |parent| tasks[parent].depth + 1);
+		open(&mut tasks, task, parent, shown_parent);
+		return true;
+	}
+
+	task.delta = if narrow(false) { String::new() } else { time::delta() };
+
+	#[cfg(feature = "metrics")]
+	monitor::started(&task.message);
+
+	events::publish(|| task.node().start(task.started.since_origin(), format::plain(&task.message)));
+	watch();
+
+	tasks.push(task);
+	let index = tasks.len() - 1;
+	format::interactive::reopen(&mut tasks, index);
+	update_indicators(&tasks);
+	true
+}
+
+/// Ends the task that `find` picks out of the running tasks, showing the
+/// lines of `details` beneath it, followed by `hint` if it is given. If
+/// `quiet`, a task that passes without anything beneath it is removed from
+/// the tree instead, as [`pass_quiet!`] describes.
+///
+/// Returns the status the task ended with, which may differ from `status`
+/// if a failure of one of its subtasks was propagated to it.
+fn end_task_at(
+	find: impl FnOnce(&[Task]) -> Option<usize>,
+	mut status: Status,
+	message: String,
+	hint: Option<String>,
+	details: &[String],
+	quiet: bool,
+) -> Status {
+	let mut tasks = TASKS.lock().recover();
+	let symbol = status.symbol();
+	let mut elapsed = None;
+	let mut completed_group = None;
+
+	if let Some(mut task) = find(&tasks).map(|index| tasks.remove(index)) {
+		// a group ending by itself only warns about what its subtasks did
+		let completes_group = task.group.is_some_and(|count| task.ended.len() >= count);
+
+		if status == Status::Warn && !task.hidden && !completes_group {
+			session::warned(&tasks, &task, &message);
+		}
+
+		let propagation = task.propagation.unwrap_or(CONFIG.read().recover().propagation);
+		status = propagation.settle(status, task.failed_descendant);
+
+		let duration = task.started.elapsed();
+		elapsed = Some(duration);
+
+		#[cfg(feature = "metrics")]
+		if !task.hidden {
+			monitor::ended(&task.message, status, duration);
+		}
+
+		let cpu_time = task.cpu_started
+			.zip(time::cpu_time())
+			.map(|(start, end)| end.saturating_sub(start));
+
+		let suffix = if CONFIG.read().recover().durations && !narrow(false) {
+			format!(" {}", time::colored(duration, cpu_time))
+		} else {
+			String::new()
+		};
+
+		let line = format!("{}{message}{suffix}", task.prefix());
+
+		let mut drawn = None;
+
+		if !task.hidden {
+			events::publish(|| task.node().end(Instant::now().since_origin(), status, duration, format::plain(&message)));
+
+			let line = if narrow(false) { line } else { line + &time::delta() };
+
+			let depth = task.depth + format::base_depth();
+
+			let removed = quiet
+				&& status == Status::Pass
+				&& !task.background
+				&& task.children.is_empty()
+				&& task.output.is_empty()
+				&& task.omitted == 0
+				&& format::current() == Format::Interactive
+				&& format::interactive::remove(&mut tasks, &mut task);
+
+			match format::current() {
+				Format::Interactive if removed => {},
+				Format::Interactive if task.background => format::interactive::end_background(&mut tasks, status, &line),
+				Format::Interactive => format::interactive::end(&mut tasks, &task, status, &line),
+				Format::Sequential => format::sequential::end(depth, status, &line),
+				Format::GitHub => format::github::end(depth, status, &line),
+				Format::GitLab => format::gitlab::end(depth, task.section, status, &line),
+				Format::TeamCity => format::teamcity::end(depth, &task.label(), status, &line),
+				Format::Azure => format::azure::end(depth, status, &line),
+				Format::Accessible => format::accessible::end(depth, &task.label(), status, &message, duration),
+				Format::Porcelain => format::porcelain::end(depth, status, duration, &message),
+				Format::Console => format::console::end(depth, status, &line),
+				Format::Json => {},
+			}
+
+			// a removed line can't be drawn on again
+			drawn = (!removed).then(output::lines);
+
+			let parent = tasks.iter().position(|parent| Some(parent.id) == task.parent);
+
+			// output held back until now is only worth showing on failure
+			if status == Status::Fail {
+				for text in &task.held {
+					write_line(&mut tasks, parent, task.depth + 1, text);
+				}
+			}
+
+			write_block(&mut tasks, parent, task.depth + 1, details);
+
+			if let Some(hint) = &hint {
+				write_line(&mut tasks, parent, task.depth + 1, &hint_line(hint));
+			}
+
+			// the parent may have room for one of its collapsed subtasks now
+			if let Some(parent) = parent.filter(|_| format::current() == Format::Interactive) {
+				if task.collapsed && matches!(status, Status::Pass | Status::Cancel) {
+					tasks[parent].unshown += 1;
+				}
+
+				if !tasks[parent].collapsed {
+					promote(&mut tasks, parent);
+					format::interactive::summarize(&mut tasks, parent);
+				}
+			}
+		}
+
+		// hidden tasks aren't recorded, since their messages may never
+		// have been formatted
+		let record = (!task.hidden).then(|| Record {
+			message: task.message.clone(),
+			end_message: message.clone(),
+			target: task.target.clone(),
+			status,
+			started: task.started,
+			duration,
+			cpu_time,
+			hint,
+			output: kept_output(&mut task),
+			children: mem::take(&mut task.children),
+		});
+
+		// file the completed task under its parent, or at the top level
+		// if it has none, or its parent has already ended
+		let parent = tasks.iter_mut().find(|parent| Some(parent.id) == task.parent);
+
+		match parent {
+			Some(parent) => {
+				parent.failed_descendant |= node::fails_parent(status, task.failed_descendant);
+				parent.ended.push(status);
+				parent.children.extend(record);
+
+				if parent.group.is_some_and(|count| parent.ended.len() >= count) {
+					let status = group_status(&parent.ended);
+					completed_group = Some((parent.id, status, parent.message.clone()));
+				}
+			},
+			None => SESSION.lock().recover().tasks.extend(record),
+		}
+
+		if status == Status::Fail {
+			taskbar::mark_failed();
+		}
+
+		update_indicators(&tasks);
+
+		if task.token.is_shared() {
+			let mut ended = ENDED.lock().recover();
+			ended.retain(|(task, _)| task.token.is_shared());
+			ended.push((task, drawn));
+		}
+	} else if initialized() {
+		// if no task is running, just print the symbol and message
+		if format::current() == Format::Accessible {
+			format::accessible::report(status, &message);
+		} else if format::current() == Format::Porcelain {
+			format::porcelain::end(0, status, Duration::ZERO, &message);
+		} else if format::current() == Format::Json {
+			format::json::report(status, &message);
+		} else {
+			let mut frame = Frame::new();
+			frame.print(format!("{symbol} {message}"));
+			frame.newline();
+			frame.emit();
+		}
+
+		write_block(&mut tasks, None, 0, details);
+
+		if let Some(hint) = &hint {
+			write_line(&mut tasks, None, 0, &hint_line(hint));
+		}
+	}
+
+	drop(tasks);
+
+	if initialized() {
+		alert(status, elapsed, &message);
+	}
+
+	// the group may have been started by another thread
+	if let Some((id, status, message)) = completed_group {
+		end_task_by_id(id, status, message);
+	}
+
+	if status == Status::Fail && CONFIG.read().recover().exit_on_fail {
+		finish();
+		process::exit(exit_code());
+	}
+
+	status
+}
+
+/// The line a failure's hint is shown on, beneath the task.
+fn hint_line(hint: &str) -> String {
+	format!("\x1b[36m{}\x1b[0m", locale::text("hint: {hint}", &[("hint", &hint)]))
+}
+
+/// How a group ends given how its subtasks ended: passing only if all
+/// of them passed, and failing if any of them failed.
+fn group_status(ended: &[Status]) -> Status {
+	ended.iter().fold(Status::Pass, |group, &child| match (group, child) {
+		(Status::Fail, _) | (_, Status::Fail) => Status::Fail,
+		(Status::Pass, Status::Pass) => Status::Pass,
+		_ => Status::Warn,
+	})
+}
+
+/// Starts the next run of a watch mode or other tool that does the same
+/// work over and over, erasing the lines printed since the last call so
+/// that the next run's tree is drawn where the last run's tree was, rather
+/// than beneath an ever growing history. The first call only marks where
+/// the first run starts.
+///
+/// Lines are only erased in the interactive format, and this does nothing
+/// while any task is running other than in the background. Lines written
+/// to the terminal other than through jeflog, such as by `println!` rather
+/// than by [`stdout`](crate::stdout), aren't known and so aren't erased.
+pub fn new_generation() {
+	let tasks = TASKS.lock().recover();
+
+	if !tree_empty(&tasks) {
+		return;
+	}
+
+	let mut generation = GENERATION.lock().recover();
+
+	if let Some(started) = generation.filter(|_| initialized() && format::current() == Format::Interactive) {
+		format::interactive::erase((output::lines() - started) as usize);
+
+		// the lines that ended tasks were drawn on are gone
+		for (_, drawn) in ENDED.lock().recover().iter_mut() {
+			*drawn = None;
+		}
+	}
+
+	*generation = Some(output::lines());
+}
+
+/// Draws the running tasks again beneath the cursor, for when something
+/// else has written over the tree, such as an editor that was spawned
+/// without leaving the screen as it found it.
+///
+/// The tree is drawn from the start of the cursor's row, as a new tree
+/// with only the running tasks, since lines printed between them and the
+/// tasks that have already ended aren't kept. This only does anything in
+/// the interactive format.
+pub fn repaint() {
+	let mut tasks = TASKS.lock().recover();
+
+	if initialized() && format::current() == Format::Interactive && !tasks.is_empty() {
+		format::interactive::repaint(&mut tasks);
+	}
+}
+
+/// Ends every task that is still running as cancelled, innermost first,
+/// then waits for the spinner thread to stop and resets the terminal's
+/// colors and cursor.
+///
+/// This should be called before exiting or replacing the process (as with
+/// `exec`), so that no partially drawn output is left behind. Tasks started
+/// by other threads while this runs will delay it until they end.
+pub fn finish() {
+	end_all(Status::Cancel);
+
+	format::interactive::stop();
+
+	if CONFIG.read().recover().digest {
+		session::digest();
+	}
+
+	// scripts reading the run get its totals last, after every other line
+	if initialized() && !SUMMARIZED.swap(true, Ordering::Relaxed) {
+		match format::current() {
+			Format::Json => format::json::summary(&SESSION.lock().recover()),
+			Format::Porcelain => format::porcelain::summary(&SESSION.lock().recover()),
+			_ => {},
+		}
+	}
+
+	if initialized() && format::current() == Format::Interactive {
+		// \x1b[0m   : reset colors and styles
+		// \x1b[?25h : show the cursor
+		output::emit("\x1b[0m\x1b[?25h");
+	}
+}
+
+/// Returns an exit status for the process that reflects how its tasks
+/// ended: 1 if any of them failed, the code set by [`set_warning_exit_code`]
+/// if any of them warned, and 0 otherwise, as in
+/// `std::process::exit(jeflog::exit_code())`.
+///
+/// Only completed tasks are considered, and cancelled tasks count as
+/// neither passing nor failing.
+pub fn exit_code() -> i32 {
+	fn worst(records: &[Record]) -> Status {
+		records.iter()
+			.flat_map(|record| [record.status, worst(&record.children)])
+			.fold(Status::Pass, |worst, status| match (worst, status) {
+				(Status::Fail, _) | (_, Status::Fail) => Status::Fail,
+				(Status::Warn, _) | (_, Status::Warn) => Status::Warn,
+				_ => Status::Pass,
+			})
+	}
+
+	match worst(&SESSION.lock().recover().tasks) {
+		Status::Fail => 1,
+		Status::Warn => CONFIG.read().recover().warning_exit_code,
+		_ => 0,
+	}
+}
+
+/// Sets the exit status that [`exit_code`] returns when some task warned
+/// but none failed. This is 0 by default, so warnings don't fail a run.
+pub fn set_warning_exit_code(code: i32) {
+	CONFIG.write().recover().warning_exit_code = code;
+}
+
+/// Sets whether the process exits as soon as any task fails, after
+/// finishing as [`finish`] does, with the status given by [`exit_code`].
+pub fn set_exit_on_fail(enabled: bool) {
+	CONFIG.write().recover().exit_on_fail = enabled;
+}
+
+/// Counts the running subtasks of the task at index `parent` that
+/// are shown on a row of their own.
+fn shown_subtasks(tasks: &[Task], parent: usize) -> usize {
+	let id = tasks[parent].id;
+
+	tasks.iter()
+		.filter(|task| task.parent == Some(id) && !task.hidden && !task.collapsed)
+		.count()
+}
+
+/// Draws the oldest collapsed subtask of the task at index `parent` on
+/// a row of its own, if the parent has room for another shown subtask.
+fn promote(tasks: &mut Vec<Task>, parent: usize) {
+	let Some(limit) = CONFIG.read().recover().max_subtasks else {
+		return;
+	};
+
+	if shown_subtasks(tasks, parent) >= limit {
+		return;
+	}
+
+	let id = tasks[parent].id;
+
+	let Some(index) = tasks.iter().position(|task| task.parent == Some(id) && task.collapsed) else {
+		return;
+	};
+
+	// the subtask is taken out while its row is drawn, just as a new
+	// task would be, and put back in place so it stays after its parent
+	let mut task = tasks.remove(index);
+	let label = task.label();
+	format::interactive::start(tasks, Some(parent), &mut task, &label);
+	task.collapsed = false;
+	task.row_offset = 0;
+	tasks.insert(index, task);
+
+	if tasks[index].progress.is_some() {
+		format::interactive::redraw(tasks, index);
+	}
+}
+
+/// Puts one shown subtask of each task with collapsed subtasks back into
+/// its summary, and shows the oldest collapsed subtask in its place, so
+/// that every subtask is seen now and then while more are running than
+/// are shown. The subtask put back is the one shown longest that has
+/// nothing beneath it, since its own rows are all that must be erased.
+pub(crate) fn rotate(tasks: &mut Vec<Task>) {
+	let mut parents = tasks.iter()
+		.filter(|task| task.collapsed && !task.hidden)
+		.filter_map(|task| task.parent)
+		.collect::<Vec<_>>();
+
+	parents.sort_unstable();
+	parents.dedup();
+
+	for id in parents {
+		let Some(parent) = tasks.iter().position(|task| task.id == id && !task.collapsed) else {
+			continue;
+		};
+
+		let bare = |task: &Task| {
+			task.parent == Some(id)
+				&& !task.hidden
+				&& !task.collapsed
+				&& !task.background
+				&& task.children.is_empty()
+				&& task.output.is_empty()
+				&& task.omitted == 0
+				&& !tasks.iter().any(|other| other.parent == Some(task.id))
+		};
+
+		let Some(index) = tasks.iter().position(bare) else {
+			continue;
+		};
+
+		let mut task = tasks.remove(index);
+
+		if !format::interactive::take_out(tasks, &mut task) {
+			tasks.insert(index, task);
+			continue;
+		}
+
+		// it waits behind every other collapsed subtask to be shown again
+		task.collapsed = true;
+		task.row_offset = 0;
+		task.last_child_offset = None;
+		task.summary_offset = None;
+		task.sibling_offset = None;
+		task.interrupted = false;
+		task.parent_interrupted = false;
+		tasks.push(task);
+
+		promote(tasks, parent);
+	}
+}
+
+/// Ends every running task with the same status, innermost first,
+/// each with the message it was started with.
+fn end_all(status: Status) {
+	loop {
+		// the lock must be released before the task is ended, which takes it again
+		let Some((id, message)) = TASKS.lock().recover()
+			.last()
+			.map(|task| (task.id, task.message.clone()))
+		else {
+			break;
+		};
+
+		end_task_by_id(id, status, message);
+	}
+}
+
+#[doc(hidden)]
+pub fn __fail_all__(message: Arguments) -> Status {
+	end_current(Status::Fail, end_message(message));
+	end_all(Status::Fail);
+	Status::Fail
+}
+
+/// Calls [`finish`] when dropped, created by [`init`].
+#[must_use = "the terminal is restored when the guard is dropped"]
+#[derive(Debug)]
+pub struct Guard {
+	_private: (),
+}
+
+impl Drop for Guard {
+	fn drop(&mut self) {
+		finish();
+	}
+}
+
+/// Initializes jeflog for the application, which shows tasks and lines
+/// from then on, and returns a guard that calls [`finish`] when it is
+/// dropped. The guard is meant to be held for the whole of `main`, as in
+/// `let _guard = jeflog::init();`.
+///
+/// Until this is called, nothing is written to the terminal or recorded
+/// in the [`session`](crate::session()), and messages aren't even formatted.
+/// This lets libraries use jeflog freely, at next to no cost, with their
+/// output only shown if the application using them chooses to show it.
+/// Tasks started before this stay hidden even once it is called.
+///
+/// Since the guard is dropped on early returns and while unwinding from a
+/// panic, running tasks are always ended and the terminal is left usable.
+/// It is not dropped by [`std::process::exit`], however.
+pub fn init() -> Guard {
+	// detect the format now, before anything else could be printed
+	format::current();
+	enable();
+
+	Guard { _private: () }
+}
+
+/// Starts showing tasks and lines, as [`init`] does.
+pub(crate) fn enable() {
+	INITIALIZED.store(true, Ordering::Relaxed);
+}
+
+/// Returns whether the application has initialized jeflog, so that
+/// tasks and lines are shown.
+pub(crate) fn initialized() -> bool {
+	INITIALIZED.load(Ordering::Relaxed)
+}
+
+/// Reports how far along the most recently created task is, as
+/// `current` out of `total` units of work.
+///
+/// In the interactive format, this is drawn as a progress bar after
+/// the task's message. It has no effect if no task is running.
+pub fn progress(current: u64, total: u64) {
+	let mut tasks = TASKS.lock().recover();
+
+	if let Some(index) = self::current(&tasks) {
+		report_progress(&mut tasks, index, current, total, None);
+	}
+}
+
+/// Reports how far along the task with the given id is, just as
+/// [`progress`] does, along with a note to show after its progress bar.
+pub(crate) fn progress_of(id: u64, current: u64, total: u64, note: String) {
+	let mut tasks = TASKS.lock().recover();
+
+	if let Some(index) = tasks.iter().position(|task| task.id == id) {
+		report_progress(&mut tasks, index, current, total, Some(note));
+	}
+}
+
+/// Replaces the note shown after the message of the task with the given
+/// id, without reporting any progress.
+pub(crate) fn note_of(id: u64, note: String) {
+	let mut tasks = TASKS.lock().recover();
+
+	let Some(index) = tasks.iter().position(|task| task.id == id) else {
+		return;
+	};
+
+	let task = &mut tasks[index];
+
+	if task.note == note {
+		return;
+	}
+
+	task.note = note;
+
+	if !task.hidden && !task.collapsed && format::current() == Format::Interactive {
+		format::interactive::redraw(&mut tasks, index);
+	}
+}
+
+/// Records the progress of the task at `index` in `tasks`, replacing its
+/// note if one is given, and draws its progress bar.
+fn report_progress(tasks: &mut [Task], index: usize, current: u64, total: u64, note: Option<String>) {
+	touch(tasks, index);
+
+	let task = &mut tasks[index];
+	task.progress = Some((current.min(total), total));
+
+	if let Some(note) = note {
+		task.note = note;
+	}
+
+	if task.hidden {
+		return;
+	}
+
+	events::publish(|| task.node().progress(Instant::now().since_origin(), current.min(total), total));
+
+	// a collapsed task has no row to draw its progress on
+	if task.collapsed {
+		update_indicators(tasks);
+		return;
+	}
+
+	if format::current() == Format::Interactive {
+		format::interactive::redraw(tasks, index);
+	}
+
+	update_indicators(tasks);
+}
+
+/// Prints a line of text beneath the running tasks, in whichever
+/// way the current format allows.
+pub(crate) fn print_line(text: &str) {
+	if !initialized() {
+		return;
+	}
+
+	let mut tasks = TASKS.lock().recover();
+	let enclosing = enclosing(&tasks);
+	print_within(&mut tasks, enclosing, text);
+}
+
+/// Prints a line of text beneath the task with the given id, or beneath
+/// no task if it isn't running, as [`print_line`] prints one beneath the
+/// task running on this thread.
+pub(crate) fn print_line_in(id: Option<u64>, text: &str) {
+	if !initialized() {
+		return;
+	}
+
+	let mut tasks = TASKS.lock().recover();
+	let enclosing = id.and_then(|id| tasks.iter().position(|task| task.id == id));
+	print_within(&mut tasks, enclosing, text);
+}
+
+/// Prints a line of text beneath the task at index `enclosing`.
+fn print_within(tasks: &mut [Task], enclosing: Option<usize>, text: &str) {
+	if let Some(index) = enclosing {
+		touch(tasks, index);
+		capture(&mut tasks[index], text);
+	}
+
+	// lines printed inside a hidden task are hidden along with it
+	if enclosing.is_some_and(|index| tasks[index].hidden) {
+		return;
+	}
+
+	let depth = enclosing.map_or(0, |index| tasks[index].depth + 1);
+	write_line(tasks, enclosing, depth, text);
+}
+
+#[doc(hidden)]
+pub fn __data__(line: String) {
+	let mut tasks = TASKS.lock().recover();
+
+	if initialized() && format::current() == Format::Interactive && io::stdout().is_terminal() {
+		// data is never collapsed into a line that it repeats
+		format::interactive::forget_text();
+		format::interactive::text(&mut tasks, None, 0, &line);
+		format::interactive::forget_text();
+	} else {
+		output::data(&line);
+	}
+}
+
+/// Prints lines of text beneath the running tasks as a block, just as
+/// [`print_line`] prints one.
+pub(crate) fn print_block(lines: &[String]) {
+	if !initialized() {
+		return;
+	}
+
+	let mut tasks = TASKS.lock().recover();
+	let enclosing = enclosing(&tasks);
+
+	if let Some(index) = enclosing {
+		touch(&mut tasks, index);
+
+		for text in lines {
+			capture(&mut tasks[index], text);
+		}
+	}
+
+	if enclosing.is_some_and(|index| tasks[index].hidden) {
+		return;
+	}
+
+	let depth = enclosing.map_or(0, |index| tasks[index].depth + 1);
+	write_block(&mut tasks, enclosing, depth, lines);
+}
+
+/// Holds a line of text until the task it was printed in ends, printing
+/// it beneath that task only if it fails. Lines printed outside of any
+/// task are printed right away.
+pub(crate) fn hold_line(text: &str) {
+	let mut tasks = TASKS.lock().recover();
+
+	match enclosing(&tasks) {
+		Some(index) => {
+			touch(&mut tasks, index);
+			capture(&mut tasks[index], text);
+			tasks[index].held.push(text.to_owned());
+		},
+		None if initialized() => write_line(&mut tasks, None, 0, text),
+		None => {},
+	}
+}
+
+/// Shows a line of text in a window of the `lines` most recent lines
+/// beneath the task it was printed in, which is erased once that task ends.
+///
+/// Returns `false`, without printing anything, if there is no such window
+/// because no task is running or the format isn't interactive.
+pub(crate) fn tail_line(text: &str, lines: usize) -> bool {
+	let mut tasks = TASKS.lock().recover();
+
+	let Some(index) = enclosing(&tasks) else {
+		return false;
+	};
+
+	touch(&mut tasks, index);
+
+	// lines that can't be tailed are printed some other way, which keeps them
+	if format::current() != Format::Interactive {
+		return false;
+	}
+
+	capture(&mut tasks[index], text);
+
+	if !tasks[index].hidden {
+		events::publish(|| Event::Text {
+			task: Some(tasks[index].id),
+			depth: tasks[index].depth + 1,
+			time: Instant::now().since_origin(),
+			text: format::plain(text),
+		});
+		format::interactive::tail(&mut tasks, index, lines, text);
+	}
+
+	true
+}
+
+/// Keeps a line printed inside `task` for its record, unless jeflog hasn't
+/// been initialized, since the task is then never shown either.
+fn capture(task: &mut Task, text: &str) {
+	if !initialized() {
+		return;
+	}
+
+	task.output.push(format::plain(text));
+
+	// the earliest lines are let go a batch at a time, rather than one
+	// by one, and the rest of those over the limit when the task ends
+	if let Some(limit) = CONFIG.read().recover().max_output {
+		if task.output.len() > limit.saturating_mul(2) {
+			task.omitted += forget_output(&mut task.output, limit);
+		}
+	}
+}
+
+/// Lets go of all but the `limit` most recent lines of `output`, returning
+/// how many it let go.
+fn forget_output(output: &mut Vec<String>, limit: usize) -> usize {
+	let excess = output.len().saturating_sub(limit);
+	output.drain(..excess);
+	excess
+}
+
+/// Takes the lines kept for `task` for its record, led by a line saying
+/// how many were let go, if any were.
+fn kept_output(task: &mut Task) -> Vec<String> {
+	if let Some(limit) = CONFIG.read().recover().max_output {
+		task.omitted += forget_output(&mut task.output, limit);
+	}
+
+	let mut output = mem::take(&mut task.output);
+
+	if task.omitted > 0 {
+		output.insert(0, locale::text("… {count} earlier lines not kept", &[("count", &task.omitted)]));
+	}
+
+	output
+}
+
+/// Notes that the task at index `index` showed signs of life, which
+/// means the tasks it is nested under aren't stalled either.
+fn touch(tasks: &mut [Task], index: usize) {
+	let now = Instant::now();
+	let mut next = Some(tasks[index].id);
+
+	while let Some(task) = next.and_then(|id| tasks.iter_mut().find(|task| task.id == id)) {
+		task.active = now;
+		task.stalled = false;
+		next = task.parent;
+	}
+}
+
+/// Starts a thread that watches the running tasks for any that stall, if
+/// stalls are being looked for and nothing is watching yet, which exits
+/// once no task is running. This must be called while TASKS is locked.
+fn watch() {
+	if cfg!(all(target_family = "wasm", target_os = "unknown")) || CONFIG.read().recover().stall_after.is_none() {
+		return;
+	}
+
+	if WATCHING.swap(true, Ordering::Relaxed) {
+		return;
+	}
+
+	thread::spawn(|| loop {
+		let mut tasks = TASKS.lock().recover();
+		let (after, tick) = {
+			let config = CONFIG.read().recover();
+			(config.stall_after, config.tick)
+		};
+
+		let Some(after) = after.filter(|_| !tasks.is_empty()) else {
+			WATCHING.store(false, Ordering::Relaxed);
+			break;
+		};
+
+		stall(&mut tasks, after);
+		drop(tasks);
+		thread::sleep(tick);
+	});
+}
+
+/// Marks every task in `tasks` that has gone `after` without any activity
+/// as stalled, publishing that it has and warning about it if that is
+/// enabled. Only tasks without running subtasks stall, since a task waiting
+/// on a subtask is only as stalled as that subtask.
+fn stall(tasks: &mut [Task], after: Duration) {
+	let warn = CONFIG.read().recover().stall_warnings;
+
+	for index in 0..tasks.len() {
+		let id = tasks[index].id;
+		let waiting = tasks.iter().any(|task| task.parent == Some(id) && !task.hidden);
+		let task = &mut tasks[index];
+
+		if waiting || task.hidden || task.background || task.stalled || task.active.elapsed() < after {
+			continue;
+		}
+
+		task.stalled = true;
+		events::publish(|| task.node().stall(Instant::now().since_origin(), task.active.elapsed()));
+
+		if warn {
+			let message = locale::text("stalled: no activity for {duration}", &[("duration", &time::format(after))]);
+			let depth = task.depth + 1;
+
+			session::warned(tasks, &tasks[index], &message);
+			write_line(tasks, Some(index), depth, &format!("{} {message}", Status::Warn.symbol()));
+		}
+	}
+}
+
+/// Prints a line of text indented as a subtask `depth` levels deep would
+/// be, beneath the task at index `enclosing` if there is one.
+fn write_line(tasks: &mut [Task], enclosing: Option<usize>, depth: usize, text: &str) {
+	events::publish(|| Event::Text {
+		task: enclosing.map(|index| tasks[index].id),
+		depth,
+		time: Instant::now().since_origin(),
+		text: format::plain(text),
+	});
+
+	match format::current() {
+		Format::Interactive => format::interactive::text(tasks, enclosing, depth, text),
+		Format::Porcelain => format::porcelain::text(depth + format::base_depth(), text),
+		Format::Console => format::console::text(depth + format::base_depth(), text),
+		Format::Json => {},
+		_ => format::sequential::text(depth + format::base_depth(), text),
+	}
+}
+
+/// Prints lines of text just as [`write_line`] does, but as a block
+/// whose lines are never collapsed into one another when they repeat.
+fn write_block(tasks: &mut [Task], enclosing: Option<usize>, depth: usize, lines: &[String]) {
+	for text in lines {
+		write_line(tasks, enclosing, depth, text);
+		format::interactive::forget_text();
+	}
+}
+
+/// Updates the parts of the terminal outside of the task tree
+/// that reflect its state, like the title and taskbar.
+fn update_indicators(tasks: &[Task]) {
+	let tasks = tasks.iter()
+		.filter(|task| !task.hidden)
+		.collect::<Vec<_>>();
+
+	if format::current() == Format::Interactive {
+		title::update(&tasks);
+		taskbar::update(&tasks);
+	}
+}
+
+/// Shows the current top-level task, along with its progress if it or
+/// one of its subtasks reports any, in the terminal's title. The original
+/// title is restored once every task has ended.
+///
+/// This only applies to the interactive format.
+pub fn set_title(enabled: bool) {
+	CONFIG.write().recover().title = enabled;
+}
+
+/// Shows the overall progress of the task tree on the terminal's taskbar
+/// icon, as Windows Terminal and ConEmu support (OSC 9;4). Progress is
+/// taken from the outermost task reporting any, and the icon shows an
+/// error state once any task fails.
+///
+/// This only applies to the interactive format.
+pub fn set_taskbar_progress(enabled: bool) {
+	CONFIG.write().recover().taskbar = enabled;
+}
+
+/// Before each redraw, asks the terminal where its cursor is (with a DSR
+/// query) to notice lines that something else has written in the meantime,
+/// and shifts the task tree to match instead of drawing over those lines.
+///
+/// This is best effort: the terminal's response is read from standard
+/// input, so it should not be enabled while the program is reading from
+/// standard input itself. It only applies to the interactive format.
+#[cfg(feature = "crossterm")]
+pub fn set_reanchor(enabled: bool) {
+	CONFIG.write().recover().reanchor = enabled;
+}
+
+/// Collapses a line of text printed several times in a row into a single
+/// line with a counter, such as `retrying (×12)`, which is updated in place
+/// as more repeats are printed. This is enabled by default, and only
+/// applies to the interactive format.
+pub fn set_collapse_repeats(enabled: bool) {
+	CONFIG.write().recover().collapse_repeats = enabled;
+}
+
+/// Changes how tasks end once a task beneath them has failed, so that a
+/// parent isn't shown as passing when part of its work failed. Tasks that
+/// are cancelled are never changed, and a single task can be made to end
+/// otherwise with [`TaskHandle::set_propagation`](crate::TaskHandle::set_propagation).
+pub fn set_propagation(propagation: Propagation) {
+	CONFIG.write().recover().propagation = propagation;
+}
+
+/// Limits how many running subtasks of a single task are shown at once.
+/// Beyond the limit, subtasks are summarized on one line beneath their
+/// parent, such as `… +17 more running`, and each takes the place of a
+/// shown subtask once one ends. Every few seconds, the subtask shown the
+/// longest is also collapsed again to make room for one that hasn't been
+/// shown, if it has nothing beneath it, so that every subtask is seen now
+/// and then. Collapsed subtasks that warn or fail have their endings
+/// printed, while those that pass are not shown at all.
+///
+/// This only applies to the interactive format. By default, there is no limit.
+pub fn set_max_subtasks(limit: Option<usize>) {
+	CONFIG.write().recover().max_subtasks = limit;
+}
+
+/// Limits how many lines printed inside a single task are kept in its
+/// [`Record`], which is 1000 by default. Only the most recent lines are
+/// kept, since those closest to a failure tend to explain it, after a line
+/// such as `… 250 earlier lines not kept`. `None` keeps every line, which
+/// a long running program that prints a lot may not have the memory for,
+/// since the [`session`](crate::session) keeps every record until it exits.
+///
+/// No lines are kept before jeflog is [initialized](init), so libraries
+/// that use jeflog don't fill the memory of programs that don't.
+pub fn set_max_output(limit: Option<usize>) {
+	CONFIG.write().recover().max_output = limit;
+}
+
+/// Limits how deeply tasks are drawn, counting top-level tasks as depth
+/// 0. Tasks nested deeper than `depth` are drawn at that depth, beneath
+/// the innermost of their ancestors that isn't, so that deep recursion
+/// can't push the tree off the right edge of the screen. Tasks are still
+/// nested as they were started in the [`session`](crate::session()).
+///
+/// By default, there is no limit.
+pub fn set_max_depth(depth: Option<usize>) {
+	CONFIG.write().recover().max_depth = depth;
+}
+
+/// Rings the terminal bell whenever a task fails. The bell only rings in
+/// the formats read in a terminal, which are the interactive, sequential,
+/// and accessible formats, so that it never corrupts output read by scripts.
+pub fn set_bell_on_fail(enabled: bool) {
+	CONFIG.write().recover().bell_on_fail = enabled;
+}
+
+/// Rings the terminal bell whenever a task that ran for at least
+/// `threshold` ends, regardless of how it ended, in the same formats as
+/// [`set_bell_on_fail`]. `None` disables this.
+pub fn set_bell_threshold(threshold: Option<Duration>) {
+	CONFIG.write().recover().bell_threshold = threshold;
+}
+
+/// Sends a desktop notification (OSC 777) along with every bell,
+/// on terminals that support it.
+pub fn set_notifications(enabled: bool) {
+	CONFIG.write().recover().notify = enabled;
+}
+
+/// Appends how long each task took to its ending message, such as
+/// `(1.25s)`. The duration is colored green, yellow, or red depending
+/// on the thresholds set by [`set_duration_thresholds`].
+pub fn set_durations(enabled: bool) {
+	CONFIG.write().recover().durations = enabled;
+}
+
+/// Appends the time since the previous task started or ended to the line
+/// of every task as it starts and ends, such as `+0.42s`, which makes gaps
+/// where nothing was reported easy to spot.
+pub fn set_deltas(enabled: bool) {
+	CONFIG.write().recover().deltas = enabled;
+}
+
+/// Appends the local time of day to the line of every task as it starts
+/// and ends, such as `14:03:07`, in place of the time since the previous
+/// task set by [`set_deltas`], for matching what tasks did against other
+/// logs on the system. The time zone is the system's, or the one named by
+/// the `TZ` environment variable.
+#[cfg(feature = "chrono")]
+pub fn set_local_times(enabled: bool) {
+	CONFIG.write().recover().local_times = enabled;
+}
+
+/// Shows how long each running task has been running so far, such as
+/// `(12.00s)`, dimmed after its message and updated as its spinner turns.
+/// It appears once a task has run for a second, and is written in whole
+/// seconds in the style set by [`set_duration_style`](crate::set_duration_style).
+///
+/// This only applies to the interactive format.
+pub fn set_live_elapsed(enabled: bool) {
+	CONFIG.write().recover().live_elapsed = enabled;
+}
+
+/// Shows a line above the task tree with how long it has been since the
+/// first task started, and how many tasks have passed, warned, and failed
+/// so far, such as `⏱ 02:14 • 37 ✔ 2 ▲ 0 ✘`. It is updated as the spinners
+/// turn, and left in place with the final counts once the tree ends.
+///
+/// This only applies to the interactive format.
+pub fn set_header(enabled: bool) {
+	CONFIG.write().recover().header = enabled;
+}
+
+/// Marks a running task as stalled once it has gone `after` without
+/// reporting progress, printing a line, or starting a subtask, which sends
+/// an [`Event::Stall`] to every sink and, in the interactive format, replaces
+/// its spinner with a red `!` until it does. This helps to notice hung
+/// subprocesses. A task with running subtasks is left to them, and `None`,
+/// the default, never marks a task as stalled.
+pub fn set_stall_after(after: Option<Duration>) {
+	let tasks = TASKS.lock().recover();
+	CONFIG.write().recover().stall_after = after;
+
+	// tasks that are already running are watched as well
+	if !tasks.is_empty() {
+		watch();
+	}
+}
+
+/// Sets whether a task that stalls, as set by [`set_stall_after`], also
+/// gets a warning printed beneath it, which is listed among [`warnings`](crate::warnings)
+/// along with the others.
+pub fn set_stall_warnings(enabled: bool) {
+	CONFIG.write().recover().stall_warnings = enabled;
+}
+
+/// Colors the spinner of each running task, and the connectors leading
+/// to its subtasks, by how deeply it is nested, cycling through the depth
+/// palette, so that deeply nested trees are easier to scan.
+///
+/// This only applies to the interactive format.
+pub fn set_depth_colors(enabled: bool) {
+	CONFIG.write().recover().theme.depth_colors = enabled;
+}
+
+/// Sets the colors cycled through by [`set_depth_colors`], starting from
+/// top-level tasks, which is [`DEPTH_PALETTE`](crate::DEPTH_PALETTE) by default. An empty palette
+/// keeps the usual colors.
+pub fn set_depth_palette(palette: &[Color]) {
+	CONFIG.write().recover().theme.depth_palette = Cow::Owned(palette.to_vec());
+}
+
+/// Shortens paths in task messages and log lines that are wider than
+/// `width` cells, replacing components in their middle with `…`, just as
+/// [`shorten_path`](crate::shorten_path) does. Any word with a path separator in it is taken
+/// to be a path. `None`, the default, leaves paths alone.
+pub fn set_max_path_width(width: Option<usize>) {
+	CONFIG.write().recover().max_path_width = width;
+}
+
+/// Starts each line printed by the sequential, CI, and accessible formats
+/// with the name of the machine and the id of the process that printed it,
+/// as in `[build-3:4121]`, so that logs gathered from agents running in
+/// parallel can be told apart. The interactive format is left alone, and so
+/// is the porcelain format, whose fields are fixed.
+pub fn set_stamp(enabled: bool) {
+	CONFIG.write().recover().stamp = enabled;
+}
+
+/// Sets whether tasks, and everything else jeflog draws, are written to
+/// standard error rather than standard output, which is then left to the
+/// lines printed with [`data!`]. This keeps the output of a tool used in a
+/// pipeline free of escape sequences, while its progress is still shown
+/// on the terminal.
+///
+/// This should be set before [`init`], since the format is chosen by
+/// whether the stream being drawn on is a terminal.
+pub fn set_stderr(enabled: bool) {
+	CONFIG.write().recover().stderr = enabled;
+}
+
+/// Sets whether [`finish`] prints every warning given since it was last
+/// called, along with the tasks they were given in, so that none of them
+/// are missed in a long tree. This is enabled by default.
+pub fn set_warning_digest(enabled: bool) {
+	CONFIG.write().recover().digest = enabled;
+}
+
+/// Sets how long a task may run before its duration is colored
+/// yellow, and how long before it is colored red. By default these
+/// are one and ten seconds respectively.
+pub fn set_duration_thresholds(yellow: Duration, red: Duration) {
+	let mut config = CONFIG.write().recover();
+	config.yellow_after = yellow;
+	config.red_after = red;
+}
+
+/// Sets how long the spinner waits between frames, which is 100ms by
+/// default. A longer tick redraws less often, for slow connections, and a
+/// tick shorter than 10ms is taken as 10ms, since faster redraws would only
+/// keep the terminal busy.
+pub fn set_tick(tick: Duration) {
+	CONFIG.write().recover().tick = tick.max(MIN_TICK);
+}
+
+fn alert(status: Status, elapsed: Option<Duration>, message: &str) {
+	// a bell would corrupt output that is read by scripts and CI systems
+	if !matches!(format::current(), Format::Interactive | Format::Sequential | Format::Accessible) {
+		return;
+	}
+
+	let config = CONFIG.read().recover();
+
+	let failed = config.bell_on_fail && status == Status::Fail;
+	let slow = matches!(
+		(config.bell_threshold, elapsed),
+		(Some(threshold), Some(elapsed)) if elapsed >= threshold
+	);
+
+	if !failed && !slow {
+		return;
+	}
+
+	let mut out = String::from("\x07");
+
+	if config.notify {
+		let title = locale::text(if status == Status::Fail { "Task failed" } else { "Task finished" }, &[]);
+
+		// control characters would terminate the sequence early,
+		// and semicolons would split the body into further fields
+		let body = message.chars()
+			.filter(|&c| !c.is_control() && c != ';')
+			.collect::<String>();
+		let title = title.replace(|c: char| c.is_control() || c == ';', "");
+
+		_ = write!(out, "\x1b]777;notify;{title};{body}\x07");
+	}
+
+	output::emit(&out);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn shares_of_huge_totals_do_not_overflow() {
+		assert_eq!(share(u64::MAX / 2, u64::MAX, 100), 49);
+		assert_eq!(share(u64::MAX, u64::MAX, 20), 20);
+		assert_eq!(share(3, 4, 100), 75);
+		assert_eq!(share(5, 4, 100), 100);
+		assert_eq!(share(0, 0, 20), 20);
+	}
+
+	#[test]
+	fn only_the_most_recent_output_is_kept() {
+		let mut output = (0..5).map(|line| line.to_string()).collect::<Vec<_>>();
+		assert_eq!(forget_output(&mut output, 10), 0);
+		assert_eq!(forget_output(&mut output, 2), 3);
+		assert_eq!(output, ["3", "4"]);
+		assert_eq!(forget_output(&mut output, 0), 2);
+		assert!(output.is_empty());
+	}
+
+	// the format is shared by every test, so those that set it take turns
+	static FORMAT: Mutex<()> = Mutex::new(());
+
+	#[test]
+	fn blocks_are_closed_with_the_names_they_were_opened_with() {
+		let _format = FORMAT.lock().recover();
+		enable();
+		format::set_format(Format::TeamCity);
+		set_deltas(true);
+
+		__start_task__(None, format_args!("opening a block"));
+		__end_task__(Status::Pass, format_args!("closing it"));
+
+		let written = output::captured();
+		let name = |marker| written.lines().find_map(|line| line.strip_prefix(marker));
+
+		assert_eq!(name("##teamcity[blockOpened name="), Some("'opening a block']"));
+		assert_eq!(name("##teamcity[blockClosed name="), Some("'opening a block']"));
+	}
+
+	#[test]
+	fn sections_are_closed_with_the_numbers_they_were_opened_with() {
+		let _format = FORMAT.lock().recover();
+		enable();
+		format::set_format(Format::GitLab);
+
+		// the name follows the marker's timestamp, up to the header
+		let name = |written: &str, marker| {
+			let rest = written.split(marker).nth(1)?.split('\r').next()?;
+			rest.split(':').nth(1).map(str::to_owned)
+		};
+
+		// both are top-level tasks, the second opened while the first runs
+		let first = begin_detached(None, "first section".to_owned());
+		let second = begin_detached(None, "second section".to_owned());
+		let opened = name(&output::captured(), "section_start:");
+
+		end_task_by_id(first, Status::Pass, "first section".to_owned());
+		let closed = name(&output::captured(), "section_end:");
+		end_task_by_id(second, Status::Pass, "second section".to_owned());
+
+		assert!(opened.is_some());
+		assert_eq!(opened, closed);
+	}
+}
@@ -0,0 +1,223 @@
+use std::sync::mpsc::Receiver;
+
+use ratatui::{
+	buffer::Buffer,
+	layout::Rect,
+	style::{Color, Modifier, Style},
+	widgets::Widget,
+};
+
+use crate::{events, output, Event, Status};
+
+/// One row of the tree, which is either a task or a line printed in one.
+#[derive(Debug)]
+struct Row {
+	id: Option<u64>,
+	depth: usize,
+	status: Option<Status>,
+	text: bool,
+	message: String,
+	progress: Option<(u64, u64)>,
+	// the ids of every task this row is nested under, innermost first
+	within: Vec<u64>,
+}
+
+/// The task tree as a [ratatui](https://crates.io/crates/ratatui) widget,
+/// for frontends that draw their own interface and want to show the same
+/// tasks that jeflog would otherwise draw in the terminal.
+///
+/// The tree is built from the same events a [`Sink`](crate::Sink) receives,
+/// starting with the tasks that are already running when it is created.
+/// While any tree exists, jeflog writes nothing to the terminal, which
+/// belongs to the frontend, so one is usually created as the frontend
+/// starts and kept for as long as it runs. The application still holds
+/// the guard returned by [`init`](crate::init), without which no tasks are
+/// shown at all.
+///
+/// Events are only applied by [`update`](Self::update), which is meant to
+/// be called before each frame is drawn, as in
+/// `frame.render_widget(&tree, area)`.
+#[derive(Debug)]
+pub struct TaskTree {
+	events: Receiver<Event>,
+	rows: Vec<Row>,
+}
+
+impl TaskTree {
+	/// Creates a tree of the tasks running now, which follows every
+	/// task from then on.
+	pub fn new() -> Self {
+		output::mute();
+
+		Self {
+			events: events::subscribe(),
+			rows: Vec::new(),
+		}
+	}
+
+	/// Applies every event that has happened since the last update,
+	/// returning whether there were any, so the frame needs redrawing.
+	pub fn update(&mut self) -> bool {
+		let mut changed = false;
+
+		while let Ok(event) = self.events.try_recv() {
+			self.apply(event);
+			changed = true;
+		}
+
+		changed
+	}
+
+	fn apply(&mut self, event: Event) {
+		match event {
+			Event::Start { id, parent, depth, message, .. } => {
+				// a task that is reopened keeps the row it ended on
+				if let Some(row) = self.task(id) {
+					row.status = None;
+					row.message = message;
+					row.progress = None;
+					return;
+				}
+
+				let within = self.within(parent);
+
+				self.insert(Row {
+					id: Some(id),
+					depth,
+					status: None,
+					text: false,
+					message,
+					progress: None,
+					within,
+				});
+			},
+			Event::End { id, status, message, .. } => {
+				if let Some(row) = self.task(id) {
+					row.status = Some(status);
+					row.message = message;
+					row.progress = None;
+				}
+			},
+			Event::Progress { id, current, total, .. } => {
+				if let Some(row) = self.task(id) {
+					row.progress = Some((current, total));
+				}
+			},
+			// a stall is only shown by the warning printed with it, if any
+			Event::Stall { .. } => {},
+			Event::Text { task, text, .. } => {
+				let depth = task
+					.and_then(|id| self.rows.iter().find(|row| row.id == Some(id)))
+					.map_or(0, |row| row.depth + 1);
+
+				let within = self.within(task);
+
+				self.insert(Row {
+					id: None,
+					depth,
+					status: None,
+					text: true,
+					message: text,
+					progress: None,
+					within,
+				});
+			},
+		}
+	}
+
+	fn task(&mut self, id: u64) -> Option<&mut Row> {
+		self.rows.iter_mut().find(|row| row.id == Some(id))
+	}
+
+	/// Returns the ids of every task a row nested under `parent` is within.
+	fn within(&self, parent: Option<u64>) -> Vec<u64> {
+		let Some(parent) = parent else {
+			return Vec::new();
+		};
+
+		let mut within = vec![parent];
+
+		if let Some(row) = self.rows.iter().find(|row| row.id == Some(parent)) {
+			within.extend_from_slice(&row.within);
+		}
+
+		within
+	}
+
+	/// Inserts a row beneath the last row already nested under its
+	/// innermost task, or at the end if it isn't nested under any.
+	fn insert(&mut self, row: Row) {
+		let mut index = self.rows.len();
+
+		if let Some(&parent) = row.within.first() {
+			if let Some(position) = self.rows.iter().position(|other| other.id == Some(parent)) {
+				index = position + 1;
+
+				while index < self.rows.len() && self.rows[index].within.contains(&parent) {
+					index += 1;
+				}
+			}
+		}
+
+		self.rows.insert(index, row);
+	}
+}
+
+impl Default for TaskTree {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Drop for TaskTree {
+	fn drop(&mut self) {
+		output::unmute();
+	}
+}
+
+impl Widget for &TaskTree {
+	fn render(self, area: Rect, buf: &mut Buffer) {
+		// the newest rows are kept in view when there are too many
+		let skip = self.rows.len().saturating_sub(usize::from(area.height));
+
+		for (row, y) in self.rows.iter().skip(skip).zip(area.top()..area.bottom()) {
+			let indent = u16::try_from(row.depth * 5).unwrap_or(u16::MAX);
+
+			if indent >= area.width {
+				continue;
+			}
+
+			let mut x = area.left() + indent;
+			let mut width = usize::from(area.width - indent);
+
+			if !row.text {
+				let (symbol, color) = match row.status {
+					None => ("-", Color::Yellow),
+					Some(Status::Pass) => ("✔", Color::Green),
+					Some(Status::Warn) => ("▲", Color::Yellow),
+					Some(Status::Fail) => ("✘", Color::Red),
+					Some(Status::Cancel) => ("⦸", Color::DarkGray),
+				};
+
+				let style = Style::default().fg(color).add_modifier(Modifier::BOLD);
+				(x, _) = buf.set_stringn(x, y, format!("{symbol} "), width, style);
+				width = usize::from(area.right() - x);
+			}
+
+			let mut message = row.message.clone();
+
+			if let Some((current, total)) = row.progress {
+				let percent = crate::share(current, total, 100);
+				message += &format!(" {percent}%");
+			}
+
+			let style = if row.text {
+				Style::default().fg(Color::Gray)
+			} else {
+				Style::default()
+			};
+
+			buf.set_stringn(x, y, message, width, style);
+		}
+	}
+}
@@ -0,0 +1,56 @@
+use std::{collections::BTreeMap, fmt::Display, sync::RwLock};
+
+use crate::sync::Recover;
+
+static VARS: RwLock<BTreeMap<String, String>> = RwLock::new(BTreeMap::new());
+
+/// Sets a variable that task messages and log lines can refer to as
+/// `{$name}`, such as the host an agent is running on, so that it can be
+/// included everywhere without passing it to every place that logs.
+///
+/// Since messages are format strings, the braces must be doubled when
+/// written directly in a macro, as in `task!("deploying on {{$host}}")`.
+/// Setting a variable again replaces its value.
+pub fn set_var(name: impl Into<String>, value: impl Display) {
+	VARS.write().recover().insert(name.into(), value.to_string());
+}
+
+/// Removes a variable set by [`set_var`], after which references to it
+/// are left as they are written.
+pub fn unset_var(name: &str) {
+	VARS.write().recover().remove(name);
+}
+
+/// Replaces every reference like `{$name}` in `text` with the value of
+/// the variable it names. References to unknown variables are left alone.
+pub(crate) fn substitute(text: &str) -> String {
+	let vars = VARS.read().recover();
+
+	if vars.is_empty() || !text.contains("{$") {
+		return text.to_owned();
+	}
+
+	let mut out = String::with_capacity(text.len());
+	let mut rest = text;
+
+	while let Some(start) = rest.find("{$") {
+		out.push_str(&rest[..start]);
+		rest = &rest[start..];
+
+		let value = rest.find('}').and_then(|end| Some((end, vars.get(&rest[2..end])?)));
+
+		match value {
+			Some((end, value)) => {
+				out.push_str(value);
+				rest = &rest[end + 1..];
+			},
+			None => {
+				out.push_str("{$");
+				rest = &rest[2..];
+			},
+		}
+	}
+
+	out.push_str(rest);
+	out
+}
@@ -0,0 +1,343 @@
+use std::{
+	io::{self, BufRead, BufReader, Read, Write},
+	net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+	thread,
+	time::Duration,
+};
+
+use crate::events;
+
+/// The page served at every path other than `/events`, which draws the
+/// task tree from the events it receives over a WebSocket.
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>jeflog</title>
+<style>
+body { background: #111; color: #ddd; font: 14px monospace; margin: 2em; }
+div { white-space: pre; } .pass { color: #5c5; } .warn { color: #dc3; }
+.fail { color: #e55; } .cancel { color: #888; } .run { color: #dc3; } .text { color: #999; }
+</style>
+</head>
+<body>
+<div id="tree">connecting…</div>
+<script>
+const symbols = { run: "-", pass: "✔", warn: "▲", fail: "✘", cancel: "⦸" };
+const rows = [];
+const byId = new Map();
+const tree = document.getElementById("tree");
+
+function insert(row, after) {
+	// a row goes beneath the last row already nested under its parent
+	let index = rows.length;
+	if (after !== null && byId.has(after)) {
+		index = rows.indexOf(byId.get(after)) + 1;
+		while (index < rows.length && rows[index].within.includes(after)) index++;
+	}
+	rows.splice(index, 0, row);
+}
+
+function render() {
+	tree.replaceChildren(...rows.map(row => {
+		const line = document.createElement("div");
+		line.className = row.kind;
+		let text = " ".repeat(row.depth * 5);
+		if (row.kind !== "text") text += symbols[row.kind] + " ";
+		text += row.message;
+		if (row.progress) text += ` ${row.progress}%`;
+		line.textContent = text;
+		return line;
+	}));
+}
+
+const socket = new WebSocket(`ws://${location.host}/events`);
+socket.onclose = () => tree.append("\n(disconnected)");
+socket.onmessage = ({ data }) => {
+	const event = JSON.parse(data);
+	const task = byId.get(event.id ?? event.task);
+	const within = task ? [task.id, ...task.within] : [];
+	switch (event.event) {
+	case "start": {
+		if (task) {
+			Object.assign(task, { kind: "run", message: event.message, progress: null });
+			break;
+		}
+		const parent = byId.get(event.parent);
+		const row = { id: event.id, kind: "run", depth: event.depth, message: event.message,
+			within: parent ? [parent.id, ...parent.within] : [] };
+		byId.set(event.id, row);
+		insert(row, event.parent);
+		break;
+	}
+	case "end":
+		if (task) Object.assign(task, { kind: event.status, message: event.message, progress: null });
+		break;
+	case "progress":
+		if (task) task.progress = event.total ? Math.floor(event.current * 100 / event.total) : 100;
+		break;
+	case "text":
+		insert({ kind: "text", depth: task ? task.depth + 1 : 0, message: event.text, within }, task ? task.id : null);
+		break;
+	}
+	render();
+};
+</script>
+</body>
+</html>
+"#;
+
+/// Serves a small web page at `address` that shows the live task tree,
+/// for watching a job on a machine without a terminal to watch it on,
+/// such as one started with `nohup`. Returns the address it listens on,
+/// which tells the port chosen when given port 0.
+///
+/// The page reads the same events as a [`Sink`](crate::Sink) would over
+/// a WebSocket at `/events`, which other programs can read as well. Every
+/// browser that connects is first shown the tasks that are running. Other
+/// pages open in the browser are refused the events, since a browser would
+/// otherwise let any of them read the tasks from a local address.
+pub fn serve_web(address: impl ToSocketAddrs) -> io::Result<SocketAddr> {
+	let listener = TcpListener::bind(address)?;
+	let address = listener.local_addr()?;
+
+	thread::spawn(move || {
+		for stream in listener.incoming().flatten() {
+			thread::spawn(move || _ = handle(stream));
+		}
+	});
+
+	Ok(address)
+}
+
+/// The most a request's line and headers may take up, to keep a client
+/// from holding a thread by sending them endlessly.
+const MAX_HEADERS: u64 = 16 * 1024;
+
+/// How long a client may take to send its request, or to read an event,
+/// before it is disconnected.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Answers one connection, with the page or with the stream of events.
+fn handle(mut stream: TcpStream) -> io::Result<()> {
+	stream.set_read_timeout(Some(TIMEOUT))?;
+	stream.set_write_timeout(Some(TIMEOUT))?;
+
+	let mut reader = BufReader::new(stream.try_clone()?.take(MAX_HEADERS));
+	let mut request = String::new();
+	reader.read_line(&mut request)?;
+
+	let mut key = None;
+	let mut host = None;
+	let mut origin = None;
+
+	loop {
+		let mut header = String::new();
+
+		// a request that ends, or grows too long, before its headers do is dropped
+		if reader.read_line(&mut header)? == 0 {
+			return Ok(());
+		}
+
+		if header.trim().is_empty() {
+			break;
+		}
+
+		if let Some((name, value)) = header.split_once(':') {
+			let value = Some(value.trim().to_owned());
+			let name = name.trim();
+
+			if name.eq_ignore_ascii_case("sec-websocket-key") {
+				key = value;
+			} else if name.eq_ignore_ascii_case("host") {
+				host = value;
+			} else if name.eq_ignore_ascii_case("origin") {
+				origin = value;
+			}
+		}
+	}
+
+	let path = request.split(' ').nth(1).unwrap_or("/");
+
+	let Some(key) = key.filter(|_| path == "/events") else {
+		return write!(
+			stream,
+			"HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{PAGE}",
+			PAGE.len(),
+		);
+	};
+
+	// browsers send any page's WebSocket connections to any address, so
+	// only the page served here may read the events, while programs that
+	// aren't browsers send no origin at all
+	if !origin.is_none_or(|origin| same_origin(&origin, host.as_deref())) {
+		return write!(stream, "HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+	}
+
+	write!(
+		stream,
+		"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+		accept(&key),
+	)?;
+
+	for event in events::subscribe() {
+		stream.write_all(&frame(event.to_json().as_bytes()))?;
+	}
+
+	Ok(())
+}
+
+/// Returns whether `origin`, such as `http://localhost:8080`, is the
+/// server named by the `Host` header of the same request.
+fn same_origin(origin: &str, host: Option<&str>) -> bool {
+	let Some(host) = host else {
+		return false;
+	};
+
+	origin.strip_prefix("http://")
+		.or_else(|| origin.strip_prefix("https://"))
+		.is_some_and(|origin| origin.eq_ignore_ascii_case(host))
+}
+
+/// Answers the key of a WebSocket handshake with a hash of it and a fixed
+/// GUID, as RFC 6455 requires.
+fn accept(key: &str) -> String {
+	base64(&sha1(format!("{key}258EAFA5-E914-47DA-95CA-C5AB0DC85B11").as_bytes()))
+}
+
+/// Wraps `payload` in an unmasked WebSocket text frame, as sent by servers.
+fn frame(payload: &[u8]) -> Vec<u8> {
+	let mut frame = vec![0x81];
+
+	match payload.len() {
+		length @ 0..=125 => frame.push(length as u8),
+		length @ 126..=0xffff => {
+			frame.push(126);
+			frame.extend_from_slice(&(length as u16).to_be_bytes());
+		},
+		length => {
+			frame.push(127);
+			frame.extend_from_slice(&(length as u64).to_be_bytes());
+		},
+	}
+
+	frame.extend_from_slice(payload);
+	frame
+}
+
+/// Hashes `data` with SHA-1, which the WebSocket handshake is built on.
+fn sha1(data: &[u8]) -> [u8; 20] {
+	let mut state: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+	let mut message = data.to_vec();
+	message.push(0x80);
+
+	while message.len() % 64 != 56 {
+		message.push(0);
+	}
+
+	message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+	for block in message.chunks_exact(64) {
+		let mut words = [0u32; 80];
+
+		for (index, word) in block.chunks_exact(4).enumerate() {
+			words[index] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+		}
+
+		for index in 16..80 {
+			words[index] = (words[index - 3] ^ words[index - 8] ^ words[index - 14] ^ words[index - 16]).rotate_left(1);
+		}
+
+		let [mut a, mut b, mut c, mut d, mut e] = state;
+
+		for (index, word) in words.iter().enumerate() {
+			let (f, k) = match index {
+				0..=19 => ((b & c) | (!b & d), 0x5a827999),
+				20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+				40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+				_ => (b ^ c ^ d, 0xca62c1d6),
+			};
+
+			let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(*word);
+			e = d;
+			d = c;
+			c = b.rotate_left(30);
+			b = a;
+			a = temp;
+		}
+
+		for (state, value) in state.iter_mut().zip([a, b, c, d, e]) {
+			*state = state.wrapping_add(value);
+		}
+	}
+
+	let mut hash = [0; 20];
+
+	for (chunk, value) in hash.chunks_exact_mut(4).zip(state) {
+		chunk.copy_from_slice(&value.to_be_bytes());
+	}
+
+	hash
+}
+
+/// Encodes `data` in standard, padded base64.
+fn base64(data: &[u8]) -> String {
+	const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+	let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+
+	for chunk in data.chunks(3) {
+		let bits = chunk.iter().enumerate().fold(0u32, |bits, (index, &byte)| bits | u32::from(byte) << (16 - index * 8));
+
+		for index in 0..4 {
+			if index <= chunk.len() {
+				out.push(ALPHABET[(bits >> (18 - index * 6)) as usize & 63] as char);
+			} else {
+				out.push('=');
+			}
+		}
+	}
+
+	out
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn answers_the_handshake_of_rfc_6455() {
+		assert_eq!(accept("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+	}
+
+	#[test]
+	fn hashes_with_sha1() {
+		let hex = |hash: [u8; 20]| hash.iter().map(|byte| format!("{byte:02x}")).collect::<String>();
+
+		assert_eq!(hex(sha1(b"")), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+		assert_eq!(hex(sha1(b"abc")), "a9993e364706816aba3e25717850c26c9cd0d89d");
+		assert_eq!(
+			hex(sha1(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")),
+			"84983e441c3bd26ebaae4aa1f95129e5e54670f1",
+		);
+	}
+
+	#[test]
+	fn encodes_base64_with_padding() {
+		assert_eq!(base64(b""), "");
+		assert_eq!(base64(b"f"), "Zg==");
+		assert_eq!(base64(b"fo"), "Zm8=");
+		assert_eq!(base64(b"foo"), "Zm9v");
+		assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+	}
+
+	#[test]
+	fn only_accepts_its_own_origin() {
+		assert!(same_origin("http://localhost:8080", Some("localhost:8080")));
+		assert!(same_origin("http://LOCALHOST:8080", Some("localhost:8080")));
+		assert!(!same_origin("http://evil.example", Some("localhost:8080")));
+		assert!(!same_origin("http://localhost:8081", Some("localhost:8080")));
+		assert!(!same_origin("null", Some("localhost:8080")));
+		assert!(!same_origin("http://localhost:8080", None));
+	}
+}
@@ -0,0 +1,89 @@
+use std::io::{self, Write};
+
+use crate::{redact, style};
+
+/// A writer that prints each line written to it as part of the task
+/// tree, created by [`stdout`].
+///
+/// Text is held until a full line has been written, and anything left
+/// over is printed when the writer is dropped.
+#[derive(Debug, Default)]
+pub struct Stdout {
+	buffer: Vec<u8>,
+	on_failure: bool,
+	tail: Option<usize>,
+}
+
+/// Returns a writer that can be handed to libraries which expect an
+/// [`io::Write`], so their output doesn't disturb the task tree.
+///
+/// Each line written to it is printed beneath the running tasks, and the
+/// rows of those tasks are shifted to make room for it, so their spinners
+/// keep being drawn on the right lines.
+pub fn stdout() -> Stdout {
+	Stdout::default()
+}
+
+impl Stdout {
+	/// Holds back every line written to this writer until the task it was
+	/// written in ends, and only prints them beneath that task if it fails.
+	/// This keeps the output of commands out of sight unless it's needed.
+	pub fn on_failure(mut self) -> Self {
+		self.on_failure = true;
+		self
+	}
+
+	/// Shows only the last `lines` lines written to this writer, dimmed,
+	/// in a window beneath the task they were written in that is rewritten
+	/// as new lines arrive and erased once the task ends. Along with
+	/// [`on_failure`](Self::on_failure), every line is still printed if
+	/// the task fails.
+	///
+	/// This only applies to the interactive format, as every other format
+	/// prints each line as usual.
+	pub fn tail(mut self, lines: usize) -> Self {
+		self.tail = Some(lines);
+		self
+	}
+
+	fn print(&self, line: &str) {
+		let line = style::sanitize(&redact::apply(line));
+
+		let tailed = self.tail.is_some_and(|lines| crate::tail_line(&line, lines));
+
+		if self.on_failure {
+			crate::hold_line(&line);
+		} else if !tailed {
+			crate::print_line(&line);
+		}
+	}
+}
+
+impl Write for Stdout {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.buffer.extend_from_slice(buf);
+
+		while let Some(newline) = self.buffer.iter().position(|&byte| byte == b'\n') {
+			let line = self.buffer.drain(..=newline).collect::<Vec<_>>();
+			let line = String::from_utf8_lossy(&line[..newline]);
+
+			self.print(line.trim_end_matches('\r'));
+		}
+
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		// lines are printed as soon as they are complete, and a partial
+		// line can't be printed without breaking it in two
+		Ok(())
+	}
+}
+
+impl Drop for Stdout {
+	fn drop(&mut self) {
+		if !self.buffer.is_empty() {
+			self.print(&String::from_utf8_lossy(&self.buffer));
+		}
+	}
+}